@@ -6,7 +6,7 @@
  * This header provides functions to:
  * - Initialize and free the Nudge context
  * - Request command completions with async callbacks
- * - Retrieve error messages
+ * - Retrieve structured edits and error messages
  *
  * Example usage:
  *
@@ -42,6 +42,8 @@
 #ifndef NUDGE_H
 #define NUDGE_H
 
+#include <stddef.h>
+
 #ifdef __cplusplus
 extern "C" {
 #endif
@@ -83,6 +85,22 @@ typedef void (*CompletionCallback)(
     void* user_data
 );
 
+/**
+ * Structured edit of the buffer for the last completion.
+ *
+ * Replacing [replace_start, replace_end) of the buffer with `insert` yields
+ * the suggestion. Offsets are given in bytes and in characters.
+ */
+typedef struct {
+    size_t replace_start;
+    size_t replace_end;
+    size_t replace_start_char;
+    size_t replace_end_char;
+    size_t cursor;       /* Byte offset of the cursor after accepting */
+    size_t cursor_char;  /* Character offset of the cursor after accepting */
+    char* insert;        /* Owned by the caller; release with nudge_free_edit() */
+} NudgeEdit;
+
 /**
  * Initialize a new NudgeContext.
  *
@@ -119,6 +137,29 @@ int nudge_complete(
     void* user_data
 );
 
+/**
+ * Get the structured edit of the last completion.
+ *
+ * @param ctx  NudgeContext handle from nudge_init()
+ * @param out  Receives the edit when one is available
+ * @return     1 if an edit was written, 0 if there is none,
+ *             negative error code on failure
+ *
+ * out->insert is a copy owned by the caller. It stays valid across later
+ * completions (including auto-mode triggers on other threads) and must be
+ * released with nudge_free_edit().
+ */
+int nudge_get_edit(NudgeContext ctx, NudgeEdit* out);
+
+/**
+ * Release the insert text of an edit filled by nudge_get_edit().
+ *
+ * @param edit  Edit to release (may be NULL); edit->insert is set to NULL
+ *
+ * Calling it again on the same edit does nothing.
+ */
+void nudge_free_edit(NudgeEdit* edit);
+
 /**
  * Get the last error message.
  *
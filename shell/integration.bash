@@ -136,6 +136,8 @@ _nudge_parse_list_row() {
     _nudge_row_why="${row%%$'\t'*}"
     row="${row#*$'\t'}"
     _nudge_row_diff="${row%%$'\t'*}"
+    row="${row#*$'\t'}"
+    _nudge_row_cursor="${row%%$'\t'*}"
 }
 
 _nudge_number_candidates() {
@@ -318,7 +320,8 @@ _nudge_popup_complete() {
     fi
 
     READLINE_LINE="$_nudge_row_command"
-    READLINE_POINT=${#READLINE_LINE}
+    # Place the cursor after the inserted text (end of line when not reported).
+    READLINE_POINT=${_nudge_row_cursor:-${#READLINE_LINE}}
     if [[ -n "$_nudge_row_warning" ]]; then
         _nudge_show_warning "$_nudge_row_warning"
     fi
@@ -112,6 +112,29 @@ pub enum Command {
         json: bool,
     },
 
+    /// Render and customize prompt templates
+    Prompt {
+        #[command(subcommand)]
+        action: PromptAction,
+    },
+
+    /// Inspect what leaves this machine
+    Privacy {
+        #[command(subcommand)]
+        action: PrivacyAction,
+    },
+
+    /// Show token usage and budget status
+    Usage {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Number of recent days to list
+        #[arg(long, default_value_t = 7)]
+        days: usize,
+    },
+
     /// Diagnose shell integration health
     Doctor {
         /// Shell target (currently: zsh, bash)
@@ -128,6 +151,50 @@ pub enum Command {
         force: bool,
     },
 
+    /// Suggest commands for a task described in natural language
+    Ask {
+        /// What the command should do
+        query: String,
+
+        /// Current working directory
+        #[arg(long, default_value = ".")]
+        cwd: PathBuf,
+
+        /// Session identifier (e.g., "zsh-12345")
+        #[arg(long, default_value = "ask")]
+        session: String,
+
+        /// Shell mode (zsh-inline, bash-inline, bash-popup, ps-inline, cmd-inline)
+        #[arg(long)]
+        shell_mode: Option<String>,
+
+        /// Output format (default: numbered candidates with explanations)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Explain what a command line does before running it
+    Explain {
+        /// Full command line to explain
+        command: String,
+
+        /// Current working directory
+        #[arg(long, default_value = ".")]
+        cwd: PathBuf,
+
+        /// Session identifier (e.g., "zsh-12345")
+        #[arg(long, default_value = "explain")]
+        session: String,
+
+        /// Shell mode (zsh-inline, bash-inline, bash-popup, ps-inline, cmd-inline)
+        #[arg(long)]
+        shell_mode: Option<String>,
+
+        /// Output format (default: summary, part explanations, side effects and risk)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
     /// Diagnose a failed command and suggest fixes
     Diagnose {
         /// Exit code of the failed command
@@ -160,6 +227,101 @@ pub enum Command {
     },
 }
 
+#[derive(Subcommand)]
+pub enum PromptAction {
+    /// Show the exact prompt sent for a buffer
+    Render {
+        /// Current input buffer content (the failed command with --diagnose)
+        #[arg(long)]
+        buffer: String,
+
+        /// Cursor position within buffer (defaults to the end)
+        #[arg(long)]
+        cursor: Option<usize>,
+
+        /// Current working directory
+        #[arg(long, default_value = ".")]
+        cwd: PathBuf,
+
+        /// Session identifier (e.g., "zsh-12345")
+        #[arg(long, default_value = "context-debug")]
+        session: String,
+
+        /// Shell mode (zsh-auto, zsh-inline, bash-inline, bash-popup, ps-inline, cmd-inline)
+        #[arg(long)]
+        shell_mode: Option<String>,
+
+        /// Exit code of the last executed command (of the failed command with --diagnose)
+        #[arg(long)]
+        last_exit_code: Option<i32>,
+
+        /// Render the diagnosis prompt instead of the completion prompt
+        #[arg(long)]
+        diagnose: bool,
+
+        /// Render the `nudge ask` prompt for --buffer as the task description
+        #[arg(long, conflicts_with = "diagnose")]
+        ask: bool,
+
+        /// Render the `nudge explain` prompt for --buffer as the command line
+        #[arg(long, conflicts_with_all = ["diagnose", "ask"])]
+        explain: bool,
+
+        /// Captured stderr of the failed command (with --diagnose)
+        #[arg(long)]
+        stderr: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Copy the built-in templates into the template directory for editing
+    Init {
+        /// Overwrite templates that already exist
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PrivacyAction {
+    /// Show the completion request sent for a buffer, with each redaction
+    Audit {
+        /// Current input buffer content
+        #[arg(long)]
+        buffer: String,
+
+        /// Cursor position within buffer (defaults to the end)
+        #[arg(long)]
+        cursor: Option<usize>,
+
+        /// Current working directory
+        #[arg(long, default_value = ".")]
+        cwd: PathBuf,
+
+        /// Session identifier (e.g., "zsh-12345")
+        #[arg(long, default_value = "context-debug")]
+        session: String,
+
+        /// Shell mode (zsh-auto, zsh-inline, bash-inline, bash-popup, ps-inline, cmd-inline)
+        #[arg(long)]
+        shell_mode: Option<String>,
+
+        /// Exit code of the last executed command
+        #[arg(long)]
+        last_exit_code: Option<i32>,
+
+        /// Show the prompt before and after sanitization side by side
+        #[arg(long)]
+        diff: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum OutputFormat {
     /// Output only the suggestion text (for shell integration)
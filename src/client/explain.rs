@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use tracing::debug;
+
+use crate::cli::OutputFormat;
+use crate::client::ipc;
+use crate::config::Config;
+use crate::protocol::{ExplainRequest, ExplainResponse};
+
+/// Widest part text that is padded into the explanation column
+const MAX_PART_WIDTH: usize = 24;
+
+/// Send an explain request and print the breakdown
+pub async fn explain(
+    command: String,
+    cwd: PathBuf,
+    session: String,
+    shell_mode: Option<String>,
+    format: Option<OutputFormat>,
+) -> Result<()> {
+    let timeout_ms = Config::load()
+        .map(|config| config.explain.timeout_ms)
+        .unwrap_or_else(|_| Config::default().explain.timeout_ms);
+    let request = ExplainRequest {
+        shell_mode,
+        ..ExplainRequest::new(session, command.clone(), cwd)
+    };
+
+    debug!("Sending explain request");
+    let response = ipc::send_explain_request(&request, timeout_ms).await?;
+
+    match format {
+        Some(OutputFormat::Plain) => print!("{}", build_plain_output(&response)),
+        Some(OutputFormat::List) => print!("{}", build_list_output(&response)),
+        Some(OutputFormat::Json) => println!("{}", serde_json::to_string_pretty(&response)?),
+        None => {
+            print!("{}", build_explain_output(&command, &response));
+            if let Some(error) = &response.error {
+                eprintln!("Explanation unavailable: {}", error.message);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Risk level and summary on one line: `<risk>\t<summary>`
+fn build_plain_output(response: &ExplainResponse) -> String {
+    format!("{}\t{}\n", response.risk.level.as_str(), response.summary)
+}
+
+/// One line per part for popup previews:
+/// `<start>\t<end>\t<text>\t<explanation>` with character offsets
+fn build_list_output(response: &ExplainResponse) -> String {
+    response
+        .parts
+        .iter()
+        .map(|part| {
+            format!(
+                "{}\t{}\t{}\t{}\n",
+                part.char_range.start,
+                part.char_range.end,
+                part.text,
+                part.explanation.as_deref().unwrap_or_default()
+            )
+        })
+        .collect()
+}
+
+/// Summary, aligned part explanations, side effects and risk
+fn build_explain_output(command: &str, response: &ExplainResponse) -> String {
+    let mut out = format!("{}\n\n", command);
+    if !response.summary.is_empty() {
+        out.push_str(&format!("{}\n\n", response.summary));
+    }
+
+    let width = response
+        .parts
+        .iter()
+        .map(|part| part.text.chars().count())
+        .filter(|width| *width <= MAX_PART_WIDTH)
+        .max()
+        .unwrap_or(0);
+    let explained: Vec<_> = response
+        .parts
+        .iter()
+        .filter_map(|part| Some((&part.text, part.explanation.as_deref()?)))
+        .collect();
+    for (text, explanation) in &explained {
+        out.push_str(&format!("  {:<width$}  {}\n", text, explanation));
+    }
+    if !explained.is_empty() {
+        out.push('\n');
+    }
+
+    if !response.side_effects.is_empty() {
+        out.push_str("Side effects:\n");
+        for effect in &response.side_effects {
+            out.push_str(&format!("  - {}\n", effect));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&format!("Risk: {}\n", response.risk.level.as_str()));
+    for reason in &response.risk.reasons {
+        out.push_str(&format!("  - {}\n", reason));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::explain::command_parts;
+    use crate::daemon::safety;
+
+    #[test]
+    fn human_output_aligns_parts_and_lists_risk() {
+        let command = "git push --force origin";
+        let mut parts = command_parts(command);
+        parts[0].explanation = Some("Git CLI".to_string());
+        parts[2].explanation = Some("Overwrite remote history".to_string());
+        let response = ExplainResponse {
+            summary: "Force-pushes the current branch to origin.".to_string(),
+            side_effects: vec!["Replaces the branch on origin".to_string()],
+            ..ExplainResponse::local("id".to_string(), parts, safety::assess(command, &[]))
+        };
+
+        assert_eq!(
+            build_explain_output(command, &response),
+            "git push --force origin\n\n\
+             Force-pushes the current branch to origin.\n\n\
+             \x20 git      Git CLI\n\
+             \x20 --force  Overwrite remote history\n\n\
+             Side effects:\n  - Replaces the branch on origin\n\n\
+             Risk: medium\n  - Rewrites history on the remote\n"
+        );
+        assert_eq!(
+            build_plain_output(&response),
+            "medium\tForce-pushes the current branch to origin.\n"
+        );
+        assert!(build_list_output(&response).starts_with("0\t3\tgit\tGit CLI\n4\t8\tpush\t\n"));
+    }
+}
@@ -13,8 +13,8 @@ use interprocess::local_socket::GenericNamespaced;
 
 use crate::config::Config;
 use crate::protocol::{
-    CompletionRequest, CompletionResponse, DiagnosisRequest, DiagnosisResponse, ErrorCode,
-    ErrorInfo,
+    AskRequest, CompletionRequest, CompletionResponse, DiagnosisRequest, DiagnosisResponse,
+    ErrorCode, ErrorInfo, ExplainRequest, ExplainResponse, StatusRequest, StatusResponse,
 };
 
 /// Connection timeout
@@ -23,6 +23,9 @@ const CONNECT_TIMEOUT_MS: u64 = 1000;
 /// Read timeout
 const READ_TIMEOUT_MS: u64 = 10000;
 
+/// Read timeout for status queries (answered without touching the LLM)
+const STATUS_TIMEOUT_MS: u64 = 1000;
+
 /// Check if daemon process is actually running (not just socket file exists)
 fn is_daemon_alive() -> bool {
     let pid_path = Config::pid_path();
@@ -306,3 +309,80 @@ pub async fn send_diagnosis_request(request: &DiagnosisRequest) -> Result<Diagno
         )),
     }
 }
+
+/// Send a natural-language command request; `timeout_ms` is the daemon's
+/// ask timeout, the read timeout adds a margin for context gathering
+pub async fn send_ask_request(request: &AskRequest, timeout_ms: u64) -> Result<CompletionResponse> {
+    send_typed_request("ask", request, timeout_ms + STATUS_TIMEOUT_MS).await
+}
+
+/// Send a command explanation request; `timeout_ms` is the daemon's explain
+/// timeout, the read timeout adds a margin for context gathering
+pub async fn send_explain_request(
+    request: &ExplainRequest,
+    timeout_ms: u64,
+) -> Result<ExplainResponse> {
+    send_typed_request("explain", request, timeout_ms + STATUS_TIMEOUT_MS).await
+}
+
+/// Query daemon runtime status (LLM backends and circuit breaker state)
+pub async fn send_status_request() -> Result<StatusResponse> {
+    send_typed_request("status", &StatusRequest::default(), STATUS_TIMEOUT_MS).await
+}
+
+/// Send a `{"type": kind, "payload": ...}` request and parse the single-line reply.
+///
+/// Unlike completion/diagnosis there is no in-band error response to fall back
+/// to, so connection problems are returned as errors.
+async fn send_typed_request<Req, Resp>(
+    kind: &str,
+    payload: &Req,
+    read_timeout_ms: u64,
+) -> Result<Resp>
+where
+    Req: serde::Serialize,
+    Resp: serde::de::DeserializeOwned,
+{
+    if !is_daemon_alive() {
+        anyhow::bail!("Daemon is not running. Start it with: nudge start");
+    }
+
+    let socket_path = Config::socket_path();
+    let socket_path_str = socket_path.to_string_lossy().to_string();
+
+    #[cfg(unix)]
+    let name = socket_path_str.as_str().to_fs_name::<GenericFilePath>()?;
+    #[cfg(windows)]
+    let name = socket_path_str.as_str().to_ns_name::<GenericNamespaced>()?;
+
+    let stream = timeout(
+        Duration::from_millis(CONNECT_TIMEOUT_MS),
+        Stream::connect(name),
+    )
+    .await
+    .context("Connection to daemon timed out")?
+    .context("Failed to connect to daemon")?;
+
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+
+    let wrapped = serde_json::json!({
+        "type": kind,
+        "payload": payload
+    });
+    let request_json = serde_json::to_string(&wrapped)?;
+    writer.write_all(request_json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+
+    let mut response_line = String::new();
+    timeout(
+        Duration::from_millis(read_timeout_ms),
+        reader.read_line(&mut response_line),
+    )
+    .await
+    .context("Daemon did not respond in time")?
+    .context("Failed to read daemon response")?;
+
+    serde_json::from_str(&response_line).context("Failed to parse daemon response")
+}
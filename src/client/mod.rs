@@ -1,7 +1,9 @@
 mod diagnosis;
+mod explain;
 pub mod ipc;
 
 pub use diagnosis::diagnose;
+pub use explain::explain;
 
 use std::path::PathBuf;
 
@@ -9,7 +11,8 @@ use anyhow::Result;
 use tracing::debug;
 
 use crate::cli::OutputFormat;
-use crate::protocol::{CompletionRequest, CompletionResponse};
+use crate::config::Config;
+use crate::protocol::{AskRequest, CompletionRequest, CompletionResponse};
 
 const PLAIN_WARNING_PREFIX: &str = "NUDGE_WARNING:";
 const LIST_RISK_HIGH: &str = "high";
@@ -57,6 +60,66 @@ pub async fn complete(
     Ok(())
 }
 
+/// Ask for commands performing a task description; without a format the
+/// candidates are listed for reading
+pub async fn ask(
+    query: String,
+    cwd: PathBuf,
+    session: String,
+    shell_mode: Option<String>,
+    format: Option<OutputFormat>,
+) -> Result<()> {
+    let timeout_ms = Config::load()
+        .map(|config| config.ask.timeout_ms)
+        .unwrap_or_else(|_| Config::default().ask.timeout_ms);
+    let request = AskRequest {
+        shell_mode,
+        ..AskRequest::new(session, query, cwd)
+    };
+
+    debug!("Sending ask request");
+    let response = ipc::send_ask_request(&request, timeout_ms).await?;
+
+    match format {
+        Some(OutputFormat::Plain) => output_plain(&response),
+        Some(OutputFormat::List) => output_list(&response, ""),
+        Some(OutputFormat::Json) => output_json(&response)?,
+        None => {
+            if let Some(error) = &response.error {
+                anyhow::bail!("{}", error.message);
+            }
+            print!("{}", build_ask_output(&response));
+        }
+    }
+
+    Ok(())
+}
+
+/// Numbered candidates with their explanation and safety warning
+fn build_ask_output(response: &CompletionResponse) -> String {
+    if response.suggestions.is_empty() {
+        return "No command found for this request.\n".to_string();
+    }
+
+    let mut out = String::new();
+    for (idx, suggestion) in response.suggestions.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", idx + 1, suggestion.text));
+        for line in [&suggestion.summary_short, &suggestion.reason_short]
+            .into_iter()
+            .flatten()
+        {
+            out.push_str(&format!("   {}\n", line));
+        }
+        if let Some(warning) = &suggestion.warning {
+            out.push_str(&format!("   warning: {}\n", warning.message));
+        }
+        if let Some(validation) = suggestion.validation.as_ref().filter(|v| !v.valid) {
+            out.push_str(&format!("   invalid: {}\n", validation.issues.join("; ")));
+        }
+    }
+    out
+}
+
 /// Output plain text (just the suggestion)
 fn output_plain(response: &CompletionResponse) {
     if let Some(text) = build_plain_output(response) {
@@ -76,7 +139,9 @@ fn build_plain_output(response: &CompletionResponse) -> Option<String> {
 }
 
 /// Output tab-separated list for popup selectors.
-/// Format per line: `<risk>\t<command>\t<warning>\t<why>\t<diff>`
+/// Format per line: `<risk>\t<command>\t<warning>\t<why>\t<diff>\t<cursor>\t<placeholders>`
+/// where `<cursor>` is the character offset to place the cursor at after accepting and
+/// `<placeholders>` lists tab stops as comma-separated `name:start:end` character ranges.
 fn output_list(response: &CompletionResponse, buffer: &str) {
     if let Some(text) = build_list_output(response, buffer) {
         print!("{}", text);
@@ -96,7 +161,13 @@ fn build_list_output(response: &CompletionResponse, buffer: &str) -> Option<Stri
             LIST_RISK_LOW
         };
         let why = build_why(buffer, suggestion);
-        let diff = build_diff(buffer, &suggestion.text);
+        let diff = build_diff(buffer, suggestion);
+        let cursor = suggestion
+            .edit
+            .as_ref()
+            .and_then(|edit| edit.cursor)
+            .map(|cursor| cursor.char.to_string())
+            .unwrap_or_default();
         let warning = suggestion
             .warning
             .as_ref()
@@ -112,6 +183,10 @@ fn build_list_output(response: &CompletionResponse, buffer: &str) -> Option<Stri
         out.push_str(&sanitize_list_field(&why));
         out.push('\t');
         out.push_str(&sanitize_list_field(&diff));
+        out.push('\t');
+        out.push_str(&cursor);
+        out.push('\t');
+        out.push_str(&build_placeholders(suggestion));
         out.push('\n');
     }
 
@@ -144,7 +219,24 @@ fn build_why(buffer: &str, suggestion: &crate::protocol::Suggestion) -> String {
     "context rewrite".to_string()
 }
 
-fn build_diff(buffer: &str, suggestion: &str) -> String {
+fn build_diff(buffer: &str, suggestion: &crate::protocol::Suggestion) -> String {
+    if let Some(edit) = &suggestion.edit {
+        if edit.range.end < buffer.len() {
+            let replaced = buffer
+                .get(edit.range.start..edit.range.end)
+                .unwrap_or_default();
+            return if replaced.is_empty() {
+                format!("+{} @{}", edit.insert, edit.char_range.start)
+            } else {
+                format!(
+                    "~ {} -> {} @{}",
+                    replaced, edit.insert, edit.char_range.start
+                )
+            };
+        }
+    }
+
+    let suggestion = suggestion.text.as_str();
     if let Some(tail) = suggestion.strip_prefix(buffer) {
         if tail.is_empty() {
             "+<none>".to_string()
@@ -156,6 +248,15 @@ fn build_diff(buffer: &str, suggestion: &str) -> String {
     }
 }
 
+fn build_placeholders(suggestion: &crate::protocol::Suggestion) -> String {
+    suggestion
+        .placeholders
+        .iter()
+        .map(|p| format!("{}:{}:{}", p.name, p.char_range.start, p.char_range.end))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 /// Output full JSON response
 fn output_json(response: &CompletionResponse) -> Result<()> {
     let json = serde_json::to_string_pretty(response)?;
@@ -165,8 +266,10 @@ fn output_json(response: &CompletionResponse) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{build_list_output, build_plain_output};
-    use crate::protocol::{CompletionResponse, Suggestion, Warning};
+    use super::{build_ask_output, build_list_output, build_plain_output};
+    use crate::protocol::{
+        CompletionResponse, Placeholder, Suggestion, SuggestionEdit, TextRange, Validation, Warning,
+    };
 
     #[test]
     fn test_plain_output_emits_warning_sentinel() {
@@ -249,4 +352,69 @@ mod tests {
         let cols: Vec<&str> = output.lines().next().unwrap().split('\t').collect();
         assert_eq!(cols[3], "matches typed prefix");
     }
+
+    #[test]
+    fn test_list_output_describes_mid_line_edit_and_cursor() {
+        let buffer = "git  main";
+        let mut suggestion = Suggestion::new("git push main".to_string());
+        suggestion.edit = SuggestionEdit::between(buffer, &suggestion.text, 4);
+        let response = CompletionResponse::success("req-4".to_string(), vec![suggestion], 0);
+
+        let output = build_list_output(&response, buffer).unwrap();
+        let cols: Vec<&str> = output.lines().next().unwrap().split('\t').collect();
+        assert_eq!(cols[4], "+push @4");
+        assert_eq!(cols[5], "8");
+    }
+
+    #[test]
+    fn test_list_output_lists_placeholders_as_char_ranges() {
+        let suggestion =
+            Suggestion::new("git commit -m \"msg\"".to_string()).with_placeholders(vec![
+                Placeholder {
+                    name: "message".to_string(),
+                    default: Some("msg".to_string()),
+                    range: TextRange { start: 15, end: 18 },
+                    char_range: TextRange { start: 15, end: 18 },
+                },
+            ]);
+        let response = CompletionResponse::success("req-5".to_string(), vec![suggestion], 0);
+
+        let output = build_list_output(&response, "git commit").unwrap();
+        let cols: Vec<&str> = output.lines().next().unwrap().split('\t').collect();
+        assert_eq!(cols.len(), 7);
+        assert_eq!(cols[6], "message:15:18");
+    }
+
+    #[test]
+    fn test_ask_output_numbers_candidates_with_explanations() {
+        let response = CompletionResponse::success(
+            "req-6".to_string(),
+            vec![
+                Suggestion::new("find . -size +100M -mtime -7".to_string())
+                    .with_summary_short("List files over 100 MB changed this week"),
+                Suggestion::new("rm -rf ./cache".to_string())
+                    .with_warning(Warning::dangerous("recursive delete")),
+                Suggestion {
+                    validation: Some(Validation {
+                        valid: false,
+                        issues: vec!["unknown command 'fnd'".to_string()],
+                    }),
+                    ..Suggestion::new("fnd . -big".to_string())
+                },
+            ],
+            0,
+        );
+
+        assert_eq!(
+            build_ask_output(&response),
+            "1. find . -size +100M -mtime -7\n   List files over 100 MB changed this week\n\
+             2. rm -rf ./cache\n   warning: recursive delete\n\
+             3. fnd . -big\n   invalid: unknown command 'fnd'\n"
+        );
+        let empty = CompletionResponse::success("req-7".to_string(), Vec::new(), 0);
+        assert_eq!(
+            build_ask_output(&empty),
+            "No command found for this request.\n"
+        );
+    }
 }
@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use serde::Serialize;
 
 use crate::config::Config;
+use crate::daemon::policy::AppliedPolicy;
 use crate::daemon::{context, sanitizer};
 use crate::protocol::CompletionRequest;
 
@@ -13,8 +15,12 @@ struct ContextOutput {
     session: String,
     cwd: PathBuf,
     last_exit_code: Option<i32>,
+    /// Privacy policy for `cwd`
+    policy: AppliedPolicy,
     sanitize_enabled: bool,
     sanitized_count: usize,
+    /// Redacted items per field
+    sanitized_fields: BTreeMap<String, usize>,
     context: context::ContextData,
 }
 
@@ -39,21 +45,25 @@ pub async fn run_context(
     let params = context::GatherParams::from(&request);
     let gathered_context = context::gather(&params, &config).await?;
 
-    let (effective_context, sanitized_count) = if config.privacy.sanitize_enabled {
-        let (sanitized_context, events) =
-            sanitizer::sanitize(&gathered_context, &config.privacy.custom_patterns);
-        (sanitized_context, events.len())
+    let (effective_context, buffer, events) = if config.privacy.sanitize_enabled {
+        let privacy = &config.privacy;
+        let (sanitized_context, mut events) = sanitizer::sanitize(&gathered_context, privacy);
+        let (buffer, buffer_events) = sanitizer::sanitize_field("buffer", &buffer, privacy);
+        events.extend(buffer_events);
+        (sanitized_context, buffer, events)
     } else {
-        (gathered_context, 0)
+        (gathered_context, buffer, Vec::new())
     };
 
     let output = ContextOutput {
+        policy: AppliedPolicy::resolve(&config.privacy, &cwd),
         buffer,
         session,
         cwd,
         last_exit_code,
         sanitize_enabled: config.privacy.sanitize_enabled,
-        sanitized_count,
+        sanitized_count: events.len(),
+        sanitized_fields: sanitizer::field_counts(&events),
         context: effective_context,
     };
 
@@ -66,8 +76,17 @@ pub async fn run_context(
         println!("session: {}", output.session);
         println!("cwd: {}", output.cwd.display());
         println!("last_exit_code: {:?}", output.last_exit_code);
+        println!("policy: {}", output.policy.describe());
         println!("sanitize_enabled: {}", output.sanitize_enabled);
         println!("sanitized_count: {}", output.sanitized_count);
+        if !output.sanitized_fields.is_empty() {
+            let fields: Vec<String> = output
+                .sanitized_fields
+                .iter()
+                .map(|(field, count)| format!("{}={}", field, count))
+                .collect();
+            println!("sanitized_fields: {}", fields.join(", "));
+        }
         println!();
         println!("{}", serde_json::to_string_pretty(&output.context)?);
     }
@@ -8,6 +8,7 @@ use tokio::time::sleep;
 
 use crate::client::ipc;
 use crate::config::{Config, TriggerMode, ZshGhostOwner, ZshOverlayBackend};
+use crate::daemon::llm::{api_key, provider};
 use crate::paths::AppPaths;
 use crate::protocol::CompletionRequest;
 
@@ -51,6 +52,8 @@ async fn run_zsh_doctor() -> Result<()> {
     println!("diagnosis.enabled: {}", config.diagnosis.enabled);
     println!();
 
+    print_llm_checks(&config).await;
+
     println!("Checks");
     println!("------");
     let zsh_version =
@@ -182,6 +185,8 @@ async fn run_bash_doctor() -> Result<()> {
     println!("diagnosis.enabled: {}", config.diagnosis.enabled);
     println!();
 
+    print_llm_checks(&config).await;
+
     println!("Checks");
     println!("------");
     let bash_version =
@@ -290,6 +295,53 @@ async fn run_bash_doctor() -> Result<()> {
     Ok(())
 }
 
+/// Report each backend, where its API key comes from (without printing the
+/// key) and whether it can be reached through the configured transport.
+async fn print_llm_checks(config: &Config) {
+    println!("LLM");
+    println!("---");
+    if let Err(e) = config.validate_llm_config() {
+        println!("[warn] {:#}", e);
+    }
+    let http = &config.model.http;
+    if let Some(proxy) = &http.proxy {
+        println!("[info] proxy: {}", provider::redact_url(proxy));
+    }
+    let clients = match provider::HttpClients::new(http) {
+        Ok(clients) => Some(clients),
+        Err(e) => {
+            println!("[warn] http client: {:#}", e);
+            None
+        }
+    };
+
+    for backend in config.model.backends() {
+        println!(
+            "backend: {} {} @ {}",
+            backend.provider.as_str(),
+            backend.model_name,
+            backend.endpoint
+        );
+        match (backend.api_key_source(), api_key::resolve(&backend).await) {
+            (None, _) => println!("[info] api key: not configured"),
+            (Some(source), Ok(_)) => println!("[ok] api key: {}", source),
+            (Some(source), Err(e)) => println!("[warn] api key: {} ({:#})", source, e),
+        }
+        if let Some(clients) = &clients {
+            let client = clients.for_endpoint(&backend.endpoint);
+            match provider::check_connectivity(&client, &backend.endpoint, http).await {
+                Ok(reply) => println!(
+                    "[ok] connectivity: HTTP {} in {} ms",
+                    reply.status,
+                    reply.elapsed.as_millis()
+                ),
+                Err(e) => println!("[warn] connectivity: {}", e),
+            }
+        }
+    }
+    println!();
+}
+
 fn probe_zsh_bindings(integration_script: &Path) -> Result<HashMap<String, String>> {
     let exe = std::env::current_exe().context("Failed to resolve current executable path")?;
     let exe_q = shell_quote(exe.to_string_lossy().as_ref());
@@ -1,5 +1,7 @@
+use super::usage::UsageSummary;
 use crate::config::{Config, Platform, TriggerMode, ZshGhostOwner, ZshOverlayBackend};
 use crate::paths::AppPaths;
+use crate::protocol::BackendStatus;
 use anyhow::Result;
 use serde::Serialize;
 use std::path::PathBuf;
@@ -22,12 +24,22 @@ pub struct InfoOutput {
     pub auto_delay_ms: u64,
     pub zsh_ghost_owner: String,
     pub zsh_overlay_backend: String,
+    // History predictor mode (off, fallback, fast-first, only)
+    pub predictor_mode: String,
     // Diagnosis configuration
     pub diagnosis_enabled: bool,
+    // LLM backends reported by the running daemon (fallback order)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backends: Option<Vec<BackendStatus>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_backend: Option<String>,
+    // Token usage from the local ledger (omitted when usage.enabled is false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageSummary>,
 }
 
 /// Run the info command
-pub fn run_info(json: bool, field: Option<String>) -> Result<()> {
+pub async fn run_info(json: bool, field: Option<String>) -> Result<()> {
     let platform = Platform::detect()?;
     let config_dir = AppPaths::root_dir();
     let config_file = Config::default_config_path();
@@ -44,6 +56,18 @@ pub fn run_info(json: bool, field: Option<String>) -> Result<()> {
     // Check daemon status
     let daemon_status = check_daemon_status();
 
+    // Ask the running daemon for live backend/circuit state
+    let backend_status = if daemon_status == "Running" {
+        crate::client::ipc::send_status_request().await.ok()
+    } else {
+        None
+    };
+    let active_backend = backend_status
+        .as_ref()
+        .and_then(|status| status.active_backend())
+        .map(|backend| format!("{}:{}", backend.provider, backend.model_name));
+    let backends = backend_status.map(|status| status.backends);
+
     // Load config for trigger settings
     let config = Config::load().unwrap_or_default();
     let trigger_mode = match config.trigger.mode {
@@ -77,7 +101,11 @@ pub fn run_info(json: bool, field: Option<String>) -> Result<()> {
         auto_delay_ms,
         zsh_ghost_owner,
         zsh_overlay_backend,
+        predictor_mode: config.predictor.mode.as_str().to_string(),
         diagnosis_enabled: config.diagnosis.enabled,
+        backends,
+        active_backend,
+        usage: config.usage.enabled.then(|| UsageSummary::load(&config)),
     };
 
     if let Some(field_name) = field {
@@ -101,8 +129,22 @@ pub fn run_info(json: bool, field: Option<String>) -> Result<()> {
             "auto_delay_ms" => info.auto_delay_ms.to_string(),
             "zsh_ghost_owner" => info.zsh_ghost_owner.clone(),
             "zsh_overlay_backend" => info.zsh_overlay_backend.clone(),
+            "predictor_mode" => info.predictor_mode.clone(),
             "diagnosis_enabled" => info.diagnosis_enabled.to_string(),
             "interactive_commands" => config.diagnosis.interactive_commands.join(","),
+            "active_backend" => info
+                .active_backend
+                .clone()
+                .unwrap_or_else(|| "N/A".to_string()),
+            "usage_today_tokens" => usage_field(&info, |u| u.today.total_tokens()),
+            "usage_today_requests" => usage_field(&info, |u| u.today.requests),
+            "usage_month_tokens" => usage_field(&info, |u| u.month.total_tokens()),
+            "usage_month_requests" => usage_field(&info, |u| u.month.requests),
+            "budget_exceeded" => info
+                .usage
+                .as_ref()
+                .and_then(|u| u.budget_exceeded.clone())
+                .unwrap_or_else(|| "false".to_string()),
             _ => anyhow::bail!("Unknown field: {}", field_name),
         };
         println!("{}", value);
@@ -140,15 +182,49 @@ pub fn run_info(json: bool, field: Option<String>) -> Result<()> {
         println!("Auto Delay:           {}ms", info.auto_delay_ms);
         println!("Zsh Ghost Owner:      {}", info.zsh_ghost_owner);
         println!("Zsh Overlay Backend:  {}", info.zsh_overlay_backend);
+        println!("History Predictor:    {}", info.predictor_mode);
         println!();
         println!("Diagnosis Configuration");
         println!("-----------------------");
         println!("Enabled:              {}", info.diagnosis_enabled);
+        if let Some(ref backends) = info.backends {
+            println!();
+            println!("LLM Backends (* = active)");
+            println!("-------------------------");
+            for backend in backends {
+                println!("{}", backend.summary());
+            }
+        }
+        if let Some(ref usage) = info.usage {
+            println!();
+            println!("Token Usage");
+            println!("-----------");
+            println!(
+                "Today:                {} requests, {} tokens",
+                usage.today.requests,
+                usage.today.total_tokens()
+            );
+            println!(
+                "This Month:           {} requests, {} tokens",
+                usage.month.requests,
+                usage.month.total_tokens()
+            );
+            if let Some(ref reason) = usage.budget_exceeded {
+                println!("Budget:               exceeded ({})", reason);
+            }
+        }
     }
 
     Ok(())
 }
 
+fn usage_field(info: &InfoOutput, value: impl Fn(&UsageSummary) -> u64) -> String {
+    info.usage
+        .as_ref()
+        .map(|usage| value(usage).to_string())
+        .unwrap_or_else(|| "N/A".to_string())
+}
+
 /// Check daemon status using shared runtime health check.
 fn check_daemon_status() -> String {
     if crate::daemon::check_status().is_ok() {
@@ -1,4 +1,7 @@
 pub mod context;
 pub mod doctor;
 pub mod info;
+pub mod privacy;
+pub mod prompt;
 pub mod setup;
+pub mod usage;
@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use crate::config::{Config, PredictorMode};
+use crate::daemon::llm::provider;
+use crate::daemon::llm::router::ModelRouter;
+use crate::daemon::llm::LlmRequest;
+use crate::daemon::policy::AppliedPolicy;
+use crate::daemon::sanitizer;
+use crate::daemon::server;
+use crate::daemon::shell_mode::ShellMode;
+use crate::protocol::CompletionRequest;
+
+#[derive(Debug, Serialize)]
+struct AuditOutput {
+    shell_mode: &'static str,
+    policy: AppliedPolicy,
+    sanitize_enabled: bool,
+    reversible_redaction: bool,
+    /// Why no model request would be made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refused: Option<String>,
+    /// The request that would be sent, unless it is refused
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    request: Option<AuditRequest>,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditRequest {
+    /// `ask` when the buffer starts with `ask.prefix`
+    kind: &'static str,
+    profile: String,
+    model: String,
+    /// Endpoints the request may go to, in the order they are tried
+    endpoints: Vec<String>,
+    /// Proxy for non-loopback endpoints (`model.http.proxy` or the
+    /// environment); loopback endpoints always connect directly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy: Option<String>,
+    fill_in_middle: bool,
+    /// Redactions in the context fields and the buffer
+    redactions: Vec<Redaction>,
+    /// Redactions made by the last pass over the rendered prompt
+    prompt_redactions: usize,
+    system: String,
+    user: String,
+    /// Text around the cursor sent to native fill-in-the-middle endpoints
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fim: Option<FimText>,
+    /// The same prompt rendered without sanitization (with --diff)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unsanitized: Option<PromptText>,
+}
+
+#[derive(Debug, Serialize)]
+struct Redaction {
+    field: String,
+    pattern: String,
+    replacement: String,
+    original_length: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct FimText {
+    prefix: String,
+    suffix: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PromptText {
+    system: String,
+    user: String,
+}
+
+/// Options of `nudge privacy audit`
+pub struct AuditOptions {
+    pub buffer: String,
+    pub cursor: Option<usize>,
+    pub cwd: PathBuf,
+    pub session: String,
+    pub shell_mode: Option<String>,
+    pub last_exit_code: Option<i32>,
+    pub diff: bool,
+    pub json: bool,
+}
+
+pub async fn run_privacy_audit(options: AuditOptions) -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let shell_mode = ShellMode::resolve(options.shell_mode.as_deref(), &options.session);
+    let router = ModelRouter::from_config(&config.model)?.with_usage(&config)?;
+    let request = CompletionRequest::new(
+        options.session.clone(),
+        options.buffer.clone(),
+        options.cursor.unwrap_or(options.buffer.len()),
+        options.cwd.clone(),
+        options.last_exit_code,
+    );
+
+    let output = AuditOutput {
+        shell_mode: shell_mode.as_str(),
+        policy: AppliedPolicy::resolve(&config.privacy, &options.cwd),
+        sanitize_enabled: config.privacy.sanitize_enabled,
+        reversible_redaction: config.privacy.reversible_redaction,
+        refused: None,
+        request: None,
+    };
+    let output = match audit_request(&request, &config, shell_mode, &router, options.diff).await {
+        Ok(audited) => AuditOutput {
+            request: Some(audited),
+            ..output
+        },
+        Err(reason) => AuditOutput {
+            refused: Some(reason),
+            ..output
+        },
+    };
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("Nudge Privacy Audit");
+    println!("===================");
+    println!("{:<16}{}", "Shell mode:", output.shell_mode);
+    if let Some(audited) = &output.request {
+        println!("{:<16}{}", "Request:", audited.kind);
+        println!("{:<16}{} ({})", "Profile:", audited.profile, audited.model);
+        println!("{:<16}{}", "Endpoints:", audited.endpoints.join(", "));
+        println!(
+            "{:<16}{}",
+            "Proxy:",
+            match &audited.proxy {
+                Some(proxy) => format!("{} (loopback endpoints connect directly)", proxy),
+                None => "none".to_string(),
+            }
+        );
+    }
+    println!("{:<16}{}", "Policy:", output.policy.describe());
+    println!(
+        "{:<16}{}",
+        "Sanitization:",
+        match (output.sanitize_enabled, output.reversible_redaction) {
+            (false, _) => "off",
+            (true, true) => "on (placeholders)",
+            (true, false) => "on (labels)",
+        }
+    );
+    let audited = match (&output.refused, &output.request) {
+        (None, Some(audited)) => audited,
+        (reason, _) => {
+            println!(
+                "{:<16}nothing, {}",
+                "Sent:",
+                reason.as_deref().unwrap_or_default()
+            );
+            return Ok(());
+        }
+    };
+    if audited.fill_in_middle {
+        println!(
+            "{:<16}the prompt below, or prefix and suffix to native FIM endpoints",
+            "Sent:"
+        );
+    } else {
+        println!("{:<16}the prompt below", "Sent:");
+    }
+    println!(
+        "{:<16}{}",
+        "Redactions:",
+        audited.redactions.len() + audited.prompt_redactions
+    );
+    for redaction in &audited.redactions {
+        println!(
+            "  {:<24}{:<18}{} ({} chars)",
+            redaction.replacement, redaction.pattern, redaction.field, redaction.original_length
+        );
+    }
+    if audited.prompt_redactions > 0 {
+        println!(
+            "  {} more in the rendered prompt (templates or system prompt)",
+            audited.prompt_redactions
+        );
+    }
+
+    if let Some(unsanitized) = &audited.unsanitized {
+        let width = crossterm::terminal::size()
+            .map(|(columns, _)| columns as usize)
+            .unwrap_or(160);
+        println!();
+        println!("--- system: before | after sanitization ---");
+        print_side_by_side(&unsanitized.system, &audited.system, width);
+        println!();
+        println!("--- user: before | after sanitization ---");
+        print_side_by_side(&unsanitized.user, &audited.user, width);
+        return Ok(());
+    }
+
+    // Mark redactions on a terminal; piped output stays byte-exact.
+    let names = if std::io::stdout().is_terminal() {
+        Some(replacement_names(&audited.redactions))
+    } else {
+        None
+    };
+    let show = |text: &str| match &names {
+        Some(names) => highlight(text, names),
+        None => text.to_string(),
+    };
+    println!();
+    println!("--- system ---");
+    println!("{}", show(&audited.system));
+    println!();
+    println!("--- user ---");
+    println!("{}", show(&audited.user));
+    if let Some(fim) = &audited.fim {
+        println!();
+        println!("--- fim prefix ---");
+        println!("{}", show(&fim.prefix));
+        println!();
+        println!("--- fim suffix ---");
+        println!("{}", show(&fim.suffix));
+    }
+
+    Ok(())
+}
+
+/// Plan `request` through the daemon's own completion path; the error is
+/// why nothing would be sent.
+async fn audit_request(
+    request: &CompletionRequest,
+    config: &Config,
+    shell_mode: ShellMode,
+    router: &ModelRouter,
+    diff: bool,
+) -> Result<AuditRequest, String> {
+    let asking = server::ask_query(&request.buffer, shell_mode, config).is_some();
+    if !asking && config.predictor.mode == PredictorMode::Only {
+        return Err("predictor.mode is only: completions come from history alone".to_string());
+    }
+    let planned = server::plan_completion(request, config, shell_mode, router)
+        .await
+        .map_err(|error| error.into_info().message)?;
+
+    let unsanitized = if diff {
+        let mut raw_config = config.clone();
+        raw_config.privacy.sanitize_enabled = false;
+        server::plan_completion(request, &raw_config, shell_mode, router)
+            .await
+            .ok()
+            .map(|raw| {
+                let chat = raw.prepared.request.chat();
+                PromptText {
+                    system: chat.system.clone(),
+                    user: chat.user.clone(),
+                }
+            })
+    } else {
+        None
+    };
+
+    let route = &planned.route;
+    let chat = planned.prepared.request.chat();
+    Ok(AuditRequest {
+        kind: planned.kind.as_str(),
+        profile: route.profile.to_string(),
+        model: route.provider.model().model_name.clone(),
+        endpoints: route.provider.endpoints(),
+        proxy: route
+            .provider
+            .proxy()
+            .or_else(env_proxy)
+            .map(|proxy| provider::redact_url(&proxy)),
+        fill_in_middle: matches!(planned.prepared.request, LlmRequest::Fim(_)),
+        redactions: planned
+            .redactor
+            .events
+            .iter()
+            .map(|event| Redaction {
+                field: event.field.clone(),
+                pattern: sanitizer::pattern_name(&event.pattern_type).to_string(),
+                replacement: event.replacement.clone(),
+                original_length: event.original_length,
+            })
+            .collect(),
+        prompt_redactions: planned
+            .prepared
+            .summary
+            .sanitized_fields
+            .as_ref()
+            .and_then(|fields| fields.get("prompt"))
+            .copied()
+            .unwrap_or_default(),
+        system: chat.system.clone(),
+        user: chat.user.clone(),
+        fim: match &planned.prepared.request {
+            LlmRequest::Fim(fim) => Some(FimText {
+                prefix: fim.prefix.clone(),
+                suffix: fim.suffix.clone(),
+            }),
+            LlmRequest::Chat(_) => None,
+        },
+        unsanitized,
+    })
+}
+
+/// Proxy reqwest picks up from the environment when `model.http.proxy` is unset
+fn env_proxy() -> Option<String> {
+    [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ]
+    .iter()
+    .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+/// Pattern names behind each replacement text
+fn replacement_names(redactions: &[Redaction]) -> BTreeMap<&str, Vec<&str>> {
+    let mut names: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for redaction in redactions {
+        let entry = names.entry(redaction.replacement.as_str()).or_default();
+        if !entry.contains(&redaction.pattern.as_str()) {
+            entry.push(&redaction.pattern);
+        }
+    }
+    names
+}
+
+/// `text` with each placeholder or label highlighted and followed by the
+/// name of the pattern that produced it
+fn highlight(text: &str, names: &BTreeMap<&str, Vec<&str>>) -> String {
+    let mut known: Vec<&str> = names.keys().copied().collect();
+    known.sort_by_key(|replacement| std::cmp::Reverse(replacement.len()));
+    let mut alternatives: Vec<String> = known.into_iter().map(regex::escape).collect();
+    // Labels from the last pass over the rendered prompt
+    alternatives.push(r"\[REDACTED:[a-z_]+\]".to_string());
+    let Ok(pattern) = Regex::new(&alternatives.join("|")) else {
+        return text.to_string();
+    };
+    pattern
+        .replace_all(text, |caps: &Captures| {
+            let matched = &caps[0];
+            let name = names
+                .get(matched)
+                .map(|names| names.join(", "))
+                .unwrap_or_else(|| sanitizer::pattern_name(matched).to_string());
+            format!("\x1b[1;33m{}\x1b[0m\x1b[2m({})\x1b[0m", matched, name)
+        })
+        .into_owned()
+}
+
+/// Print a line diff of `before` and `after` in two columns, marking changed
+/// rows with `|` and rows present on one side only with `<` or `>`
+fn print_side_by_side(before: &str, after: &str, width: usize) {
+    let column = (width.saturating_sub(3) / 2).max(20);
+    for (left, marker, right) in align_lines(before, after) {
+        let left: Vec<char> = left.chars().collect();
+        let right: Vec<char> = right.chars().collect();
+        let left_rows = left.chunks(column).count().max(1);
+        let right_rows = right.chunks(column).count().max(1);
+        for row in 0..left_rows.max(right_rows) {
+            let chunk = |chars: &[char]| -> String {
+                chars
+                    .chunks(column)
+                    .nth(row)
+                    .map(|chunk| chunk.iter().collect())
+                    .unwrap_or_default()
+            };
+            let marker = if row == 0 { marker } else { ' ' };
+            let line = format!(
+                "{:<column$} {} {}",
+                chunk(&left),
+                marker,
+                chunk(&right),
+                column = column
+            );
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+/// Rows of a side-by-side diff from the longest common subsequence of lines
+fn align_lines<'a>(before: &'a str, after: &'a str) -> Vec<(&'a str, char, &'a str)> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    // common[i][j]: length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |rows: &mut Vec<(&'a str, char, &'a str)>,
+                 removed: &mut Vec<&'a str>,
+                 added: &mut Vec<&'a str>| {
+        for k in 0..removed.len().max(added.len()) {
+            match (removed.get(k), added.get(k)) {
+                (Some(left), Some(right)) => rows.push((*left, '|', *right)),
+                (Some(left), _) => rows.push((*left, '<', "")),
+                (_, Some(right)) => rows.push(("", '>', *right)),
+                (None, None) => {}
+            }
+        }
+        removed.clear();
+        added.clear();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            flush(&mut rows, &mut removed, &mut added);
+            rows.push((old[i], ' ', new[j]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            removed.push(old[i]);
+            i += 1;
+        } else {
+            added.push(new[j]);
+            j += 1;
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
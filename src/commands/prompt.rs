@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::{Config, RequestKind};
+use crate::daemon::llm::router::ModelRouter;
+use crate::daemon::llm::{self, LlmRequest, Prepared};
+use crate::daemon::shell_mode::ShellMode;
+use crate::daemon::{ask, context, diagnosis, explain, prompts, sanitizer};
+use crate::protocol::{AskRequest, CompletionRequest, DiagnosisRequest, ExplainRequest};
+
+#[derive(Debug, Serialize)]
+struct PromptOutput {
+    kind: &'static str,
+    shell_mode: &'static str,
+    profile: String,
+    model: String,
+    fill_in_middle: bool,
+    template_dir: PathBuf,
+    overridden_templates: Vec<&'static str>,
+    max_tokens: u32,
+    temperature: f32,
+    prompt_tokens: usize,
+    max_prompt_tokens: usize,
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_schema: Option<Value>,
+    system: String,
+    user: String,
+}
+
+/// Options of `nudge prompt render`
+pub struct RenderOptions {
+    pub buffer: String,
+    pub cursor: Option<usize>,
+    pub cwd: PathBuf,
+    pub session: String,
+    pub shell_mode: Option<String>,
+    pub last_exit_code: Option<i32>,
+    pub diagnose: bool,
+    pub ask: bool,
+    pub explain: bool,
+    pub stderr: Option<String>,
+    pub json: bool,
+}
+
+pub async fn run_prompt_render(options: RenderOptions) -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let shell_mode = ShellMode::resolve(options.shell_mode.as_deref(), &options.session);
+    let router = ModelRouter::from_config(&config.model)?;
+
+    // Gather and sanitize context exactly like the daemon does for this kind of request.
+    let kind = if options.diagnose {
+        RequestKind::Diagnosis
+    } else if options.ask {
+        RequestKind::Ask
+    } else if options.explain {
+        RequestKind::Explain
+    } else {
+        RequestKind::Completion
+    };
+    let params = match kind {
+        RequestKind::Completion => context::GatherParams::from(&CompletionRequest::new(
+            options.session.clone(),
+            options.buffer.clone(),
+            options.buffer.len(),
+            options.cwd.clone(),
+            options.last_exit_code,
+        )),
+        RequestKind::Diagnosis => context::GatherParams::from(&DiagnosisRequest::new(
+            options.session.clone(),
+            options.buffer.clone(),
+            options.last_exit_code.unwrap_or(1),
+            options.cwd.clone(),
+        )),
+        RequestKind::Ask => context::GatherParams::from(&AskRequest::new(
+            options.session.clone(),
+            options.buffer.clone(),
+            options.cwd.clone(),
+        )),
+        RequestKind::Explain => context::GatherParams::from(&ExplainRequest::new(
+            options.session.clone(),
+            options.buffer.clone(),
+            options.cwd.clone(),
+        )),
+    };
+    let mut gathered = context::gather(&params, &config).await?;
+    let mut stderr = options.stderr;
+    let mut buffer = options.buffer.clone();
+    let mut cursor = options.cursor.unwrap_or(options.buffer.len());
+    if config.privacy.sanitize_enabled {
+        let privacy = &config.privacy;
+        gathered = sanitizer::sanitize(&gathered, privacy).0;
+        stderr = stderr.map(|s| sanitizer::sanitize_field("stderr", &s, privacy).0);
+        (buffer, cursor, _) = sanitizer::sanitize_buffer(&options.buffer, cursor, privacy);
+    }
+
+    let route = router
+        .route(kind, shell_mode)
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    let prepared = match kind {
+        RequestKind::Completion => {
+            llm::build_request(&buffer, cursor, &gathered, &config, shell_mode, &route)
+        }
+        RequestKind::Diagnosis => {
+            let prepared = diagnosis::build_request(
+                &buffer,
+                options.last_exit_code.unwrap_or(1),
+                stderr.as_deref(),
+                None,
+                &gathered,
+                &config,
+                &route,
+            );
+            Prepared {
+                request: LlmRequest::Chat(prepared.request),
+                summary: prepared.summary,
+            }
+        }
+        RequestKind::Ask => {
+            let prepared = ask::build_request(&buffer, &gathered, &config, shell_mode, &route);
+            Prepared {
+                request: LlmRequest::Chat(prepared.request),
+                summary: prepared.summary,
+            }
+        }
+        RequestKind::Explain => {
+            let parts = explain::command_parts(&options.buffer);
+            let part_texts = explain::prompt_parts(&options.buffer, &parts, &config);
+            let prepared = explain::build_request(
+                &buffer,
+                &part_texts,
+                &gathered,
+                &config,
+                shell_mode,
+                &route,
+            );
+            Prepared {
+                request: LlmRequest::Chat(prepared.request),
+                summary: prepared.summary,
+            }
+        }
+    };
+    let request = &prepared.request;
+    let chat = request.chat();
+
+    let template_dir = prompts::template_dir();
+    let output = PromptOutput {
+        kind: kind.as_str(),
+        shell_mode: shell_mode.as_str(),
+        profile: route.profile.to_string(),
+        model: route.provider.model().model_name.clone(),
+        fill_in_middle: matches!(request, LlmRequest::Fim(_)),
+        overridden_templates: prompts::overridden(&template_dir),
+        template_dir,
+        max_tokens: chat.max_tokens,
+        temperature: chat.temperature,
+        prompt_tokens: prepared.summary.total_tokens.unwrap_or_default(),
+        max_prompt_tokens: config.context.max_total_tokens,
+        truncated: prepared.summary.truncated.unwrap_or_default(),
+        response_schema: chat.schema.as_ref().map(|schema| schema.schema.clone()),
+        system: chat.system.clone(),
+        user: chat.user.clone(),
+    };
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("Nudge Prompt");
+    println!("============");
+    println!("{:<16}{}", "Kind:", output.kind);
+    println!("{:<16}{}", "Shell mode:", output.shell_mode);
+    println!("{:<16}{} ({})", "Profile:", output.profile, output.model);
+    if output.fill_in_middle {
+        println!("{:<16}fill-in-the-middle", "Form:");
+    }
+    println!(
+        "{:<16}{} tokens, temperature {}",
+        "Limits:", output.max_tokens, output.temperature
+    );
+    println!(
+        "{:<16}{} / {}{}",
+        "Prompt tokens:",
+        output.prompt_tokens,
+        output.max_prompt_tokens,
+        if output.truncated {
+            " (context truncated)"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "{:<16}{}",
+        "Structured:",
+        if output.response_schema.is_some() {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!("{:<16}{}", "Templates:", output.template_dir.display());
+    if output.overridden_templates.is_empty() {
+        println!("{:<16}none (built-in templates)", "Overridden:");
+    } else {
+        println!(
+            "{:<16}{}",
+            "Overridden:",
+            output.overridden_templates.join(", ")
+        );
+    }
+    println!();
+    println!("--- system ---");
+    println!("{}", output.system);
+    println!();
+    println!("--- user ---");
+    println!("{}", output.user);
+
+    Ok(())
+}
+
+/// Copy the built-in templates into the template directory, keeping existing
+/// files unless `force` is set.
+pub async fn run_prompt_init(force: bool) -> Result<()> {
+    let dir = prompts::template_dir();
+    let mut written = 0;
+    for name in prompts::names() {
+        let path = dir.join(name);
+        if path.exists() && !force {
+            println!("kept     {}", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&path, prompts::builtin(name))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("written  {}", path.display());
+        written += 1;
+    }
+    println!();
+    println!(
+        "{} template(s) written to {}. Delete a file to go back to the built-in version.",
+        written,
+        dir.display()
+    );
+    Ok(())
+}
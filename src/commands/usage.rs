@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::config::{BudgetAction, BudgetConfig, Config};
+use crate::daemon::usage::{self, UsageCounters};
+
+#[derive(Debug, Serialize)]
+struct UsageOutput {
+    date: NaiveDate,
+    enabled: bool,
+    today: UsageCounters,
+    month: UsageCounters,
+    by_model: BTreeMap<String, UsageCounters>,
+    by_kind: BTreeMap<String, UsageCounters>,
+    budget: BudgetConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget_exceeded: Option<String>,
+    days: Vec<DayOutput>,
+}
+
+#[derive(Debug, Serialize)]
+struct DayOutput {
+    date: NaiveDate,
+    #[serde(flatten)]
+    total: UsageCounters,
+}
+
+/// Token usage summary shared with `nudge info`
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSummary {
+    pub today: UsageCounters,
+    pub month: UsageCounters,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_exceeded: Option<String>,
+}
+
+impl UsageSummary {
+    pub fn load(config: &Config) -> Self {
+        let (ledger, unknown) = usage::load_checked(&usage::usage_path(), &config.usage.budget);
+        let today = usage::today();
+        Self {
+            today: ledger.day(today),
+            month: ledger.month(today),
+            budget_exceeded: unknown
+                .or_else(|| ledger.budget_exceeded(&config.usage.budget, today)),
+        }
+    }
+}
+
+pub async fn run_usage(json: bool, days: usize) -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let (ledger, unknown) = usage::load_checked(&usage::usage_path(), &config.usage.budget);
+    let today = usage::today();
+    let today_usage = ledger.days.get(&today).cloned().unwrap_or_default();
+
+    let output = UsageOutput {
+        date: today,
+        enabled: config.usage.enabled,
+        today: today_usage.total(),
+        month: ledger.month(today),
+        by_model: today_usage.by_model,
+        by_kind: today_usage.by_kind,
+        budget_exceeded: unknown.or_else(|| ledger.budget_exceeded(&config.usage.budget, today)),
+        budget: config.usage.budget,
+        days: ledger
+            .days
+            .iter()
+            .rev()
+            .take(days)
+            .map(|(date, usage)| DayOutput {
+                date: *date,
+                total: usage.total(),
+            })
+            .collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("Nudge Token Usage");
+    println!("=================");
+    if !output.enabled {
+        println!("Recording is disabled (usage.enabled: false)");
+    }
+    println!(
+        "{:<22}{}",
+        format!("Today ({}):", output.date),
+        format_counters(&output.today)
+    );
+    println!(
+        "{:<22}{}",
+        format!("This month ({}):", output.date.format("%Y-%m")),
+        format_counters(&output.month)
+    );
+
+    println!();
+    println!("Budget");
+    println!("------");
+    let budget = &output.budget;
+    if budget.is_limited() {
+        print_limit(
+            "Daily tokens:",
+            output.today.total_tokens(),
+            budget.daily_tokens,
+        );
+        print_limit(
+            "Daily requests:",
+            output.today.requests,
+            budget.daily_requests,
+        );
+        print_limit(
+            "Monthly tokens:",
+            output.month.total_tokens(),
+            budget.monthly_tokens,
+        );
+        print_limit(
+            "Monthly requests:",
+            output.month.requests,
+            budget.monthly_requests,
+        );
+        let action = match budget.on_exceeded {
+            BudgetAction::Cache => "cache only".to_string(),
+            BudgetAction::Fallback => format!(
+                "fallback to profile '{}'",
+                budget.fallback_profile.as_deref().unwrap_or_default()
+            ),
+        };
+        println!("{:<22}{}", "When exceeded:", action);
+        match &output.budget_exceeded {
+            Some(reason) => println!("{:<22}exceeded: {}", "Status:", reason),
+            None => println!("{:<22}within budget", "Status:"),
+        }
+    } else {
+        println!("No limits configured (usage.budget)");
+    }
+
+    print_breakdown("By model (today)", &output.by_model);
+    print_breakdown("By kind (today)", &output.by_kind);
+
+    if !output.days.is_empty() {
+        println!();
+        println!("Recent days");
+        println!("-----------");
+        for day in &output.days {
+            println!("{}  {}", day.date, format_counters(&day.total));
+        }
+    }
+
+    Ok(())
+}
+
+fn format_counters(counters: &UsageCounters) -> String {
+    format!(
+        "{} requests, {} tokens ({} in / {} out)",
+        counters.requests,
+        counters.total_tokens(),
+        counters.input_tokens,
+        counters.output_tokens
+    )
+}
+
+fn print_limit(label: &str, used: u64, limit: Option<u64>) {
+    if let Some(limit) = limit {
+        println!("{:<22}{} / {}", label, used, limit);
+    }
+}
+
+fn print_breakdown(title: &str, entries: &BTreeMap<String, UsageCounters>) {
+    if entries.is_empty() {
+        return;
+    }
+    println!();
+    println!("{}", title);
+    println!("{}", "-".repeat(title.len()));
+    for (name, counters) in entries {
+        println!("{:<22}{}", name, format_counters(counters));
+    }
+}
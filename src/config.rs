@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
@@ -19,9 +20,14 @@ pub struct Config {
     pub plugins: PluginsConfig,
     pub trigger: TriggerConfig,
     pub cache: CacheConfig,
+    pub predictor: PredictorConfig,
+    pub usage: UsageConfig,
     pub privacy: PrivacyConfig,
     pub log: LogConfig,
     pub diagnosis: DiagnosisConfig,
+    pub ask: AskConfig,
+    pub explain: ExplainConfig,
+    pub validation: ValidationConfig,
     pub system_prompt: Option<String>,
 }
 
@@ -29,6 +35,8 @@ pub struct Config {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ModelConfig {
+    /// Provider API flavor used to talk to the endpoint
+    pub provider: ModelProvider,
     /// API endpoint URL
     pub endpoint: String,
     /// Model name/identifier
@@ -37,18 +45,452 @@ pub struct ModelConfig {
     pub api_key: Option<String>,
     /// Environment variable containing API key (fallback if api_key is not set)
     pub api_key_env: Option<String>,
+    /// Command printing the API key (e.g. `pass show openai`), run when first needed
+    pub api_key_command: Option<String>,
+    /// Seconds the daemon caches the output of `api_key_command`
+    pub api_key_command_ttl_secs: u64,
+    /// File holding the API key; must not be readable by group or others
+    pub api_key_file: Option<PathBuf>,
     /// Request timeout in milliseconds
     pub timeout_ms: u64,
+    /// Stream completions and stop once the first command line or JSON value arrives
+    pub stream: bool,
+    /// Sampling temperature (built-in per-request default when unset)
+    pub temperature: Option<f32>,
+    /// Response token limit (built-in per-shell-mode default when unset)
+    pub max_tokens: Option<u32>,
+    /// Cursor-aware fill-in-the-middle completion
+    pub fim: FimConfig,
+    /// Send a JSON schema for completion, diagnosis, ask and explain answers (OpenAI
+    /// `response_format`, Anthropic forced tool call, Ollama `format`)
+    pub structured_output: bool,
+    /// Shared HTTP client settings
+    pub http: HttpConfig,
+    /// Backends tried in order when the primary endpoint fails
+    pub fallbacks: Vec<FallbackModelConfig>,
+    /// Per-endpoint circuit breaker
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Retries of rate-limited and server errors (manual triggers and diagnosis)
+    pub retry: RetryConfig,
+    /// Named model profiles; unset fields inherit from this section
+    pub profiles: BTreeMap<String, ModelProfileConfig>,
+    /// Routing rules, first match wins; unmatched requests use this section
+    pub routes: Vec<ModelRouteConfig>,
 }
 
 impl Default for ModelConfig {
     fn default() -> Self {
         Self {
+            provider: ModelProvider::OpenAi,
             endpoint: "http://localhost:11434/v1".to_string(),
             model_name: "codellama:7b".to_string(),
             api_key: None,
             api_key_env: None,
+            api_key_command: None,
+            api_key_command_ttl_secs: 3600,
+            api_key_file: None,
             timeout_ms: 5000,
+            stream: true,
+            temperature: None,
+            max_tokens: None,
+            fim: FimConfig::default(),
+            structured_output: true,
+            http: HttpConfig::default(),
+            fallbacks: Vec::new(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            retry: RetryConfig::default(),
+            profiles: BTreeMap::new(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl ModelConfig {
+    /// Ordered backend chain: the primary model followed by its fallbacks.
+    ///
+    /// Fallback entries inherit timeout, streaming and HTTP settings from the
+    /// primary model but carry their own provider, endpoint and credentials.
+    pub fn backends(&self) -> Vec<ModelConfig> {
+        let primary = ModelConfig {
+            fallbacks: Vec::new(),
+            ..self.clone()
+        };
+        let fallbacks = self.fallbacks.iter().map(|fallback| ModelConfig {
+            provider: fallback.provider,
+            endpoint: fallback.endpoint.clone(),
+            model_name: fallback.model_name.clone(),
+            api_key: fallback.api_key.clone(),
+            api_key_env: fallback.api_key_env.clone(),
+            api_key_command: fallback.api_key_command.clone(),
+            api_key_file: fallback.api_key_file.clone(),
+            structured_output: fallback
+                .structured_output
+                .unwrap_or(primary.structured_output),
+            ..primary.clone()
+        });
+        std::iter::once(primary.clone()).chain(fallbacks).collect()
+    }
+
+    /// Resolve a named profile into a full model configuration.
+    ///
+    /// The key sources are inherited as a unit, and only when the profile
+    /// sets none of them and keeps the top-level provider and endpoint, so a
+    /// credential never follows the profile to another backend.
+    pub fn with_profile(&self, profile: &ModelProfileConfig) -> ModelConfig {
+        let own_key = profile.api_key.is_some()
+            || profile.api_key_env.is_some()
+            || profile.api_key_command.is_some()
+            || profile.api_key_file.is_some();
+        let inherit_key = !own_key && profile.provider.is_none() && profile.endpoint.is_none();
+        let (api_key, api_key_env, api_key_command, api_key_file) = if inherit_key {
+            (
+                self.api_key.clone(),
+                self.api_key_env.clone(),
+                self.api_key_command.clone(),
+                self.api_key_file.clone(),
+            )
+        } else {
+            (
+                profile.api_key.clone(),
+                profile.api_key_env.clone(),
+                profile.api_key_command.clone(),
+                profile.api_key_file.clone(),
+            )
+        };
+        ModelConfig {
+            provider: profile.provider.unwrap_or(self.provider),
+            endpoint: profile
+                .endpoint
+                .clone()
+                .unwrap_or_else(|| self.endpoint.clone()),
+            model_name: profile
+                .model_name
+                .clone()
+                .unwrap_or_else(|| self.model_name.clone()),
+            api_key,
+            api_key_env,
+            api_key_command,
+            api_key_file,
+            timeout_ms: profile.timeout_ms.unwrap_or(self.timeout_ms),
+            temperature: profile.temperature.or(self.temperature),
+            max_tokens: profile.max_tokens.or(self.max_tokens),
+            stream: profile.stream.unwrap_or(self.stream),
+            fim: profile.fim.clone().unwrap_or_else(|| self.fim.clone()),
+            structured_output: profile.structured_output.unwrap_or(self.structured_output),
+            fallbacks: profile
+                .fallbacks
+                .clone()
+                .unwrap_or_else(|| self.fallbacks.clone()),
+            profiles: BTreeMap::new(),
+            routes: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Configured API key source, in precedence order: `api_key`,
+    /// `api_key_command`, `api_key_file`, `api_key_env`.
+    pub fn api_key_source(&self) -> Option<ApiKeySource> {
+        let set = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+        if set(&self.api_key).is_some() {
+            return Some(ApiKeySource::Config);
+        }
+        if let Some(command) = set(&self.api_key_command) {
+            return Some(ApiKeySource::Command(command));
+        }
+        if let Some(path) = self
+            .api_key_file
+            .clone()
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            return Some(ApiKeySource::File(path));
+        }
+        set(&self.api_key_env).map(ApiKeySource::Env)
+    }
+}
+
+/// Where a backend's API key comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiKeySource {
+    /// `api_key` written in the config file
+    Config,
+    /// Output of `api_key_command`
+    Command(String),
+    /// Contents of `api_key_file`
+    File(PathBuf),
+    /// Environment variable named by `api_key_env`
+    Env(String),
+}
+
+impl std::fmt::Display for ApiKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Config => write!(f, "api_key (config file)"),
+            Self::Command(command) => write!(f, "api_key_command `{}`", command),
+            Self::File(path) => write!(f, "api_key_file {}", path.display()),
+            Self::Env(var) => write!(f, "api_key_env {}", var),
+        }
+    }
+}
+
+/// Read an API key file, rejecting files that group or others can read.
+pub fn read_api_key_file(path: &Path) -> Result<String> {
+    let path = expand_home(path);
+    let metadata = std::fs::metadata(&path)
+        .with_context(|| format!("Cannot access api_key_file {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            anyhow::bail!(
+                "api_key_file {} is accessible by group or others (mode {:o}); run `chmod 600 {}`",
+                path.display(),
+                mode,
+                path.display()
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read api_key_file {}", path.display()))?;
+    let key = content
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if key.is_empty() {
+        anyhow::bail!("api_key_file {} is empty", path.display());
+    }
+    Ok(key)
+}
+
+/// Expand a leading `~/` to the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), directories::BaseDirs::new()) {
+        (Ok(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Named model profile; every field is optional and inherits from `model`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelProfileConfig {
+    pub provider: Option<ModelProvider>,
+    pub endpoint: Option<String>,
+    pub model_name: Option<String>,
+    pub api_key: Option<String>,
+    pub api_key_env: Option<String>,
+    pub api_key_command: Option<String>,
+    pub api_key_file: Option<PathBuf>,
+    pub timeout_ms: Option<u64>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stream: Option<bool>,
+    pub fim: Option<FimConfig>,
+    pub structured_output: Option<bool>,
+    pub fallbacks: Option<Vec<FallbackModelConfig>>,
+}
+
+/// Fill-in-the-middle settings for edits in the middle of the buffer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FimConfig {
+    /// Send the text before and after the cursor separately when the cursor is
+    /// not at the end of the buffer
+    pub enabled: bool,
+    /// Use the provider's native FIM endpoint (OpenAI-compatible `/completions`
+    /// or Ollama `/api/generate` with `suffix`) instead of a chat prompt
+    pub native: bool,
+}
+
+impl Default for FimConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            native: false,
+        }
+    }
+}
+
+/// Routing rule mapping shell mode and/or request kind to a profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelRouteConfig {
+    /// Shell mode to match (e.g. `zsh-auto`, `bash-popup`); any when unset
+    pub shell_mode: Option<String>,
+    /// Request kind to match; any when unset
+    pub kind: Option<RequestKind>,
+    /// Profile name (`default` selects the top-level model)
+    pub profile: String,
+}
+
+/// Kind of LLM request, used for model routing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestKind {
+    Completion,
+    Diagnosis,
+    Ask,
+    Explain,
+}
+
+impl RequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestKind::Completion => "completion",
+            RequestKind::Diagnosis => "diagnosis",
+            RequestKind::Ask => "ask",
+            RequestKind::Explain => "explain",
+        }
+    }
+}
+
+/// Shell modes accepted in `model.routes[].shell_mode`
+const ROUTABLE_SHELL_MODES: &[&str] = &[
+    "zsh-auto",
+    "zsh-inline",
+    "bash-inline",
+    "bash-popup",
+    "ps-inline",
+    "cmd-inline",
+];
+
+/// Profile name that refers to the top-level `model` section
+pub const DEFAULT_MODEL_PROFILE: &str = "default";
+
+/// Fallback backend entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallbackModelConfig {
+    pub provider: ModelProvider,
+    pub endpoint: String,
+    pub model_name: String,
+    pub api_key: Option<String>,
+    pub api_key_env: Option<String>,
+    pub api_key_command: Option<String>,
+    pub api_key_file: Option<PathBuf>,
+    /// Overrides `model.structured_output` for this backend
+    pub structured_output: Option<bool>,
+}
+
+/// Circuit breaker settings applied to each backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures before the circuit opens
+    pub failure_threshold: u32,
+    /// Time an open circuit waits before allowing a half-open probe
+    pub cooldown_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown_ms: 30000,
+        }
+    }
+}
+
+/// Retry policy for 429 and 5xx responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubles on each further retry
+    pub base_delay_ms: u64,
+    /// Upper bound of the computed backoff (`Retry-After` is honored as sent)
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay_ms: 200,
+            max_delay_ms: 2000,
+        }
+    }
+}
+
+/// HTTP client settings shared by every LLM request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Negotiate HTTP/2 with the endpoint (HTTP/1.1 keep-alive otherwise)
+    pub http2: bool,
+    /// Keep idle pooled connections open for this many seconds
+    pub pool_idle_timeout_secs: u64,
+    /// Maximum idle pooled connections per host
+    pub pool_max_idle_per_host: usize,
+    /// TCP keep-alive interval in seconds (0 disables)
+    pub tcp_keepalive_secs: u64,
+    /// Connection establishment timeout in milliseconds
+    pub connect_timeout_ms: u64,
+    /// HTTP(S) proxy URL (credentials may be embedded); unset honors HTTP(S)_PROXY
+    pub proxy: Option<String>,
+    /// Hosts that bypass `proxy` (comma-separated, NO_PROXY syntax)
+    pub no_proxy: Option<String>,
+    /// PEM bundle of additional trusted root certificates
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS (may also hold the private key)
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key of `client_cert`, when kept in a separate file
+    pub client_key: Option<PathBuf>,
+    /// Static headers sent with every request (e.g. `X-Org-Id`)
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            http2: false,
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: 4,
+            tcp_keepalive_secs: 60,
+            connect_timeout_ms: 2000,
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+/// LLM provider API flavor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelProvider {
+    /// OpenAI-compatible `/chat/completions` API (OpenAI, vLLM, LM Studio, Ollama `/v1`, ...)
+    #[default]
+    OpenAi,
+    /// Anthropic Messages API (`/v1/messages`)
+    Anthropic,
+    /// Ollama native chat API (`/api/chat`)
+    Ollama,
+}
+
+impl ModelProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OpenAi => "openai",
+            Self::Anthropic => "anthropic",
+            Self::Ollama => "ollama",
+        }
+    }
+
+    /// Conventional environment variable holding this provider's API key
+    pub fn default_api_key_env(self) -> &'static str {
+        match self {
+            Self::Anthropic => "ANTHROPIC_API_KEY",
+            _ => "OPENAI_API_KEY",
         }
     }
 }
@@ -73,10 +515,15 @@ pub struct ContextConfig {
     pub similar_commands_max: usize,
     /// Max files to include in CWD listing
     pub max_files_in_listing: usize,
-    /// Max total context tokens
+    /// Max characters of each file name, branch name or plugin value in the
+    /// prompt; longer history commands are left out
+    pub max_item_chars: usize,
+    /// Max tokens of the rendered prompt; context is truncated by priority to fit
     pub max_total_tokens: usize,
     /// Priority levels for truncation
     pub priorities: PriorityConfig,
+    /// How prompt tokens are counted
+    pub tokenizer: TokenizerConfig,
 }
 
 impl Default for ContextConfig {
@@ -90,12 +537,46 @@ impl Default for ContextConfig {
             similar_commands_window: 200,
             similar_commands_max: 5,
             max_files_in_listing: 50,
+            max_item_chars: 256,
             max_total_tokens: 4000,
             priorities: PriorityConfig::default(),
+            tokenizer: TokenizerConfig::default(),
+        }
+    }
+}
+
+/// Prompt token counting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenizerConfig {
+    pub method: TokenizerMethod,
+    /// Characters per token for the `chars` method
+    pub chars_per_token: f32,
+    /// Correct estimates per model from the prompt token counts providers report
+    pub calibrate: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            method: TokenizerMethod::Estimate,
+            chars_per_token: 4.0,
+            calibrate: true,
         }
     }
 }
 
+/// Prompt token counting method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerMethod {
+    /// Split text like a BPE tokenizer and count the pieces
+    #[default]
+    Estimate,
+    /// Divide the character count by `chars_per_token`
+    Chars,
+}
+
 /// Priority levels for context sources (higher = keep when truncating)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -289,6 +770,112 @@ impl Default for CacheConfig {
     }
 }
 
+/// Offline history-based predictor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PredictorConfig {
+    /// When history predictions are served
+    pub mode: PredictorMode,
+    /// Most recent history entries indexed
+    pub history_size: usize,
+    /// Maximum predictions per response
+    pub max_suggestions: usize,
+    /// Frecency half-life in history entries: a command run this many
+    /// commands ago counts half as much as one run just now
+    pub half_life: usize,
+}
+
+impl Default for PredictorConfig {
+    fn default() -> Self {
+        Self {
+            mode: PredictorMode::Fallback,
+            history_size: 5000,
+            max_suggestions: 3,
+            half_life: 500,
+        }
+    }
+}
+
+/// How the history predictor is combined with the LLM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PredictorMode {
+    /// Never use history predictions
+    Off,
+    /// Answer from history when the LLM fails, times out or is over budget
+    #[default]
+    Fallback,
+    /// Answer from history right away and cache the LLM result for the next request
+    FastFirst,
+    /// Answer from history only; never call the LLM for completions
+    Only,
+}
+
+impl PredictorMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Fallback => "fallback",
+            Self::FastFirst => "fast-first",
+            Self::Only => "only",
+        }
+    }
+}
+
+/// Token usage accounting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    /// Record per-day token and request counts under the data directory
+    pub enabled: bool,
+    /// Limits after which `budget.on_exceeded` applies
+    pub budget: BudgetConfig,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: BudgetConfig::default(),
+        }
+    }
+}
+
+/// Daily/monthly token and request limits (unset = unlimited)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    pub daily_tokens: Option<u64>,
+    pub daily_requests: Option<u64>,
+    pub monthly_tokens: Option<u64>,
+    pub monthly_requests: Option<u64>,
+    /// What to do once a limit is reached
+    pub on_exceeded: BudgetAction,
+    /// Profile from `model.profiles` used when `on_exceeded: fallback`
+    pub fallback_profile: Option<String>,
+}
+
+impl BudgetConfig {
+    /// Whether any limit is configured
+    pub fn is_limited(&self) -> bool {
+        self.daily_tokens.is_some()
+            || self.daily_requests.is_some()
+            || self.monthly_tokens.is_some()
+            || self.monthly_requests.is_some()
+    }
+}
+
+/// Behavior once the usage budget is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Serve cached suggestions only; no new LLM requests
+    #[default]
+    Cache,
+    /// Send requests to `fallback_profile` (e.g. a local model)
+    Fallback,
+}
+
 /// Trigger mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -328,8 +915,29 @@ pub enum ZshOverlayBackend {
 pub struct PrivacyConfig {
     pub sanitize_enabled: bool,
     pub custom_patterns: Vec<String>,
+    /// Redact high-entropy tokens and structured secrets (JWT, PEM private
+    /// keys, connection URIs) besides the vendor patterns
+    pub entropy_detection: bool,
+    /// Minimum length of a token segment checked for entropy
+    pub entropy_min_length: usize,
+    /// Shannon entropy, in bits per character, from which a token is a secret
+    pub entropy_threshold: f64,
+    /// Tokens never redacted
+    pub allowlist: Vec<String>,
+    /// Redact secrets of daemon requests as `⟦SECRET_n⟧` placeholders and
+    /// restore them in the returned commands
+    pub reversible_redaction: bool,
+    /// Refuse model endpoints that are not on a loopback address
+    pub local_only: bool,
+    /// Working directories with restricted context; the first match applies
+    pub excluded_paths: Vec<ExcludedPath>,
     pub block_dangerous: bool,
     pub custom_blocked: Vec<String>,
+    /// Drop model suggestions that download and run remote code
+    pub block_fetch_exec: bool,
+    /// Drop completions that chain on commands with `;`, `&&`, `||`, `|` or
+    /// command and process substitutions the user did not type
+    pub block_new_chaining: bool,
 }
 
 impl Default for PrivacyConfig {
@@ -337,12 +945,47 @@ impl Default for PrivacyConfig {
         Self {
             sanitize_enabled: true,
             custom_patterns: Vec::new(),
+            entropy_detection: true,
+            entropy_min_length: 20,
+            entropy_threshold: 4.0,
+            allowlist: Vec::new(),
+            reversible_redaction: true,
+            local_only: false,
+            excluded_paths: Vec::new(),
             block_dangerous: true,
             custom_blocked: Vec::new(),
+            block_fetch_exec: true,
+            block_new_chaining: true,
         }
     }
 }
 
+/// What requests from an excluded path send to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcludedMode {
+    /// No model request: completion returns nothing
+    Off,
+    /// The buffer without any gathered context
+    #[default]
+    BufferOnly,
+    /// Gathered context without the opted-out fields
+    Context,
+}
+
+/// Privacy rule for working directories matching a glob
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcludedPath {
+    /// Glob: `*` within a path component, `**` across components, `~/` for home
+    pub path: String,
+    pub mode: ExcludedMode,
+    /// Leave out the directory listing
+    pub no_files: bool,
+    /// Leave out shell history and similar commands
+    pub no_history: bool,
+}
+
 /// Logging configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -431,6 +1074,86 @@ impl Default for DiagnosisConfig {
     }
 }
 
+/// Natural-language command requests (`nudge ask`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AskConfig {
+    /// Completion buffers starting with this prefix are answered as a
+    /// question (empty disables it; zsh auto mode never does this)
+    pub prefix: String,
+    /// Maximum number of candidate commands returned
+    pub max_candidates: usize,
+    /// Timeout for ask requests (ms)
+    pub timeout_ms: u64,
+}
+
+impl Default for AskConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "#".to_string(),
+            max_candidates: 3,
+            timeout_ms: 8000,
+        }
+    }
+}
+
+/// Command explanations (`nudge explain`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExplainConfig {
+    /// Timeout for explain requests (ms)
+    pub timeout_ms: u64,
+}
+
+impl Default for ExplainConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 8000 }
+    }
+}
+
+/// Checks run on model suggestions before they are returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    /// Validate model suggestions (bash and zsh modes)
+    pub enabled: bool,
+    /// Every command name must be a builtin, a `known_commands` entry, a
+    /// command from shell history, an existing path or on the daemon's `PATH`
+    pub check_commands: bool,
+    /// Aliases and functions the daemon cannot see
+    pub known_commands: Vec<String>,
+    /// Parse each suggestion with `bash -n` / `zsh -n`
+    pub shell_syntax: bool,
+    /// Timeout of one `bash -n` / `zsh -n` run (ms)
+    pub shell_syntax_timeout_ms: u64,
+    /// What happens to suggestions that fail a check
+    pub on_failure: ValidationAction,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_commands: true,
+            known_commands: Vec::new(),
+            shell_syntax: false,
+            shell_syntax_timeout_ms: 200,
+            on_failure: ValidationAction::Demote,
+        }
+    }
+}
+
+/// Handling of suggestions that fail validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationAction {
+    /// Keep them after the valid ones, with lower confidence
+    #[default]
+    Demote,
+    /// Remove them
+    Drop,
+}
+
 impl Config {
     /// Load configuration with layered approach:
     /// 1. Start with built-in defaults
@@ -583,8 +1306,11 @@ impl Config {
     fn log_loaded_summary(config: &Self) {
         // Log loaded configuration (concise single-line summary)
         info!(
-            "Config loaded: model={} endpoint={} timeout={}ms",
-            config.model.model_name, config.model.endpoint, config.model.timeout_ms
+            "Config loaded: provider={} model={} endpoint={} timeout={}ms",
+            config.model.provider.as_str(),
+            config.model.model_name,
+            config.model.endpoint,
+            config.model.timeout_ms
         );
         debug!(
             "Config details: history_window={} git_enabled={} system_info={} cwd_listing={}",
@@ -635,13 +1361,124 @@ impl Config {
             anyhow::bail!("model.timeout_ms must be greater than 0");
         }
 
+        if self.model.circuit_breaker.failure_threshold == 0 {
+            anyhow::bail!("model.circuit_breaker.failure_threshold must be greater than 0");
+        }
+
+        let http = &self.model.http;
+        if let Some(proxy) = &http.proxy {
+            if !(proxy.starts_with("http://") || proxy.starts_with("https://")) {
+                anyhow::bail!("model.http.proxy must be an http:// or https:// URL");
+            }
+        }
+        if http.client_key.is_some() && http.client_cert.is_none() {
+            anyhow::bail!("model.http.client_key requires model.http.client_cert");
+        }
+
+        let retry = &self.model.retry;
+        if retry.max_retries > 0 && retry.base_delay_ms == 0 {
+            anyhow::bail!("model.retry.base_delay_ms must be greater than 0");
+        }
+        if retry.max_delay_ms < retry.base_delay_ms {
+            anyhow::bail!("model.retry.max_delay_ms must be at least model.retry.base_delay_ms");
+        }
+
+        for (name, profile) in &self.model.profiles {
+            if name == DEFAULT_MODEL_PROFILE {
+                anyhow::bail!(
+                    "model.profiles.{} is reserved for the top-level model",
+                    DEFAULT_MODEL_PROFILE
+                );
+            }
+            if profile.timeout_ms == Some(0) {
+                anyhow::bail!("model.profiles.{}.timeout_ms must be greater than 0", name);
+            }
+            if profile.max_tokens == Some(0) {
+                anyhow::bail!("model.profiles.{}.max_tokens must be greater than 0", name);
+            }
+            if profile
+                .temperature
+                .is_some_and(|t| !(0.0..=2.0).contains(&t))
+            {
+                anyhow::bail!("model.profiles.{}.temperature must be within [0, 2]", name);
+            }
+        }
+
+        if self.model.max_tokens == Some(0) {
+            anyhow::bail!("model.max_tokens must be greater than 0");
+        }
+        if self
+            .model
+            .temperature
+            .is_some_and(|t| !(0.0..=2.0).contains(&t))
+        {
+            anyhow::bail!("model.temperature must be within [0, 2]");
+        }
+
+        for (idx, route) in self.model.routes.iter().enumerate() {
+            if route.profile != DEFAULT_MODEL_PROFILE
+                && !self.model.profiles.contains_key(&route.profile)
+            {
+                anyhow::bail!(
+                    "model.routes[{}] references unknown profile '{}'",
+                    idx,
+                    route.profile
+                );
+            }
+            if let Some(mode) = &route.shell_mode {
+                if !ROUTABLE_SHELL_MODES.contains(&mode.as_str()) {
+                    anyhow::bail!(
+                        "model.routes[{}].shell_mode '{}' must be one of: {}",
+                        idx,
+                        mode,
+                        ROUTABLE_SHELL_MODES.join(", ")
+                    );
+                }
+            }
+        }
+
+        for (idx, fallback) in self.model.fallbacks.iter().enumerate() {
+            if fallback.endpoint.is_empty() || fallback.model_name.is_empty() {
+                anyhow::bail!(
+                    "model.fallbacks[{}] must set both endpoint and model_name",
+                    idx
+                );
+            }
+        }
+
         if self.context.history_window == 0 {
             anyhow::bail!("context.history_window must be greater than 0");
         }
 
+        if self.predictor.mode != PredictorMode::Off {
+            if self.predictor.history_size == 0 {
+                anyhow::bail!("predictor.history_size must be greater than 0");
+            }
+            if self.predictor.max_suggestions == 0 {
+                anyhow::bail!("predictor.max_suggestions must be greater than 0");
+            }
+            if self.predictor.half_life == 0 {
+                anyhow::bail!("predictor.half_life must be greater than 0");
+            }
+        }
+
+        if self.validation.shell_syntax && self.validation.shell_syntax_timeout_ms == 0 {
+            anyhow::bail!("validation.shell_syntax_timeout_ms must be greater than 0");
+        }
+
+        if self.ask.max_candidates == 0 {
+            anyhow::bail!("ask.max_candidates must be greater than 0");
+        }
+
         if self.context.max_total_tokens == 0 {
             anyhow::bail!("context.max_total_tokens must be greater than 0");
         }
+        if self.context.max_item_chars == 0 {
+            anyhow::bail!("context.max_item_chars must be greater than 0");
+        }
+        if self.context.tokenizer.chars_per_token <= 0.0 {
+            anyhow::bail!("context.tokenizer.chars_per_token must be greater than 0");
+        }
 
         if self.trigger.auto_delay_ms == 0 {
             anyhow::bail!("trigger.auto_delay_ms must be greater than 0");
@@ -671,6 +1508,37 @@ impl Config {
             anyhow::bail!("cache.stale_ratio must be between 0.0 and 1.0");
         }
 
+        let budget = &self.usage.budget;
+        for (key, limit) in [
+            ("daily_tokens", budget.daily_tokens),
+            ("daily_requests", budget.daily_requests),
+            ("monthly_tokens", budget.monthly_tokens),
+            ("monthly_requests", budget.monthly_requests),
+        ] {
+            if limit == Some(0) {
+                anyhow::bail!("usage.budget.{} must be greater than 0", key);
+            }
+        }
+        if budget.is_limited() && !self.usage.enabled {
+            anyhow::bail!("usage.budget requires usage.enabled");
+        }
+        if budget.on_exceeded == BudgetAction::Fallback {
+            match budget.fallback_profile.as_deref() {
+                None => anyhow::bail!(
+                    "usage.budget.fallback_profile is required when on_exceeded is 'fallback'"
+                ),
+                Some(name)
+                    if name != DEFAULT_MODEL_PROFILE && !self.model.profiles.contains_key(name) =>
+                {
+                    anyhow::bail!(
+                        "usage.budget.fallback_profile references unknown profile '{}'",
+                        name
+                    )
+                }
+                Some(_) => {}
+            }
+        }
+
         if self.diagnosis.max_stderr_size == 0 {
             anyhow::bail!("diagnosis.max_stderr_size must be greater than 0");
         }
@@ -679,6 +1547,19 @@ impl Config {
             anyhow::bail!("diagnosis.timeout_ms must be greater than 0");
         }
 
+        for (idx, rule) in self.privacy.excluded_paths.iter().enumerate() {
+            if rule.path.trim().is_empty() {
+                anyhow::bail!("privacy.excluded_paths[{}].path must not be empty", idx);
+            }
+        }
+
+        if self.privacy.entropy_min_length == 0 {
+            anyhow::bail!("privacy.entropy_min_length must be greater than 0");
+        }
+        if !self.privacy.entropy_threshold.is_finite() || self.privacy.entropy_threshold <= 0.0 {
+            anyhow::bail!("privacy.entropy_threshold must be greater than 0");
+        }
+
         Self::validate_priority(
             "context.priorities.history",
             self.context.priorities.history,
@@ -718,8 +1599,8 @@ impl Config {
     }
 
     /// Check if LLM configuration is properly set up
-    /// Returns Ok(()) if valid, Err with helpful message if not
-    pub fn validate_llm_config(&self) -> Result<()> {
+    /// Returns the API key source in use if valid, Err with helpful message if not
+    pub fn validate_llm_config(&self) -> Result<Option<ApiKeySource>> {
         // Check endpoint
         if self.model.endpoint.is_empty() {
             anyhow::bail!(
@@ -737,59 +1618,106 @@ impl Config {
             || self.model.endpoint.contains("127.0.0.1")
             || self.model.endpoint.contains("0.0.0.0");
 
-        if !is_local {
-            // Check if api_key is set directly
-            let has_direct_key = self.model.api_key.as_ref().is_some_and(|k| !k.is_empty());
-
-            // Check if api_key_env is set and the env var exists
-            let has_env_key = self
-                .model
-                .api_key_env
-                .as_ref()
-                .is_some_and(|env_var| !env_var.is_empty() && std::env::var(env_var).is_ok());
-
-            if !has_direct_key && !has_env_key {
-                let config_path = Self::default_config_path().display().to_string();
-
-                let mut msg = format!(
-                    "API key is required for remote LLM endpoint '{}'\n\n",
-                    self.model.endpoint
+        let source = self.model.api_key_source();
+        match &source {
+            // Read once up front so insecure permissions are reported at startup.
+            Some(ApiKeySource::File(path)) => {
+                read_api_key_file(path)?;
+            }
+            Some(ApiKeySource::Env(var)) if !is_local && std::env::var(var).is_err() => {
+                anyhow::bail!(
+                    "{}\nEnvironment variable {} is not set.",
+                    self.missing_api_key_message(),
+                    var
                 );
-                msg.push_str("Please configure one of the following in your config file:\n\n");
-                msg.push_str("  Option 1 - Direct API key:\n");
-                msg.push_str("    model:\n");
-                msg.push_str("      api_key: \"your-api-key-here\"\n\n");
-                msg.push_str("  Option 2 - Environment variable (recommended for security):\n");
-                msg.push_str("    model:\n");
-                msg.push_str("      api_key_env: \"OPENAI_API_KEY\"\n\n");
-                msg.push_str(&format!("Config file location: {}", config_path));
-
-                anyhow::bail!(msg);
             }
+            None if !is_local => anyhow::bail!(self.missing_api_key_message()),
+            _ => {}
         }
 
-        Ok(())
+        Ok(source)
+    }
+
+    fn missing_api_key_message(&self) -> String {
+        let config_path = Self::default_config_path().display().to_string();
+
+        let mut msg = format!(
+            "API key is required for remote LLM endpoint '{}'\n\n",
+            self.model.endpoint
+        );
+        msg.push_str("Please configure one of the following in your config file:\n\n");
+        msg.push_str("  Option 1 - Direct API key:\n");
+        msg.push_str("    model:\n");
+        msg.push_str("      api_key: \"your-api-key-here\"\n\n");
+        msg.push_str("  Option 2 - Environment variable:\n");
+        msg.push_str("    model:\n");
+        msg.push_str(&format!(
+            "      api_key_env: \"{}\"\n\n",
+            self.model.provider.default_api_key_env()
+        ));
+        msg.push_str("  Option 3 - Password manager command (recommended for security):\n");
+        msg.push_str("    model:\n");
+        msg.push_str("      api_key_command: \"pass show openai\"\n\n");
+        msg.push_str("  Option 4 - Key file readable only by you (chmod 600):\n");
+        msg.push_str("    model:\n");
+        msg.push_str("      api_key_file: \"~/.config/nudge/openai.key\"\n\n");
+        msg.push_str(&format!("Config file location: {}", config_path));
+        msg
     }
 
     /// Get a user-friendly summary of LLM configuration status
     pub fn llm_config_summary(&self) -> String {
         let mut summary = String::new();
+        summary.push_str(&format!("  Provider: {}\n", self.model.provider.as_str()));
         summary.push_str(&format!("  Endpoint: {}\n", self.model.endpoint));
         summary.push_str(&format!("  Model: {}\n", self.model.model_name));
 
-        let auth_status = if self.model.api_key.as_ref().is_some_and(|k| !k.is_empty()) {
-            "Configured (direct)"
-        } else if let Some(env_var) = &self.model.api_key_env {
-            if std::env::var(env_var).is_ok() {
-                "Configured (via env)"
-            } else {
-                "NOT SET (env var missing)"
+        let auth_status = match self.model.api_key_source() {
+            Some(ApiKeySource::Env(var)) if std::env::var(&var).is_err() => {
+                format!("NOT SET (env var {} missing)", var)
             }
-        } else {
-            "Not required (local)"
+            Some(ApiKeySource::File(path)) if read_api_key_file(&path).is_err() => {
+                format!(
+                    "INVALID (api_key_file {} unreadable or not private)",
+                    path.display()
+                )
+            }
+            Some(source) => format!("Configured via {}", source),
+            None => "Not required (local)".to_string(),
         };
         summary.push_str(&format!("  API Key: {}", auth_status));
 
+        for fallback in &self.model.fallbacks {
+            summary.push_str(&format!(
+                "\n  Fallback: {} {} @ {}",
+                fallback.provider.as_str(),
+                fallback.model_name,
+                fallback.endpoint
+            ));
+        }
+
+        for route in &self.model.routes {
+            let target = match self.model.profiles.get(&route.profile) {
+                Some(profile) => {
+                    let resolved = self.model.with_profile(profile);
+                    format!(
+                        "{} ({} {} @ {})",
+                        route.profile,
+                        resolved.provider.as_str(),
+                        resolved.model_name,
+                        resolved.endpoint
+                    )
+                }
+                None => route.profile.clone(),
+            };
+            summary.push_str(&format!(
+                "\n  Route: {} / {} -> {}",
+                route.shell_mode.as_deref().unwrap_or("*"),
+                route.kind.map_or("*", |kind| kind.as_str()),
+                target
+            ));
+        }
+
         summary
     }
 }
@@ -998,7 +1926,11 @@ mod tests {
     use serde_yaml::Value;
     use tempfile::NamedTempFile;
 
-    use super::{Config, ZshGhostOwner, ZshOverlayBackend, CONFIG_ENV, LEGACY_CONFIG_ENV};
+    use super::{
+        ApiKeySource, BudgetAction, Config, ExcludedMode, FallbackModelConfig, ModelProfileConfig,
+        ModelProvider, ModelRouteConfig, PredictorMode, RequestKind, ZshGhostOwner,
+        ZshOverlayBackend, CONFIG_ENV, LEGACY_CONFIG_ENV,
+    };
 
     fn env_lock() -> &'static Mutex<()> {
         static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
@@ -1150,4 +2082,237 @@ context:
         let err = config.validate().expect_err("validation should fail");
         assert!(err.to_string().contains("cache.stale_ratio"));
     }
+
+    #[test]
+    fn model_backends_inherit_primary_transport_settings() {
+        let yaml = r#"
+model:
+  endpoint: "http://localhost:11434/v1"
+  model_name: "codellama:7b"
+  timeout_ms: 1500
+  fallbacks:
+    - provider: anthropic
+      endpoint: "https://api.anthropic.com"
+      model_name: "claude-3-5-haiku-latest"
+      api_key_env: "ANTHROPIC_API_KEY"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).expect("config should parse");
+        config.validate().expect("config should validate");
+
+        let backends = config.model.backends();
+        assert_eq!(backends.len(), 2);
+        assert_eq!(backends[0].model_name, "codellama:7b");
+        assert!(backends[0].fallbacks.is_empty());
+        assert_eq!(backends[1].provider, ModelProvider::Anthropic);
+        assert_eq!(backends[1].timeout_ms, 1500);
+        assert_eq!(
+            backends[1].api_key_env.as_deref(),
+            Some("ANTHROPIC_API_KEY")
+        );
+    }
+
+    #[test]
+    fn validate_rejects_incomplete_fallback() {
+        let mut config = Config::default();
+        config.model.fallbacks.push(FallbackModelConfig {
+            endpoint: "https://api.openai.com/v1".to_string(),
+            ..FallbackModelConfig::default()
+        });
+
+        let err = config.validate().expect_err("validation should fail");
+        assert!(err.to_string().contains("model.fallbacks[0]"));
+    }
+
+    #[test]
+    fn api_key_source_follows_precedence_and_is_reported() {
+        let mut config = Config::default();
+        config.model.endpoint = "https://api.openai.com/v1".to_string();
+        assert!(config.validate_llm_config().is_err());
+
+        config.model.api_key_env = Some("NUDGE_TEST_UNSET_KEY_VAR".to_string());
+        config.model.api_key_file = Some(PathBuf::from("/nonexistent/nudge.key"));
+        assert!(config.validate_llm_config().is_err());
+
+        config.model.api_key_command = Some("pass show openai".to_string());
+        let source = config.validate_llm_config().unwrap();
+        assert_eq!(
+            source,
+            Some(ApiKeySource::Command("pass show openai".to_string()))
+        );
+        assert_eq!(
+            source.unwrap().to_string(),
+            "api_key_command `pass show openai`"
+        );
+
+        config.model.api_key = Some("sk-direct".to_string());
+        assert_eq!(config.model.api_key_source(), Some(ApiKeySource::Config));
+        assert!(!config.llm_config_summary().contains("sk-direct"));
+    }
+
+    #[test]
+    fn model_profile_overrides_only_set_fields() {
+        let mut config = Config::default();
+        config.model.api_key = Some("base-key".to_string());
+        config.model.temperature = Some(0.5);
+        let resolved = config.model.with_profile(&ModelProfileConfig {
+            provider: Some(ModelProvider::Ollama),
+            model_name: Some("qwen2.5-coder:1.5b".to_string()),
+            max_tokens: Some(48),
+            stream: Some(false),
+            ..ModelProfileConfig::default()
+        });
+
+        assert_eq!(resolved.provider, ModelProvider::Ollama);
+        assert_eq!(resolved.model_name, "qwen2.5-coder:1.5b");
+        assert_eq!(resolved.endpoint, config.model.endpoint);
+        // Another provider does not get the top-level key.
+        assert_eq!(resolved.api_key, None);
+        assert_eq!(resolved.timeout_ms, config.model.timeout_ms);
+        assert_eq!(resolved.temperature, Some(0.5));
+        assert_eq!(resolved.max_tokens, Some(48));
+        assert!(!resolved.stream);
+    }
+
+    #[test]
+    fn profile_key_sources_are_inherited_as_a_unit() {
+        let mut config = Config::default();
+        config.model.api_key = Some("base-key".to_string());
+        config.model.api_key_command = Some("pass show openai".to_string());
+
+        let resolved = config.model.with_profile(&ModelProfileConfig {
+            api_key_env: Some("LOCAL_LLM_KEY".to_string()),
+            ..ModelProfileConfig::default()
+        });
+        assert_eq!(resolved.api_key, None);
+        assert_eq!(resolved.api_key_command, None);
+        assert_eq!(
+            resolved.api_key_source(),
+            Some(ApiKeySource::Env("LOCAL_LLM_KEY".to_string()))
+        );
+
+        let resolved = config.model.with_profile(&ModelProfileConfig {
+            endpoint: Some("http://localhost:11434".to_string()),
+            ..ModelProfileConfig::default()
+        });
+        assert_eq!(resolved.api_key_source(), None);
+
+        let resolved = config.model.with_profile(&ModelProfileConfig {
+            model_name: Some("gpt-4o".to_string()),
+            ..ModelProfileConfig::default()
+        });
+        assert_eq!(resolved.api_key_source(), Some(ApiKeySource::Config));
+        assert_eq!(
+            resolved.api_key_command.as_deref(),
+            Some("pass show openai")
+        );
+    }
+
+    #[test]
+    fn model_routes_parse_from_yaml() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+model:
+  profiles:
+    fast:
+      model_name: "qwen2.5-coder:1.5b"
+      timeout_ms: 800
+  routes:
+    - shell_mode: zsh-auto
+      profile: fast
+    - kind: diagnosis
+      profile: default
+"#,
+        )
+        .unwrap();
+
+        config.validate().expect("routes should validate");
+        assert_eq!(config.model.routes.len(), 2);
+        assert_eq!(
+            config.model.routes[0].shell_mode.as_deref(),
+            Some("zsh-auto")
+        );
+        assert_eq!(config.model.routes[1].kind, Some(RequestKind::Diagnosis));
+        assert_eq!(config.model.profiles["fast"].timeout_ms, Some(800));
+    }
+
+    #[test]
+    fn validate_rejects_route_to_unknown_profile_or_mode() {
+        let mut config = Config::default();
+        config.model.routes.push(ModelRouteConfig {
+            profile: "missing".to_string(),
+            ..ModelRouteConfig::default()
+        });
+        let err = config.validate().expect_err("unknown profile should fail");
+        assert!(err.to_string().contains("unknown profile 'missing'"));
+
+        config.model.routes[0] = ModelRouteConfig {
+            shell_mode: Some("fish-auto".to_string()),
+            kind: None,
+            profile: "default".to_string(),
+        };
+        let err = config
+            .validate()
+            .expect_err("unknown shell mode should fail");
+        assert!(err.to_string().contains("model.routes[0].shell_mode"));
+    }
+
+    #[test]
+    fn validate_checks_usage_budget() {
+        let mut config = Config::default();
+        config.usage.budget.daily_tokens = Some(0);
+        let err = config.validate().expect_err("zero limit should fail");
+        assert!(err.to_string().contains("usage.budget.daily_tokens"));
+
+        config.usage.budget.daily_tokens = Some(50_000);
+        config.usage.budget.on_exceeded = BudgetAction::Fallback;
+        let err = config.validate().expect_err("fallback needs a profile");
+        assert!(err.to_string().contains("fallback_profile is required"));
+
+        config.usage.budget.fallback_profile = Some("local".to_string());
+        let err = config.validate().expect_err("unknown profile should fail");
+        assert!(err.to_string().contains("unknown profile 'local'"));
+
+        config
+            .model
+            .profiles
+            .insert("local".to_string(), ModelProfileConfig::default());
+        config.validate().expect("known fallback profile is valid");
+    }
+
+    #[test]
+    fn predictor_mode_parses_and_validates() {
+        let config: Config =
+            serde_yaml::from_str("predictor:\n  mode: fast-first\n  history_size: 0\n").unwrap();
+        assert_eq!(config.predictor.mode, PredictorMode::FastFirst);
+        let err = config.validate().expect_err("empty index should fail");
+        assert!(err.to_string().contains("predictor.history_size"));
+
+        let config: Config =
+            serde_yaml::from_str("predictor:\n  mode: off\n  history_size: 0\n").unwrap();
+        config
+            .validate()
+            .expect("disabled predictor is not checked");
+        assert_eq!(Config::default().predictor.mode, PredictorMode::Fallback);
+    }
+
+    #[test]
+    fn excluded_paths_parse_and_validate() {
+        let config: Config = serde_yaml::from_str(
+            "privacy:\n  local_only: true\n  excluded_paths:\n    - path: \"~/clients/**\"\n      mode: off\n    - path: /srv\n      no_history: true\n",
+        )
+        .unwrap();
+        assert!(config.privacy.local_only);
+        assert_eq!(config.privacy.excluded_paths[0].mode, ExcludedMode::Off);
+        assert_eq!(
+            config.privacy.excluded_paths[1].mode,
+            ExcludedMode::BufferOnly
+        );
+        assert!(config.privacy.excluded_paths[1].no_history);
+        config.validate().expect("excluded paths are valid");
+
+        let config: Config =
+            serde_yaml::from_str("privacy:\n  excluded_paths:\n    - mode: off\n").unwrap();
+        let err = config.validate().expect_err("empty path should fail");
+        assert!(err.to_string().contains("privacy.excluded_paths[0].path"));
+    }
 }
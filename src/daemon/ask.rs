@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::daemon::context::{self, ContextData};
+use crate::daemon::llm::provider::{ChatRequest, ResponseSchema};
+use crate::daemon::llm::router::Route;
+use crate::daemon::llm::{self, retry, CandidateDraft, Prepared};
+use crate::daemon::shell_mode::ShellMode;
+use crate::daemon::{prompts, sanitizer, tokens};
+
+/// Same contract as the system prompt, enforced by backends with structured output
+fn ask_schema() -> ResponseSchema {
+    ResponseSchema {
+        name: "command_candidates",
+        description: "Shell commands that perform the described task",
+        schema: json!({
+            "type": "object",
+            "properties": {
+                "candidates": {
+                    "type": "array",
+                    "description": "Commands ordered by how well they fit (best first)",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "command": {"type": "string", "description": "Single command line"},
+                            "summary_short": {
+                                "type": ["string", "null"],
+                                "description": "What the command does"
+                            },
+                            "reason_short": {
+                                "type": ["string", "null"],
+                                "description": "Why it fits the task"
+                            }
+                        },
+                        "required": ["command", "summary_short", "reason_short"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["candidates"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Ask the LLM for commands that perform a task description
+pub async fn ask(
+    request: &ChatRequest,
+    config: &Config,
+    route: &Route<'_>,
+) -> Result<Vec<CandidateDraft>> {
+    debug!("Ask prompt: {}", request.user);
+
+    let timeout_ms = route.timeout_ms(config);
+
+    debug!("Ask routed to model profile '{}'", route.profile);
+    let response = retry::run(&route.retry, Duration::from_millis(timeout_ms), |timeout| {
+        route.provider.chat(request, timeout)
+    })
+    .await
+    .context("Failed to send ask request")?;
+    route.record_usage(request, &response);
+
+    let candidates = llm::parse_candidates(&response.text);
+    if response.structured && candidates.is_empty() {
+        anyhow::bail!("LLM response does not match the candidates schema");
+    }
+    Ok(candidates)
+}
+
+/// Request `ask` sends for `query`, with `context` truncated until the
+/// prompt fits `context.max_total_tokens`
+pub fn build_request(
+    query: &str,
+    context: &ContextData,
+    config: &Config,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+) -> Prepared<ChatRequest> {
+    let model = route.provider.model();
+    let counter = tokens::counter(&config.context.tokenizer, &model.model_name);
+    let (mut request, mut summary) = context::fit_prompt(
+        context,
+        config,
+        |context| ChatRequest {
+            system: prompts::ask::system_prompt(config.ask.max_candidates),
+            user: prompts::ask::user_prompt(query, context, shell_mode),
+            max_tokens: route.settings.max_tokens.unwrap_or(400),
+            temperature: route.settings.temperature.unwrap_or(0.2),
+            schema: model.structured_output.then(ask_schema),
+        },
+        |request| tokens::request_tokens(counter.as_ref(), request),
+    );
+    sanitizer::sanitize_prepared(&mut request, &mut summary, &config.privacy);
+    Prepared { request, summary }
+}
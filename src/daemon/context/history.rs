@@ -44,6 +44,23 @@ pub fn read_history(session_id: &str, window_size: usize) -> Result<Vec<String>>
     Ok(limited)
 }
 
+/// Read the last `limit` history entries, repeats included (oldest first)
+pub fn read_entries(session_id: &str, limit: usize) -> Result<Vec<String>> {
+    let history_path = get_history_path(session_id)?;
+    let bytes = match fs::read(&history_path) {
+        Ok(b) => b,
+        Err(e) => {
+            debug!("Cannot read history file {}: {}", history_path.display(), e);
+            return Ok(Vec::new());
+        }
+    };
+    let contents = String::from_utf8_lossy(&bytes).into_owned();
+    let mut entries = parse_history(&contents, detect_shell_type(session_id));
+    let skip = entries.len().saturating_sub(limit);
+    entries.drain(..skip);
+    Ok(entries)
+}
+
 /// Find similar commands from history based on query string
 pub fn find_similar_commands(
     session_id: &str,
@@ -164,7 +181,7 @@ enum ShellType {
 }
 
 /// Get the history file path
-fn get_history_path(session_id: &str) -> Result<PathBuf> {
+pub fn get_history_path(session_id: &str) -> Result<PathBuf> {
     let user_dirs = UserDirs::new().context("Failed to get user directories")?;
     let home = user_dirs.home_dir();
 
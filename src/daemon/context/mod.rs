@@ -2,6 +2,7 @@ pub mod cwd;
 pub mod history;
 pub mod plugin;
 pub mod system;
+pub mod untrusted;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -9,10 +10,14 @@ use std::path::PathBuf;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tracing::debug;
 
 use super::plugins::builtin::git::GitContext;
+use super::policy::AppliedPolicy;
 use crate::config::Config;
-use crate::protocol::{CompletionRequest, DiagnosisRequest};
+use crate::protocol::{
+    AskRequest, CompletionRequest, ContextSummary, DiagnosisRequest, ExplainRequest,
+};
 use system::SystemInfo;
 
 /// Aggregated context data
@@ -36,14 +41,27 @@ pub struct ContextData {
     /// Plugin context data (new unified field)
     #[serde(default)]
     pub plugins: HashMap<String, Value>,
-    /// Estimated token count
-    pub estimated_tokens: usize,
 }
 
 impl ContextData {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Summary of this context in a prompt of `total_tokens` tokens
+    pub fn summary(&self, total_tokens: usize, truncated: bool) -> ContextSummary {
+        let mut plugins_used: Vec<String> = self.plugins.keys().cloned().collect();
+        plugins_used.sort();
+        ContextSummary {
+            history_count: Some(self.history.len()),
+            files_count: Some(self.files.len()),
+            plugins_used: Some(plugins_used),
+            total_tokens: Some(total_tokens),
+            truncated: Some(truncated),
+            sanitized_count: None,
+            sanitized_fields: None,
+        }
+    }
 }
 
 /// Common parameters for context gathering (shared between completion and diagnosis)
@@ -89,8 +107,40 @@ impl From<&DiagnosisRequest> for GatherParams {
     }
 }
 
-/// Gather all context for completion or diagnosis
+impl From<&AskRequest> for GatherParams {
+    fn from(req: &AskRequest) -> Self {
+        Self {
+            session_id: req.session_id.clone(),
+            cwd: req.cwd.clone(),
+            command: req.query.clone(),
+            last_exit_code: None,
+            include_similar_commands: false,
+            include_legacy_git: false,
+        }
+    }
+}
+
+impl From<&ExplainRequest> for GatherParams {
+    fn from(req: &ExplainRequest) -> Self {
+        Self {
+            session_id: req.session_id.clone(),
+            cwd: req.cwd.clone(),
+            command: req.command.clone(),
+            last_exit_code: None,
+            include_similar_commands: false,
+            include_legacy_git: false,
+        }
+    }
+}
+
+/// Gather all context for completion, diagnosis, ask or explain requests
 pub async fn gather(params: &GatherParams, config: &Config) -> Result<ContextData> {
+    // Excluded paths may send the buffer alone
+    let policy = AppliedPolicy::resolve(&config.privacy, &params.cwd);
+    if !policy.sends_context() {
+        return Ok(ContextData::new());
+    }
+
     let mut context = ContextData::new();
     context.cwd = params.cwd.clone();
 
@@ -100,11 +150,14 @@ pub async fn gather(params: &GatherParams, config: &Config) -> Result<ContextDat
     }
 
     // Gather history
-    let history = history::read_history(&params.session_id, config.context.history_window)?;
-    context.history = history;
+    if !policy.no_history {
+        let history = history::read_history(&params.session_id, config.context.history_window)?;
+        context.history = history;
+    }
 
     // Gather similar commands (if enabled, requested, and command is long enough)
-    if params.include_similar_commands
+    if !policy.no_history
+        && params.include_similar_commands
         && config.context.similar_commands_enabled
         && params.command.len() >= 3
     {
@@ -118,7 +171,7 @@ pub async fn gather(params: &GatherParams, config: &Config) -> Result<ContextDat
     }
 
     // Gather CWD listing
-    if config.context.include_cwd_listing {
+    if config.context.include_cwd_listing && !policy.no_files {
         let files = cwd::list_files(&params.cwd, config.context.max_files_in_listing)?;
         context.files = files;
     }
@@ -150,49 +203,44 @@ pub async fn gather(params: &GatherParams, config: &Config) -> Result<ContextDat
         }
     }
 
-    // Estimate tokens
-    context.estimated_tokens = estimate_tokens(&context);
-
-    // Truncate if necessary
-    if context.estimated_tokens > config.context.max_total_tokens {
-        truncate_by_priority(&mut context, config);
-    }
-
+    untrusted::harden(&mut context, config.context.max_item_chars);
     Ok(context)
 }
 
-/// Estimate token count (word-based approximation)
-fn estimate_tokens(context: &ContextData) -> usize {
-    let mut total = 0;
-
-    // System info: fixed cost (~40 tokens)
-    total += 40;
-
-    // History: count words × 1.3
-    for cmd in &context.history {
-        total += (cmd.split_whitespace().count() as f32 * 1.3).ceil() as usize;
+/// Render a prompt from `context`, dropping context by priority until the
+/// prompt fits `context.max_total_tokens` as counted by `measure`.
+pub fn fit_prompt<R>(
+    context: &ContextData,
+    config: &Config,
+    render: impl Fn(&ContextData) -> R,
+    measure: impl Fn(&R) -> usize,
+) -> (R, ContextSummary) {
+    let rendered = render(context);
+    let tokens = measure(&rendered);
+    if tokens <= config.context.max_total_tokens {
+        return (rendered, context.summary(tokens, false));
     }
 
-    // Similar commands: count words × 1.3
-    for cmd in &context.similar_commands {
-        total += (cmd.split_whitespace().count() as f32 * 1.3).ceil() as usize;
-    }
-
-    // Files: roughly 1 token per file name
-    total += context.files.len();
-
-    // Plugin context: estimate based on JSON size (rough approximation)
-    for data in context.plugins.values() {
-        // Conservative estimate: JSON string length / 4 characters per token
-        let json_str = serde_json::to_string(data).unwrap_or_default();
-        total += json_str.len() / 4;
-    }
-
-    total
+    let mut fitted = context.clone();
+    let mut last = rendered;
+    let tokens = truncate_by_priority(&mut fitted, config, tokens, |context| {
+        last = render(context);
+        measure(&last)
+    });
+    debug!(
+        "Context truncated to fit {} prompt tokens: {} tokens",
+        config.context.max_total_tokens, tokens
+    );
+    (last, fitted.summary(tokens, true))
 }
 
-/// Truncate context by priority
-fn truncate_by_priority(context: &mut ContextData, config: &Config) {
+/// Truncate context by priority; returns the final token count.
+fn truncate_by_priority(
+    context: &mut ContextData,
+    config: &Config,
+    mut tokens: usize,
+    mut measure: impl FnMut(&ContextData) -> usize,
+) -> usize {
     let priorities = &config.context.priorities;
     let max_tokens = config.context.max_total_tokens;
 
@@ -204,16 +252,17 @@ fn truncate_by_priority(context: &mut ContextData, config: &Config) {
     // 4. History (80)
 
     // Truncate in order from lowest to highest priority
-    while context.estimated_tokens > max_tokens {
-        let before_tokens = context.estimated_tokens;
+    while tokens > max_tokens {
+        let before_tokens = tokens;
 
         // First: Remove plugin contexts (priority ~40-50, lowest)
-        if !context.plugins.is_empty() && priorities.plugins <= priorities.cwd_listing {
-            if remove_lowest_priority_plugin(context, config) {
-                context.estimated_tokens = estimate_tokens(context);
-                if context.estimated_tokens != before_tokens {
-                    continue;
-                }
+        if !context.plugins.is_empty()
+            && priorities.plugins <= priorities.cwd_listing
+            && remove_lowest_priority_plugin(context, config)
+        {
+            tokens = measure(context);
+            if tokens != before_tokens {
+                continue;
             }
         }
 
@@ -222,8 +271,8 @@ fn truncate_by_priority(context: &mut ContextData, config: &Config) {
             let half = context.files.len() / 2;
             if half > 0 {
                 context.files.truncate(half);
-                context.estimated_tokens = estimate_tokens(context);
-                if context.estimated_tokens != before_tokens {
+                tokens = measure(context);
+                if tokens != before_tokens {
                     continue;
                 }
             }
@@ -234,14 +283,14 @@ fn truncate_by_priority(context: &mut ContextData, config: &Config) {
             let half = context.similar_commands.len() / 2;
             if half > 0 {
                 context.similar_commands.truncate(half);
-                context.estimated_tokens = estimate_tokens(context);
-                if context.estimated_tokens != before_tokens {
+                tokens = measure(context);
+                if tokens != before_tokens {
                     continue;
                 }
             } else if context.similar_commands.len() == 1 {
                 context.similar_commands.clear();
-                context.estimated_tokens = estimate_tokens(context);
-                if context.estimated_tokens != before_tokens {
+                tokens = measure(context);
+                if tokens != before_tokens {
                     continue;
                 }
             }
@@ -252,18 +301,19 @@ fn truncate_by_priority(context: &mut ContextData, config: &Config) {
             let half = context.history.len() / 2;
             if half > 0 {
                 context.history = context.history.split_off(context.history.len() - half);
-                context.estimated_tokens = estimate_tokens(context);
-                if context.estimated_tokens != before_tokens {
+                tokens = measure(context);
+                if tokens != before_tokens {
                     continue;
                 }
             }
         }
 
         // If nothing changed, break to avoid infinite loop
-        if context.estimated_tokens == before_tokens {
+        if tokens == before_tokens {
             break;
         }
     }
+    tokens
 }
 
 fn remove_lowest_priority_plugin(context: &mut ContextData, config: &Config) -> bool {
@@ -378,3 +428,43 @@ fn create_plugin_manager(config: &Config) -> plugin::PluginManager {
             config.plugins.python.priority.unwrap_or(45),
         )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{fit_prompt, ContextData};
+    use crate::config::Config;
+    use serde_json::json;
+
+    #[test]
+    fn fit_prompt_truncates_by_priority_until_the_prompt_fits() {
+        let context = ContextData {
+            history: (0..8).map(|i| format!("cmd{}", i)).collect(),
+            files: (0..8).map(|i| format!("file{}", i)).collect(),
+            plugins: [("git".to_string(), json!({"branch": "main"}))].into(),
+            ..ContextData::default()
+        };
+        let render = |context: &ContextData| {
+            let mut parts = context.history.clone();
+            parts.extend(context.files.iter().cloned());
+            parts.extend(context.plugins.keys().cloned());
+            parts
+        };
+        let measure = |parts: &Vec<String>| parts.len();
+
+        let mut config = Config::default();
+        config.context.max_total_tokens = 100;
+        let (rendered, summary) = fit_prompt(&context, &config, render, measure);
+        assert_eq!(rendered.len(), 17);
+        assert_eq!(summary.total_tokens, Some(17));
+        assert_eq!(summary.truncated, Some(false));
+
+        // Plugins go first, then half of the files.
+        config.context.max_total_tokens = 12;
+        let (rendered, summary) = fit_prompt(&context, &config, render, measure);
+        assert_eq!(rendered.len(), 12);
+        assert_eq!(summary.truncated, Some(true));
+        assert_eq!(summary.files_count, Some(4));
+        assert_eq!(summary.history_count, Some(8));
+        assert_eq!(summary.plugins_used, Some(Vec::new()));
+    }
+}
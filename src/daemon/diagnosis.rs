@@ -1,50 +1,17 @@
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_json::json;
 use tracing::debug;
 
 use crate::config::Config;
-use crate::daemon::context::ContextData;
-
-const DIAGNOSIS_SYSTEM_PROMPT: &str = r#"You are a CLI error diagnosis assistant. Analyze the failed command and provide a fix.
-
-Rules:
-1. Return ONLY a JSON object with "diagnosis" and "suggestion" fields
-2. The "diagnosis" should be a brief (1-2 sentence) explanation starting with an emoji (❌, 💡, ⚠️)
-3. The "suggestion" should be the single most likely correct command to fix the error
-4. If you cannot determine a fix, set "suggestion" to null
-5. Do not explain or add commentary outside the JSON
-6. Focus on common issues: typos, missing arguments, wrong paths, permission errors
-
-Example response:
-{"diagnosis": "❌ Typo: 'gti' should be 'git'", "suggestion": "git status"}"#;
-
-#[derive(Debug, Serialize)]
-struct ChatRequest {
-    model: String,
-    messages: Vec<Message>,
-    max_tokens: u32,
-    temperature: f32,
-    stream: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Message {
-    role: String,
-    content: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct ChatResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Choice {
-    message: Message,
-}
+use crate::daemon::context::{self, ContextData};
+use crate::daemon::llm::provider::{ChatRequest, ResponseSchema};
+use crate::daemon::llm::retry;
+use crate::daemon::llm::router::Route;
+use crate::daemon::llm::Prepared;
+use crate::daemon::{prompts, sanitizer, tokens};
 
 #[derive(Debug, Deserialize)]
 struct DiagnosisResult {
@@ -52,6 +19,29 @@ struct DiagnosisResult {
     suggestion: Option<String>,
 }
 
+/// Same contract as the system prompt, enforced by backends with structured output
+fn diagnosis_schema() -> ResponseSchema {
+    ResponseSchema {
+        name: "command_diagnosis",
+        description: "Explain the failed command and suggest a fix",
+        schema: json!({
+            "type": "object",
+            "properties": {
+                "diagnosis": {
+                    "type": "string",
+                    "description": "1-2 sentence explanation starting with an emoji"
+                },
+                "suggestion": {
+                    "type": ["string", "null"],
+                    "description": "Most likely correct command, or null"
+                }
+            },
+            "required": ["diagnosis", "suggestion"],
+            "additionalProperties": false
+        }),
+    }
+}
+
 /// Diagnose a failed command using LLM
 pub async fn diagnose(
     command: &str,
@@ -60,188 +50,86 @@ pub async fn diagnose(
     error_record: Option<&serde_json::Value>,
     context: &ContextData,
     config: &Config,
+    route: &Route<'_>,
 ) -> Result<(String, Option<String>)> {
-    let client = Client::builder()
-        .timeout(Duration::from_millis(config.diagnosis.timeout_ms))
-        .build()?;
-
-    let user_prompt = build_diagnosis_prompt(command, exit_code, stderr, error_record, context);
-
-    debug!("Diagnosis prompt: {}", user_prompt);
-
-    let request = ChatRequest {
-        model: config.model.model_name.clone(),
-        messages: vec![
-            Message {
-                role: "system".to_string(),
-                content: DIAGNOSIS_SYSTEM_PROMPT.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt,
-            },
-        ],
-        max_tokens: 200,
-        temperature: 0.2,
-        stream: false,
-    };
-
-    let mut req_builder = client
-        .post(format!("{}/chat/completions", config.model.endpoint))
-        .json(&request);
-
-    // Add API key
-    if let Some(api_key) = &config.model.api_key {
-        req_builder = req_builder.header("Authorization", format!("Bearer {}", api_key));
-    } else if let Some(api_key_env) = &config.model.api_key_env {
-        if let Ok(api_key) = std::env::var(api_key_env) {
-            req_builder = req_builder.header("Authorization", format!("Bearer {}", api_key));
-        }
-    }
-
-    let response = req_builder
-        .send()
-        .await
-        .context("Failed to send diagnosis request")?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        anyhow::bail!("LLM request failed with status {}: {}", status, body);
+    let request = build_request(
+        command,
+        exit_code,
+        stderr,
+        error_record,
+        context,
+        config,
+        route,
+    )
+    .request;
+
+    debug!("Diagnosis prompt: {}", request.user);
+
+    let timeout_ms = route.timeout_ms(config);
+
+    debug!("Diagnosis routed to model profile '{}'", route.profile);
+    let response = retry::run(&route.retry, Duration::from_millis(timeout_ms), |timeout| {
+        route.provider.chat(&request, timeout)
+    })
+    .await
+    .context("Failed to send diagnosis request")?;
+    route.record_usage(&request, &response);
+
+    if response.structured {
+        return parse_structured_diagnosis(&response.text)
+            .context("LLM response does not match the diagnosis schema");
     }
-
-    let completion: ChatResponse = response
-        .json()
-        .await
-        .context("Failed to parse LLM response")?;
-
-    let text = completion
-        .choices
-        .first()
-        .map(|c| c.message.content.clone())
-        .unwrap_or_default();
-
     // Parse JSON response
-    parse_diagnosis_response(&text)
+    parse_diagnosis_response(&response.text)
 }
 
-fn build_diagnosis_prompt(
+/// Request `diagnose` sends for a failed command, with `context` truncated
+/// until the prompt fits `context.max_total_tokens`
+pub fn build_request(
     command: &str,
     exit_code: i32,
     stderr: Option<&str>,
     error_record: Option<&serde_json::Value>,
     context: &ContextData,
-) -> String {
-    let mut prompt = String::new();
-
-    prompt.push_str(&format!("## Failed Command\n```\n{}\n```\n\n", command));
-    prompt.push_str(&format!("## Exit Code\n{}\n\n", exit_code));
-
-    if let Some(stderr) = stderr {
-        if !stderr.is_empty() {
-            prompt.push_str("## Error Output (stderr)\n```\n");
-            // Truncate if too long
-            if stderr.len() > 2000 {
-                prompt.push_str(&stderr[..2000]);
-                prompt.push_str("\n... (truncated)");
-            } else {
-                prompt.push_str(stderr);
-            }
-            prompt.push_str("\n```\n\n");
-        }
-    }
-
-    if let Some(record) = error_record {
-        prompt.push_str("## PowerShell Error Record\n```json\n");
-        prompt.push_str(&serde_json::to_string_pretty(record).unwrap_or_default());
-        prompt.push_str("\n```\n\n");
-    }
-
-    // Add system information
-    if !context.system.os_type.is_empty() {
-        prompt.push_str(&format!(
-            "## System\n{} {} ({})\n\n",
-            context.system.os_type, context.system.os_version, context.system.arch
-        ));
-    }
-
-    // Add context
-    prompt.push_str(&format!(
-        "## Current Directory\n{}\n\n",
-        context.cwd.display()
-    ));
-
-    if !context.files.is_empty() {
-        prompt.push_str("## Files in Directory\n");
-        prompt.push_str(&context.files.join(", "));
-        prompt.push_str("\n\n");
-    }
-
-    if !context.history.is_empty() {
-        prompt.push_str("## Recent Commands\n");
-        for cmd in context.history.iter().take(10) {
-            prompt.push_str(&format!("- {}\n", cmd));
-        }
-        prompt.push('\n');
-    }
-
-    // Add Git context if available
-    if let Some(git) = &context.git {
-        prompt.push_str("## Git Status\n");
-        if let Some(branch) = &git.branch {
-            prompt.push_str(&format!("Branch: {}\n", branch));
-        }
-        if !git.staged.is_empty() {
-            prompt.push_str(&format!("Staged files: {}\n", git.staged.join(", ")));
-        }
-        if !git.unstaged.is_empty() {
-            prompt.push_str(&format!("Modified files: {}\n", git.unstaged.join(", ")));
-        }
-        if !git.local_branches.is_empty() {
-            prompt.push_str(&format!(
-                "Local branches: {}\n",
-                git.local_branches.join(", ")
-            ));
-        }
-        prompt.push('\n');
-    }
-
-    // Add plugin context (excluding git which is handled above)
-    for (plugin_id, data) in &context.plugins {
-        if plugin_id == "git" {
-            continue; // Already handled above
-        }
-        prompt.push_str(&format!(
-            "## {} Project Context\n",
-            capitalize_first(plugin_id)
-        ));
-        // Format plugin data as compact JSON
-        if let Ok(json_str) = serde_json::to_string_pretty(data) {
-            // Truncate if too long
-            if json_str.len() > 500 {
-                prompt.push_str(&json_str[..500]);
-                prompt.push_str("\n... (truncated)");
-            } else {
-                prompt.push_str(&json_str);
-            }
-        }
-        prompt.push_str("\n\n");
-    }
-
-    prompt.push_str(
-        "Analyze the error and respond with JSON only: {\"diagnosis\": \"...\", \"suggestion\": \"...\"}",
+    config: &Config,
+    route: &Route<'_>,
+) -> Prepared<ChatRequest> {
+    let model = route.provider.model();
+    let counter = tokens::counter(&config.context.tokenizer, &model.model_name);
+    let (mut request, mut summary) = context::fit_prompt(
+        context,
+        config,
+        |context| ChatRequest {
+            system: prompts::diagnosis::system_prompt(),
+            user: prompts::diagnosis::user_prompt(
+                command,
+                exit_code,
+                stderr,
+                error_record,
+                context,
+            ),
+            max_tokens: route.settings.max_tokens.unwrap_or(200),
+            temperature: route.settings.temperature.unwrap_or(0.2),
+            schema: model.structured_output.then(diagnosis_schema),
+        },
+        |request| tokens::request_tokens(counter.as_ref(), request),
     );
-
-    prompt
+    sanitizer::sanitize_prepared(&mut request, &mut summary, &config.privacy);
+    Prepared { request, summary }
 }
 
-/// Capitalize first letter of a string
-fn capitalize_first(s: &str) -> String {
-    let mut chars = s.chars();
-    match chars.next() {
-        None => String::new(),
-        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+/// Validate an answer generated under [`diagnosis_schema`].
+fn parse_structured_diagnosis(text: &str) -> Result<(String, Option<String>)> {
+    let result: DiagnosisResult = serde_json::from_str(text.trim())?;
+    let diagnosis = result.diagnosis.trim();
+    if diagnosis.is_empty() {
+        anyhow::bail!("diagnosis is empty");
     }
+    let suggestion = result
+        .suggestion
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    Ok((diagnosis.to_string(), suggestion))
 }
 
 fn parse_diagnosis_response(text: &str) -> Result<(String, Option<String>)> {
@@ -269,3 +157,39 @@ fn parse_diagnosis_response(text: &str) -> Result<(String, Option<String>)> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_diagnosis_requires_the_contract() {
+        let (diagnosis, suggestion) = parse_structured_diagnosis(
+            r#"{"diagnosis":"❌ Typo: 'gti' should be 'git'","suggestion":"git status"}"#,
+        )
+        .unwrap();
+        assert_eq!(diagnosis, "❌ Typo: 'gti' should be 'git'");
+        assert_eq!(suggestion.as_deref(), Some("git status"));
+
+        let (_, suggestion) =
+            parse_structured_diagnosis(r#"{"diagnosis":"💡 Unknown","suggestion":null}"#).unwrap();
+        assert_eq!(suggestion, None);
+
+        assert!(parse_structured_diagnosis("Command not found").is_err());
+        assert!(parse_structured_diagnosis(r#"{"diagnosis":"","suggestion":null}"#).is_err());
+    }
+
+    #[test]
+    fn lenient_parser_extracts_embedded_json() {
+        let (diagnosis, suggestion) = parse_diagnosis_response(
+            r#"Sure: {"diagnosis":"❌ Missing file","suggestion":"ls"} hope that helps"#,
+        )
+        .unwrap();
+        assert_eq!(diagnosis, "❌ Missing file");
+        assert_eq!(suggestion.as_deref(), Some("ls"));
+
+        let (diagnosis, suggestion) = parse_diagnosis_response("Permission denied").unwrap();
+        assert_eq!(diagnosis, "Permission denied");
+        assert_eq!(suggestion, None);
+    }
+}
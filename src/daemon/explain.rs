@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::daemon::context::{self, ContextData};
+use crate::daemon::llm::provider::{ChatRequest, ResponseSchema};
+use crate::daemon::llm::retry;
+use crate::daemon::llm::router::Route;
+use crate::daemon::llm::Prepared;
+use crate::daemon::shell_mode::ShellMode;
+use crate::daemon::shell_words::{self, WordKind};
+use crate::daemon::{prompts, sanitizer, tokens};
+use crate::protocol::{CommandPart, TextRange};
+
+/// What the LLM contributes to an explanation
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct ExplainDraft {
+    pub summary: String,
+    #[serde(default)]
+    pub parts: Vec<PartExplanation>,
+    #[serde(default)]
+    pub side_effects: Vec<String>,
+}
+
+/// Explanation of the part at `index` of the command
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct PartExplanation {
+    pub index: usize,
+    pub explanation: String,
+}
+
+impl ExplainDraft {
+    /// Attach the part explanations to `parts`; unknown indexes are dropped
+    pub fn annotate(&self, parts: &mut [CommandPart]) {
+        for explained in &self.parts {
+            let explanation = explained.explanation.trim();
+            if let Some(part) = parts.get_mut(explained.index) {
+                if !explanation.is_empty() {
+                    part.explanation = Some(explanation.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Same contract as the system prompt, enforced by backends with structured output
+fn explain_schema() -> ResponseSchema {
+    ResponseSchema {
+        name: "command_explanation",
+        description: "Explain what a command line does before it is run",
+        schema: json!({
+            "type": "object",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "What the whole command does in 1-2 sentences"
+                },
+                "parts": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "index": {"type": "integer", "description": "Index of the part"},
+                            "explanation": {"type": "string", "description": "What the part does"}
+                        },
+                        "required": ["index", "explanation"],
+                        "additionalProperties": false
+                    }
+                },
+                "side_effects": {
+                    "type": "array",
+                    "description": "Changes made outside the command's output",
+                    "items": {"type": "string"}
+                }
+            },
+            "required": ["summary", "parts", "side_effects"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Words and operators of `command` with their byte and character ranges
+pub fn command_parts(command: &str) -> Vec<CommandPart> {
+    shell_words::split(command)
+        .into_iter()
+        .map(|word| {
+            let start_char = command[..word.range.start].chars().count();
+            let end_char = start_char + word.text.chars().count();
+            CommandPart {
+                range: TextRange {
+                    start: word.range.start,
+                    end: word.range.end,
+                },
+                char_range: TextRange {
+                    start: start_char,
+                    end: end_char,
+                },
+                operator: word.kind == WordKind::Operator,
+                text: word.text,
+                explanation: None,
+            }
+        })
+        .collect()
+}
+
+/// Part texts as sent to the LLM, one per entry of `parts` so indexes still
+/// match. With `privacy.sanitize_enabled` they are cut from the sanitized
+/// `command`, so a value recognised by the word before it (`-p hunter2`) is
+/// redacted too; a part swallowed by the replacement becomes `[REDACTED]`.
+pub fn prompt_parts(command: &str, parts: &[CommandPart], config: &Config) -> Vec<String> {
+    if !config.privacy.sanitize_enabled {
+        return parts.iter().map(|part| part.text.clone()).collect();
+    }
+    let spans: Vec<(usize, usize)> = parts
+        .iter()
+        .map(|part| (part.range.start, part.range.end))
+        .collect();
+    let mut redactor = sanitizer::Redactor::new(&config.privacy);
+    let (sanitized, spans) = redactor.spans("command", command, &spans);
+    parts
+        .iter()
+        .zip(spans)
+        .map(|(part, (start, end))| match sanitized.get(start..end) {
+            Some("") if !part.text.is_empty() => "[REDACTED]".to_string(),
+            Some(text) => text.to_string(),
+            None => "[REDACTED]".to_string(),
+        })
+        .collect()
+}
+
+/// Ask the LLM what a command does
+pub async fn explain(
+    request: &ChatRequest,
+    config: &Config,
+    route: &Route<'_>,
+) -> Result<ExplainDraft> {
+    debug!("Explain prompt: {}", request.user);
+
+    let timeout_ms = route.timeout_ms(config);
+
+    debug!("Explain routed to model profile '{}'", route.profile);
+    let response = retry::run(&route.retry, Duration::from_millis(timeout_ms), |timeout| {
+        route.provider.chat(request, timeout)
+    })
+    .await
+    .context("Failed to send explain request")?;
+    route.record_usage(request, &response);
+
+    if response.structured {
+        return parse_structured_explanation(&response.text)
+            .context("LLM response does not match the explanation schema");
+    }
+    Ok(parse_explanation(&response.text))
+}
+
+/// Request `explain` sends for `command` split into `parts`, with `context`
+/// truncated until the prompt fits `context.max_total_tokens`
+pub fn build_request(
+    command: &str,
+    parts: &[String],
+    context: &ContextData,
+    config: &Config,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+) -> Prepared<ChatRequest> {
+    let model = route.provider.model();
+    let counter = tokens::counter(&config.context.tokenizer, &model.model_name);
+    let (mut request, mut summary) = context::fit_prompt(
+        context,
+        config,
+        |context| ChatRequest {
+            system: prompts::explain::system_prompt(),
+            user: prompts::explain::user_prompt(command, parts, context, shell_mode),
+            max_tokens: route.settings.max_tokens.unwrap_or(600),
+            temperature: route.settings.temperature.unwrap_or(0.2),
+            schema: model.structured_output.then(explain_schema),
+        },
+        |request| tokens::request_tokens(counter.as_ref(), request),
+    );
+    sanitizer::sanitize_prepared(&mut request, &mut summary, &config.privacy);
+    Prepared { request, summary }
+}
+
+/// Validate an answer generated under [`explain_schema`].
+fn parse_structured_explanation(text: &str) -> Result<ExplainDraft> {
+    let mut draft: ExplainDraft = serde_json::from_str(text.trim())?;
+    draft.summary = draft.summary.trim().to_string();
+    if draft.summary.is_empty() {
+        anyhow::bail!("summary is empty");
+    }
+    Ok(draft)
+}
+
+/// Extract the JSON object from a free-form answer; an answer without one
+/// becomes the summary.
+fn parse_explanation(text: &str) -> ExplainDraft {
+    let text = text.trim();
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    };
+    serde_json::from_str::<ExplainDraft>(json)
+        .ok()
+        .filter(|draft| !draft.summary.trim().is_empty())
+        .unwrap_or_else(|| ExplainDraft {
+            summary: text.to_string(),
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_carry_byte_and_char_ranges() {
+        let parts = command_parts("echo 'héllo' > out.txt");
+        let texts: Vec<&str> = parts.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, vec!["echo", "'héllo'", ">", "out.txt"]);
+        assert_eq!(parts[3].range, TextRange { start: 16, end: 23 });
+        assert_eq!(parts[3].char_range, TextRange { start: 15, end: 22 });
+        assert!(parts[2].operator);
+        assert!(!parts[1].operator);
+    }
+
+    #[test]
+    fn prompt_parts_redact_values_named_by_the_word_before() {
+        let config = Config::default();
+        let command = "mysql -u root -p hunter2 --password x db";
+        let parts = command_parts(command);
+        let texts = prompt_parts(command, &parts, &config);
+        assert_eq!(texts.len(), parts.len());
+        assert!(!texts.iter().any(|text| text.contains("hunter2")));
+        assert!(!texts.iter().any(|text| text == "x"));
+        assert_eq!(texts[0], "mysql");
+        assert_eq!(texts.last().map(String::as_str), Some("db"));
+
+        let command = "curl -H 'Authorization: Bearer abc123' https://example.com";
+        let parts = command_parts(command);
+        let texts = prompt_parts(command, &parts, &config);
+        assert!(!texts.iter().any(|text| text.contains("abc123")));
+        assert_eq!(texts[3], "https://example.com");
+    }
+
+    #[test]
+    fn explanations_attach_to_parts_by_index() {
+        let draft = parse_structured_explanation(
+            r#"{"summary":"Push main to origin","parts":[{"index":0,"explanation":"Git CLI"},{"index":1,"explanation":"Upload commits"},{"index":9,"explanation":"stray"}],"side_effects":["Updates origin/main"]}"#,
+        )
+        .unwrap();
+        assert_eq!(draft.side_effects, vec!["Updates origin/main"]);
+
+        let mut parts = command_parts("git push origin main");
+        draft.annotate(&mut parts);
+        assert_eq!(parts[1].explanation.as_deref(), Some("Upload commits"));
+        assert_eq!(parts[3].explanation, None);
+
+        assert!(parse_structured_explanation("Pushes commits").is_err());
+    }
+
+    #[test]
+    fn lenient_parser_falls_back_to_summary() {
+        let draft = parse_explanation(
+            "Here you go: {\"summary\":\"Lists files\",\"parts\":[{\"index\":0,\"explanation\":\"List\"}]}",
+        );
+        assert_eq!(draft.summary, "Lists files");
+        assert_eq!(draft.parts.len(), 1);
+        assert!(draft.side_effects.is_empty());
+
+        let draft = parse_explanation("Lists the files in the current directory.");
+        assert_eq!(draft.summary, "Lists the files in the current directory.");
+        assert!(draft.parts.is_empty());
+    }
+}
@@ -0,0 +1,546 @@
+//! Anthropic Messages API (`/v1/messages`) backend.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::provider::{
+    join_url, parse_retry_after, resolve_api_key, truncate_message, ChatRequest, ChatResponse,
+    LlmProvider, ProviderError, ResponseSchema, StructuredOutput, TokenUsage,
+};
+use super::stream::{self, Cutoff, Framing, StreamEvent};
+use crate::config::ModelConfig;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    system: &'a str,
+    messages: Vec<Message<'a>>,
+    max_tokens: u32,
+    temperature: f32,
+    stream: bool,
+    /// Structured output is a single tool the model is forced to call
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool<'a> {
+    name: &'a str,
+    description: &'a str,
+    input_schema: &'a Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolChoice<'a> {
+    #[serde(rename = "type")]
+    choice_type: &'static str,
+    name: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    /// Arguments of a `tool_use` block
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamPayload {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    delta: Option<StreamDelta>,
+    /// `message_start` carries `message.usage`, `message_delta` a top-level `usage`
+    #[serde(default)]
+    message: Option<MessagesResponse>,
+    #[serde(default)]
+    usage: Option<Usage>,
+    #[serde(default)]
+    error: Option<ErrorBody>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    #[serde(rename = "type", default)]
+    delta_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    /// Tool arguments arrive as `input_json_delta` fragments
+    #[serde(default)]
+    partial_json: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    #[serde(rename = "type", default)]
+    error_type: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Anthropic Messages API provider
+pub(super) struct AnthropicProvider {
+    client: Client,
+    model: ModelConfig,
+    structured: StructuredOutput,
+}
+
+impl AnthropicProvider {
+    pub(super) fn new(client: Client, model: ModelConfig) -> Self {
+        Self {
+            client,
+            structured: StructuredOutput::new(&model),
+            model,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        stream: bool,
+    ) -> Result<(Response, bool), ProviderError> {
+        self.structured
+            .send(&self.model, request, |schema| {
+                self.post(request, schema, timeout, stream)
+            })
+            .await
+    }
+
+    async fn post(
+        &self,
+        request: &ChatRequest,
+        schema: Option<&ResponseSchema>,
+        timeout: Duration,
+        stream: bool,
+    ) -> Result<Response, ProviderError> {
+        let body = build_body(&self.model.model_name, request, schema, stream);
+        let mut req_builder = self
+            .client
+            .post(messages_url(&self.model.endpoint))
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .timeout(timeout)
+            .json(&body);
+        if let Some(api_key) = resolve_api_key(&self.model).await {
+            req_builder = req_builder.header("x-api-key", api_key);
+        }
+
+        let response = req_builder
+            .send()
+            .await
+            .map_err(ProviderError::from_transport)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            return Err(classify_error(status, &headers, &text));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &ModelConfig {
+        &self.model
+    }
+
+    async fn chat(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, false).await?;
+        let text = response
+            .text()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        Ok(ChatResponse {
+            structured,
+            ..parse_body(&text)?
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        cutoff: Cutoff<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, true).await?;
+        let response = if stream::is_streamed(&response, Framing::Sse) {
+            stream::read(response, Framing::Sse, cutoff, decode_event).await?
+        } else {
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            parse_body(&text)?
+        };
+        Ok(ChatResponse {
+            structured,
+            ..response
+        })
+    }
+}
+
+/// Accept both `https://api.anthropic.com` and `https://api.anthropic.com/v1`.
+fn messages_url(endpoint: &str) -> String {
+    let base = endpoint.trim_end_matches('/');
+    if base.ends_with("/v1") {
+        join_url(base, "/messages")
+    } else {
+        join_url(base, "/v1/messages")
+    }
+}
+
+fn build_body<'a>(
+    model_name: &'a str,
+    request: &'a ChatRequest,
+    schema: Option<&'a ResponseSchema>,
+    stream: bool,
+) -> MessagesRequest<'a> {
+    MessagesRequest {
+        model: model_name,
+        system: &request.system,
+        messages: vec![Message {
+            role: "user",
+            content: &request.user,
+        }],
+        max_tokens: request.max_tokens,
+        temperature: request.temperature,
+        stream,
+        tools: schema
+            .map(|schema| Tool {
+                name: schema.name,
+                description: schema.description,
+                input_schema: &schema.schema,
+            })
+            .into_iter()
+            .collect(),
+        tool_choice: schema.map(|schema| ToolChoice {
+            choice_type: "tool",
+            name: schema.name,
+        }),
+    }
+}
+
+fn parse_body(text: &str) -> Result<ChatResponse, ProviderError> {
+    let response: MessagesResponse = serde_json::from_str(text).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse Anthropic response: {}", e))
+    })?;
+
+    // A forced tool call answers with its arguments instead of text.
+    let text = match response
+        .content
+        .iter()
+        .find(|block| block.block_type == "tool_use")
+        .and_then(|block| block.input.as_ref())
+    {
+        Some(input) => input.to_string(),
+        None => response
+            .content
+            .into_iter()
+            .filter(|block| block.block_type == "text")
+            .filter_map(|block| block.text)
+            .collect::<Vec<_>>()
+            .join(""),
+    };
+
+    Ok(ChatResponse {
+        text,
+        usage: response.usage.map(TokenUsage::from),
+        ..ChatResponse::default()
+    })
+}
+
+/// Decode one Messages streaming event (`content_block_delta`, `message_stop`, `error`, ...).
+fn decode_event(_event: Option<&str>, data: &str) -> Result<StreamEvent, ProviderError> {
+    let event: StreamPayload = serde_json::from_str(data).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse Anthropic stream event: {}", e))
+    })?;
+
+    match event.event_type.as_str() {
+        "content_block_delta" => Ok(event
+            .delta
+            .and_then(|delta| match delta.delta_type.as_str() {
+                "text_delta" => delta.text,
+                "input_json_delta" => delta.partial_json,
+                _ => None,
+            })
+            .map(StreamEvent::Text)
+            .unwrap_or(StreamEvent::Ignore)),
+        "message_start" | "message_delta" => Ok(event
+            .usage
+            .or(event.message.and_then(|message| message.usage))
+            .map(|usage| StreamEvent::Usage(usage.into()))
+            .unwrap_or(StreamEvent::Ignore)),
+        "message_stop" => Ok(StreamEvent::Done),
+        "error" => {
+            // Mid-stream errors arrive with a 200 status; map the typed error onto its HTTP equivalent.
+            let code = match event.error.as_ref().map(|e| e.error_type.as_str()) {
+                Some("overloaded_error") => 529,
+                Some("rate_limit_error") => 429,
+                Some("api_error") => 500,
+                _ => 400,
+            };
+            let status = StatusCode::from_u16(code).unwrap_or(StatusCode::BAD_REQUEST);
+            Err(classify_error(status, &HeaderMap::new(), data))
+        }
+        _ => Ok(StreamEvent::Ignore),
+    }
+}
+
+/// Classify using the typed `error.type` first, falling back to the HTTP status.
+fn classify_error(status: StatusCode, headers: &HeaderMap, body: &str) -> ProviderError {
+    let Ok(envelope) = serde_json::from_str::<ErrorEnvelope>(body) else {
+        return ProviderError::from_status(status, headers, truncate_message(body));
+    };
+
+    let code = status.as_u16();
+    let message = envelope.error.message;
+    match envelope.error.error_type.as_str() {
+        "authentication_error" | "permission_error" => ProviderError::Auth {
+            status: code,
+            message,
+        },
+        "not_found_error" => ProviderError::NotFound {
+            status: code,
+            message,
+        },
+        "overloaded_error" => ProviderError::Overloaded {
+            status: code,
+            message,
+            retry_after: parse_retry_after(headers),
+        },
+        "invalid_request_error" => ProviderError::BadRequest {
+            status: code,
+            message,
+        },
+        _ => ProviderError::from_status(status, headers, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_url_appends_version_once() {
+        assert_eq!(
+            messages_url("https://api.anthropic.com"),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(
+            messages_url("https://api.anthropic.com/v1/"),
+            "https://api.anthropic.com/v1/messages"
+        );
+    }
+
+    #[test]
+    fn body_uses_top_level_system_field() {
+        let request = ChatRequest {
+            system: "sys".to_string(),
+            user: "usr".to_string(),
+            max_tokens: 100,
+            temperature: 0.3,
+            schema: None,
+        };
+        let body =
+            serde_json::to_value(build_body("claude-3-5-haiku-latest", &request, None, false))
+                .unwrap();
+        assert_eq!(body["system"], "sys");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["max_tokens"], 100);
+        assert!(body.get("tools").is_none());
+    }
+
+    #[test]
+    fn schema_becomes_forced_tool_call() {
+        let request = ChatRequest {
+            system: "sys".to_string(),
+            user: "usr".to_string(),
+            max_tokens: 100,
+            temperature: 0.3,
+            schema: None,
+        };
+        let schema = ResponseSchema {
+            name: "completion",
+            description: "Completed command",
+            schema: serde_json::json!({"type": "object"}),
+        };
+        let body = serde_json::to_value(build_body(
+            "claude-3-5-haiku-latest",
+            &request,
+            Some(&schema),
+            true,
+        ))
+        .unwrap();
+        assert_eq!(body["tools"][0]["name"], "completion");
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(body["tool_choice"]["type"], "tool");
+        assert_eq!(body["tool_choice"]["name"], "completion");
+
+        let parsed = parse_body(
+            r#"{"content":[{"type":"tool_use","id":"toolu_1","name":"completion","input":{"command":"git status"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.text, r#"{"command":"git status"}"#);
+        assert_eq!(
+            decode_event(
+                Some("content_block_delta"),
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"command\":"}}"#
+            )
+            .unwrap(),
+            StreamEvent::Text(r#"{"command":"#.to_string())
+        );
+    }
+
+    #[test]
+    fn parses_text_content_blocks() {
+        let parsed = parse_body(
+            r#"{"content":[{"type":"text","text":"git "},{"type":"text","text":"status"}],"stop_reason":"end_turn"}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.text, "git status");
+
+        let with_usage = parse_body(
+            r#"{"content":[{"type":"text","text":"ls"}],"usage":{"input_tokens":30,"output_tokens":2}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            with_usage.usage,
+            Some(TokenUsage {
+                input_tokens: 30,
+                output_tokens: 2
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_stream_events() {
+        assert_eq!(
+            decode_event(
+                Some("content_block_delta"),
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"git"}}"#
+            )
+            .unwrap(),
+            StreamEvent::Text("git".to_string())
+        );
+        assert_eq!(
+            decode_event(Some("ping"), r#"{"type":"ping"}"#).unwrap(),
+            StreamEvent::Ignore
+        );
+        assert_eq!(
+            decode_event(
+                Some("message_start"),
+                r#"{"type":"message_start","message":{"id":"msg_1","content":[],"usage":{"input_tokens":42,"output_tokens":1}}}"#
+            )
+            .unwrap(),
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 42,
+                output_tokens: 1
+            })
+        );
+        assert_eq!(
+            decode_event(
+                Some("message_delta"),
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":9}}"#
+            )
+            .unwrap(),
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 0,
+                output_tokens: 9
+            })
+        );
+        assert_eq!(
+            decode_event(Some("message_stop"), r#"{"type":"message_stop"}"#).unwrap(),
+            StreamEvent::Done
+        );
+        assert!(matches!(
+            decode_event(
+                Some("error"),
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+            ),
+            Err(ProviderError::Overloaded { status: 529, .. })
+        ));
+    }
+
+    #[test]
+    fn classifies_typed_errors() {
+        let headers = HeaderMap::new();
+        let err = classify_error(
+            StatusCode::from_u16(529).unwrap(),
+            &headers,
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+        assert!(matches!(err, ProviderError::Overloaded { status: 529, .. }));
+
+        let err = classify_error(
+            StatusCode::UNAUTHORIZED,
+            &headers,
+            r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+        );
+        assert!(matches!(err, ProviderError::Auth { status: 401, .. }));
+    }
+}
@@ -0,0 +1,212 @@
+//! API key resolution for provider requests.
+//!
+//! Keys come from the source [`ModelConfig::api_key_source`] selects. The
+//! output of `api_key_command` is cached per command for
+//! `api_key_command_ttl_secs`, so a password manager is asked once rather
+//! than on every keystroke; failures are not cached. Concurrent requests for
+//! the same command share one run, and a run that takes longer than
+//! [`COMMAND_TIMEOUT`] is killed and reported as a timeout.
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tokio::process::Command;
+
+use crate::config::{read_api_key_file, ApiKeySource, ModelConfig};
+
+/// How long `api_key_command` may run before the request gives up on it
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Last output of one command; the async lock makes concurrent callers wait
+/// for a single refresh instead of each starting the command.
+type CachedOutput = Arc<tokio::sync::Mutex<Option<(Instant, String)>>>;
+
+/// Cached command outputs, keyed by command line
+static COMMAND_CACHE: OnceLock<Mutex<HashMap<String, CachedOutput>>> = OnceLock::new();
+
+/// Resolve the API key of `model`; `Ok(None)` when no source is configured.
+pub async fn resolve(model: &ModelConfig) -> Result<Option<String>> {
+    let Some(source) = model.api_key_source() else {
+        return Ok(None);
+    };
+    let key = match source {
+        ApiKeySource::Config => model.api_key.clone().unwrap_or_default(),
+        ApiKeySource::Command(command) => {
+            cached_command_output(
+                &command,
+                Duration::from_secs(model.api_key_command_ttl_secs),
+                COMMAND_TIMEOUT,
+            )
+            .await?
+        }
+        ApiKeySource::File(path) => read_api_key_file(&path)?,
+        ApiKeySource::Env(var) => std::env::var(&var)
+            .with_context(|| format!("API key environment variable {} not set", var))?,
+    };
+    Ok(Some(key))
+}
+
+async fn cached_command_output(command: &str, ttl: Duration, timeout: Duration) -> Result<String> {
+    let entry = {
+        let cache = COMMAND_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.entry(command.to_string()).or_default().clone()
+    };
+    let mut cached = entry.lock().await;
+    if let Some((fetched, key)) = cached.as_ref() {
+        if fetched.elapsed() < ttl {
+            return Ok(key.clone());
+        }
+    }
+
+    let key = run_key_command(command, timeout).await?;
+    if !ttl.is_zero() {
+        *cached = Some((Instant::now(), key.clone()));
+    }
+    Ok(key)
+}
+
+/// Run `command` through the platform shell and take the first output line.
+async fn run_key_command(command: &str, timeout: Duration) -> Result<String> {
+    #[cfg(unix)]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run api_key_command `{}`", command))?;
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output
+            .with_context(|| format!("Failed to run api_key_command `{}`", command))?,
+        Err(_) => anyhow::bail!(
+            "api_key_command `{}` timed out after {} s (is the password manager waiting for input?)",
+            command,
+            timeout.as_secs_f32()
+        ),
+    };
+    if !output.status.success() {
+        // stderr of password managers does not contain the secret
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "api_key_command `{}` failed ({}): {}",
+            command,
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let key = stdout.lines().next().unwrap_or_default().trim().to_string();
+    if key.is_empty() {
+        anyhow::bail!("api_key_command `{}` printed no key", command);
+    }
+    Ok(key)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn command_output_is_first_line_and_cached() {
+        let marker = std::env::temp_dir().join(format!("nudge-key-command-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let command = format!(
+            "echo run >> {}; printf 'sk-test\\nsecond line\\n'",
+            marker.display()
+        );
+        let model = ModelConfig {
+            api_key_command: Some(command),
+            ..ModelConfig::default()
+        };
+
+        assert_eq!(resolve(&model).await.unwrap().as_deref(), Some("sk-test"));
+        assert_eq!(resolve(&model).await.unwrap().as_deref(), Some("sk-test"));
+        let runs = std::fs::read_to_string(&marker).unwrap();
+        assert_eq!(runs.lines().count(), 1);
+        let _ = std::fs::remove_file(marker);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_share_one_run() {
+        let marker = std::env::temp_dir().join(format!("nudge-key-shared-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let command = format!(
+            "echo run >> {}; sleep 0.2; echo sk-shared",
+            marker.display()
+        );
+        let model = ModelConfig {
+            api_key_command: Some(command),
+            ..ModelConfig::default()
+        };
+
+        let (first, second) = tokio::join!(resolve(&model), resolve(&model));
+        assert_eq!(first.unwrap().as_deref(), Some("sk-shared"));
+        assert_eq!(second.unwrap().as_deref(), Some("sk-shared"));
+        let runs = std::fs::read_to_string(&marker).unwrap();
+        assert_eq!(runs.lines().count(), 1);
+        let _ = std::fs::remove_file(marker);
+    }
+
+    #[tokio::test]
+    async fn slow_command_times_out() {
+        let err = cached_command_output(
+            "sleep 5; echo sk-late",
+            Duration::ZERO,
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("timed out"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn failing_command_is_an_error() {
+        let model = ModelConfig {
+            api_key_command: Some("echo locked >&2; exit 3".to_string()),
+            ..ModelConfig::default()
+        };
+        let err = resolve(&model).await.unwrap_err().to_string();
+        assert!(err.contains("locked"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn key_file_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("nudge-key-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key");
+        std::fs::write(&path, "sk-file\n").unwrap();
+        let model = ModelConfig {
+            api_key_file: Some(path.clone()),
+            ..ModelConfig::default()
+        };
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let err = resolve(&model).await.unwrap_err().to_string();
+        assert!(err.contains("chmod 600"), "{}", err);
+        assert!(!err.contains("sk-file"));
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(resolve(&model).await.unwrap().as_deref(), Some("sk-file"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
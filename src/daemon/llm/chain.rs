@@ -0,0 +1,404 @@
+//! Ordered backend fallback chain with a circuit breaker per endpoint.
+//!
+//! Backends are tried in configuration order. A backend whose circuit is open
+//! is skipped without touching the network, so a dead endpoint costs nothing
+//! until its cool-down expires and a single half-open probe is let through.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tracing::{debug, info, warn};
+
+use super::provider::{
+    build_provider, ChatRequest, ChatResponse, FimRequest, HttpClients, LlmProvider, ProviderError,
+    SharedProvider,
+};
+use super::stream::Cutoff;
+use crate::config::{CircuitBreakerConfig, ModelConfig, DEFAULT_MODEL_PROFILE};
+use crate::protocol::{BackendStatus, CircuitState};
+
+/// Consecutive-failure circuit breaker
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_millis(config.cooldown_ms),
+            consecutive_failures: 0,
+            opened_at: None,
+            probing: false,
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened) if now.duration_since(opened) >= self.cooldown => CircuitState::HalfOpen,
+            Some(_) => CircuitState::Open,
+        }
+    }
+
+    /// Whether a request may be sent now. In half-open state only one probe is admitted.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.probing => false,
+            CircuitState::HalfOpen => {
+                self.probing = true;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probing = false;
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.probing || self.consecutive_failures >= self.threshold {
+            self.opened_at = Some(now);
+        }
+        self.probing = false;
+    }
+
+    /// Release a half-open probe slot without judging the backend.
+    pub fn release(&mut self) {
+        self.probing = false;
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Time until an open circuit admits a probe
+    pub fn retry_in(&self, now: Instant) -> Option<Duration> {
+        let opened = self.opened_at?;
+        Some(self.cooldown.saturating_sub(now.duration_since(opened)))
+    }
+}
+
+struct ChainEntry {
+    provider: SharedProvider,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+/// Failure of a chain request, with the backend it came from
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct ChainError {
+    pub error: ProviderError,
+    /// Index of the backend that returned `error`; `None` when no backend
+    /// was tried
+    pub backend: Option<usize>,
+}
+
+impl std::borrow::Borrow<ProviderError> for ChainError {
+    fn borrow(&self) -> &ProviderError {
+        &self.error
+    }
+}
+
+/// Backends tried in order, each guarded by its own circuit breaker
+pub struct ProviderChain {
+    /// Model profile this chain serves
+    profile: String,
+    entries: Vec<ChainEntry>,
+    /// Index of the backend that last served a request
+    active: AtomicUsize,
+}
+
+impl ProviderChain {
+    /// Build the chain for a named profile on existing HTTP clients.
+    pub fn with_client(profile: &str, model: &ModelConfig, clients: &HttpClients) -> Self {
+        let providers = model
+            .backends()
+            .iter()
+            .map(|backend| build_provider(backend, clients.for_endpoint(&backend.endpoint)))
+            .collect();
+        let mut chain = Self::new(providers, &model.circuit_breaker);
+        chain.profile = profile.to_string();
+        chain
+    }
+
+    pub fn new(providers: Vec<SharedProvider>, breaker: &CircuitBreakerConfig) -> Self {
+        let entries = providers
+            .into_iter()
+            .map(|provider| ChainEntry {
+                provider,
+                breaker: Mutex::new(CircuitBreaker::new(breaker)),
+            })
+            .collect();
+        Self {
+            profile: DEFAULT_MODEL_PROFILE.to_string(),
+            entries,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Settings of the backend that last served a request
+    pub fn model(&self) -> &ModelConfig {
+        let active = self.active.load(Ordering::Relaxed);
+        self.backend(active)
+    }
+
+    /// Settings of the backend at `index`, e.g. [`ChainError::backend`]
+    pub fn backend(&self, index: usize) -> &ModelConfig {
+        self.entries[index.min(self.entries.len() - 1)]
+            .provider
+            .model()
+    }
+
+    /// Endpoint of every backend, in the order they are tried
+    pub fn endpoints(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.provider.model().endpoint.clone())
+            .collect()
+    }
+
+    /// `model.http.proxy` the backends are configured with, if any
+    pub fn proxy(&self) -> Option<String> {
+        self.entries
+            .iter()
+            .find_map(|entry| entry.provider.model().http.proxy.clone())
+    }
+
+    /// Snapshot of every backend for `nudge status` / `nudge info`
+    pub fn status(&self) -> Vec<BackendStatus> {
+        let now = Instant::now();
+        let active = self.active.load(Ordering::Relaxed);
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let model = entry.provider.model();
+                let breaker = entry.breaker.lock().unwrap_or_else(|e| e.into_inner());
+                let circuit = breaker.state(now);
+                BackendStatus {
+                    profile: self.profile.clone(),
+                    provider: entry.provider.id().to_string(),
+                    endpoint: model.endpoint.clone(),
+                    model_name: model.model_name.clone(),
+                    circuit,
+                    consecutive_failures: breaker.consecutive_failures(),
+                    retry_in_ms: (circuit == CircuitState::Open)
+                        .then(|| breaker.retry_in(now))
+                        .flatten()
+                        .map(|d| d.as_millis() as u64),
+                    active: idx == active,
+                }
+            })
+            .collect()
+    }
+
+    /// Send a single-turn chat request, bounded by `timeout` across all backends
+    pub async fn chat(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ChainError> {
+        self.run(timeout, |provider, timeout| provider.chat(request, timeout))
+            .await
+    }
+
+    /// Stream a chat request, stopping as soon as `cutoff` accepts the accumulated text
+    pub async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        cutoff: Cutoff<'_>,
+    ) -> Result<ChatResponse, ChainError> {
+        self.run(timeout, |provider, timeout| {
+            provider.chat_stream(request, timeout, cutoff)
+        })
+        .await
+    }
+
+    /// Fill-in-the-middle completion between `prefix` and `suffix`
+    pub async fn fill_in_middle(
+        &self,
+        request: &FimRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ChainError> {
+        self.run(timeout, |provider, timeout| {
+            provider.fill_in_middle(request, timeout)
+        })
+        .await
+    }
+
+    /// Run `call` against each admitted backend in order until one succeeds.
+    /// All attempts share one `timeout`; each backend gets what is left of it.
+    async fn run<'a, F, Fut>(
+        &'a self,
+        timeout: Duration,
+        call: F,
+    ) -> Result<ChatResponse, ChainError>
+    where
+        F: Fn(&'a dyn LlmProvider, Duration) -> Fut,
+        Fut: std::future::Future<Output = Result<ChatResponse, ProviderError>>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut last_error = None;
+        let mut soonest_retry: Option<Duration> = None;
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                debug!(
+                    "Request deadline passed; not trying {}",
+                    backend_label(entry)
+                );
+                return Err(last_error.unwrap_or(ChainError {
+                    error: ProviderError::Timeout,
+                    backend: None,
+                }));
+            }
+            let admitted = {
+                let mut breaker = entry.breaker.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let admitted = breaker.try_acquire(now);
+                if !admitted {
+                    if let Some(retry) = breaker.retry_in(now) {
+                        soonest_retry = Some(soonest_retry.map_or(retry, |s| s.min(retry)));
+                    }
+                }
+                admitted
+            };
+            if !admitted {
+                debug!("Skipping backend {} (circuit open)", backend_label(entry));
+                continue;
+            }
+
+            match call(entry.provider.as_ref(), remaining).await {
+                Ok(mut response) => {
+                    response
+                        .model
+                        .get_or_insert_with(|| entry.provider.model().model_name.clone());
+                    entry
+                        .breaker
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .record_success();
+                    if self.active.swap(idx, Ordering::Relaxed) != idx {
+                        info!("LLM backend now active: {}", backend_label(entry));
+                    }
+                    return Ok(response);
+                }
+                Err(error) => {
+                    let mut breaker = entry.breaker.lock().unwrap_or_else(|e| e.into_inner());
+                    if error.is_endpoint_failure() {
+                        breaker.record_failure(Instant::now());
+                        if breaker.state(Instant::now()) == CircuitState::Open {
+                            warn!(
+                                "Circuit opened for {} after {} consecutive failures",
+                                backend_label(entry),
+                                breaker.consecutive_failures()
+                            );
+                        }
+                    } else {
+                        breaker.release();
+                    }
+                    drop(breaker);
+
+                    if idx + 1 < self.entries.len() {
+                        warn!(
+                            "LLM backend {} failed ({}), trying next",
+                            backend_label(entry),
+                            error
+                        );
+                    }
+                    last_error = Some(ChainError {
+                        error,
+                        backend: Some(idx),
+                    });
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(ChainError {
+            error: ProviderError::CircuitOpen {
+                retry_in: soonest_retry.unwrap_or_default(),
+            },
+            backend: None,
+        }))
+    }
+}
+
+fn backend_label(entry: &ChainEntry) -> String {
+    let model = entry.provider.model();
+    format!(
+        "{}:{}@{}",
+        entry.provider.id(),
+        model.model_name,
+        model.endpoint
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32, cooldown_ms: u64) -> CircuitBreaker {
+        CircuitBreaker::new(&CircuitBreakerConfig {
+            failure_threshold: threshold,
+            cooldown_ms,
+        })
+    }
+
+    #[test]
+    fn opens_after_threshold_consecutive_failures() {
+        let mut breaker = breaker(2, 1000);
+        let now = Instant::now();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Open);
+        assert!(!breaker.try_acquire(now));
+    }
+
+    #[test]
+    fn success_resets_failure_count() {
+        let mut breaker = breaker(2, 1000);
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_admits_single_probe() {
+        let mut breaker = breaker(1, 100);
+        let opened = Instant::now();
+        breaker.record_failure(opened);
+
+        let later = opened + Duration::from_millis(150);
+        assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(later));
+        assert!(!breaker.try_acquire(later));
+
+        // Failed probe re-opens the circuit for another cool-down.
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(later), CircuitState::Open);
+        assert_eq!(breaker.retry_in(later), Some(Duration::from_millis(100)));
+
+        let much_later = later + Duration::from_millis(150);
+        assert!(breaker.try_acquire(much_later));
+        breaker.record_success();
+        assert_eq!(breaker.state(much_later), CircuitState::Closed);
+    }
+}
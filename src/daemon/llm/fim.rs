@@ -0,0 +1,139 @@
+//! Cursor-aware (fill-in-the-middle) completion.
+//!
+//! When the cursor sits before the end of the buffer, the text before and
+//! after it is sent separately and the model answers with the insertion that
+//! belongs between them. The daemon reassembles `prefix + insertion + suffix`
+//! so callers still receive a complete command line.
+
+use serde_json::Value;
+
+use super::{sanitize_summary, CompletionDraft};
+
+/// Split `buffer` at `cursor` (a byte offset, clamped to a char boundary).
+///
+/// Returns `None` when there is nothing meaningful after the cursor, in which
+/// case the regular end-of-line completion applies.
+pub(super) fn split_at_cursor(buffer: &str, cursor: usize) -> Option<(&str, &str)> {
+    let mut cursor = cursor.min(buffer.len());
+    while !buffer.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    let (prefix, suffix) = buffer.split_at(cursor);
+    if suffix.trim().is_empty() {
+        return None;
+    }
+    Some((prefix, suffix))
+}
+
+/// Parse a FIM reply (JSON `insertion` contract, or raw insertion text from
+/// native endpoints and non-compliant models) into a full command.
+pub(super) fn parse_insertion(text: &str, prefix: &str, suffix: &str) -> CompletionDraft {
+    let trimmed = text.trim();
+    let (insertion, summary_short) = match serde_json::from_str::<Value>(trimmed) {
+        Ok(Value::Object(obj)) => {
+            let summary = ["summary_short", "summary"]
+                .iter()
+                .find_map(|key| obj.get(*key).and_then(Value::as_str))
+                .and_then(sanitize_summary);
+            let insertion = ["insertion", "insert", "text", "completion"]
+                .iter()
+                .find_map(|key| obj.get(*key).and_then(Value::as_str));
+            // Models that ignore the contract sometimes answer with the whole line.
+            let command = obj.get("command").and_then(Value::as_str).map(str::trim);
+            match (insertion, command) {
+                (None, Some(command)) if !command.is_empty() => {
+                    return CompletionDraft {
+                        command: command.to_string(),
+                        summary_short: summary,
+                        reason_short: None,
+                        additional_candidates: Vec::new(),
+                    };
+                }
+                _ => (insertion.unwrap_or_default().to_string(), summary),
+            }
+        }
+        _ => (raw_insertion(text), None),
+    };
+
+    let insertion = strip_overlap(&insertion, prefix, suffix);
+    CompletionDraft {
+        command: format!("{}{}{}", prefix, insertion, suffix),
+        summary_short,
+        reason_short: None,
+        additional_candidates: Vec::new(),
+    }
+}
+
+/// Raw text keeps its inner spacing; only fences and anything past the first line go.
+fn raw_insertion(text: &str) -> String {
+    let text = text.trim_matches('\n');
+    let text = if text.trim_start().starts_with("```") {
+        text.lines()
+            .skip(1)
+            .take_while(|line| !line.starts_with("```"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        text.to_string()
+    };
+    text.lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('\r')
+        .to_string()
+}
+
+/// Drop text the model echoed from either side of the cursor.
+fn strip_overlap<'a>(insertion: &'a str, prefix: &str, suffix: &str) -> &'a str {
+    let mut insertion = insertion;
+    if !prefix.trim().is_empty() {
+        if let Some(rest) = insertion.strip_prefix(prefix) {
+            insertion = rest;
+        }
+    }
+    if let Some(rest) = insertion.strip_suffix(suffix) {
+        insertion = rest;
+    }
+    insertion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_requires_text_after_cursor() {
+        assert_eq!(split_at_cursor("git status", 10), None);
+        assert_eq!(split_at_cursor("git status   ", 10), None);
+        assert_eq!(split_at_cursor("git  main", 4), Some(("git ", " main")));
+        // Cursor inside a multi-byte char snaps back to its start.
+        assert_eq!(split_at_cursor("echo ä x", 6), Some(("echo ", "ä x")));
+    }
+
+    #[test]
+    fn json_insertion_is_placed_between_prefix_and_suffix() {
+        let draft = parse_insertion(
+            r#"{"insertion":"checkout -b","summary_short":"Create a branch"}"#,
+            "git ",
+            " feature/x",
+        );
+        assert_eq!(draft.command, "git checkout -b feature/x");
+        assert_eq!(draft.summary_short.as_deref(), Some("Create a branch"));
+
+        let whole_line = parse_insertion(r#"{"command":"git checkout -b x"}"#, "git ", " x");
+        assert_eq!(whole_line.command, "git checkout -b x");
+    }
+
+    #[test]
+    fn raw_insertion_keeps_spacing_and_strips_echoes() {
+        assert_eq!(
+            parse_insertion("-la ", "ls ", "| grep x").command,
+            "ls -la | grep x"
+        );
+        assert_eq!(
+            parse_insertion("docker run -it ubuntu", "docker run ", " ubuntu").command,
+            "docker run -it ubuntu"
+        );
+        assert_eq!(parse_insertion("", "ls ", "/tmp").command, "ls /tmp");
+    }
+}
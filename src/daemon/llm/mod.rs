@@ -0,0 +1,738 @@
+mod anthropic;
+pub mod api_key;
+pub mod chain;
+mod fim;
+mod ollama;
+mod openai;
+pub mod placeholder;
+pub mod provider;
+pub mod retry;
+pub mod router;
+pub mod stream;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::context::{self, ContextData};
+use super::{prompts, sanitizer, shell_mode::ShellMode, tokens};
+use crate::config::Config;
+use crate::protocol::ContextSummary;
+use provider::{ChatRequest, FimRequest, ResponseSchema};
+use router::Route;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionDraft {
+    pub command: String,
+    pub summary_short: Option<String>,
+    pub reason_short: Option<String>,
+    pub additional_candidates: Vec<CandidateDraft>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateDraft {
+    pub command: String,
+    pub summary_short: Option<String>,
+    pub reason_short: Option<String>,
+}
+
+impl CompletionDraft {
+    fn from_command(command: String) -> Self {
+        Self {
+            command,
+            summary_short: None,
+            reason_short: None,
+            additional_candidates: Vec::new(),
+        }
+    }
+}
+
+/// Request sent for a completion: the chat prompt, or the fill-in-the-middle
+/// form when the cursor sits inside the buffer
+#[derive(Debug, Clone)]
+pub enum LlmRequest {
+    Chat(ChatRequest),
+    Fim(FimRequest),
+}
+
+impl LlmRequest {
+    /// The chat form (the prompt-based equivalent for FIM requests)
+    pub fn chat(&self) -> &ChatRequest {
+        match self {
+            Self::Chat(request) => request,
+            Self::Fim(request) => &request.chat,
+        }
+    }
+}
+
+/// A request together with the context summary of its prompt
+#[derive(Debug, Clone)]
+pub struct Prepared<T> {
+    pub request: T,
+    pub summary: ContextSummary,
+}
+
+/// Build the request `complete` sends for `buffer`, truncating `context`
+/// until the whole prompt fits `context.max_total_tokens`.
+pub fn build_request(
+    buffer: &str,
+    cursor: usize,
+    context: &ContextData,
+    config: &Config,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+) -> Prepared<LlmRequest> {
+    let model = route.provider.model();
+    let counter = tokens::counter(&config.context.tokenizer, &model.model_name);
+    let (mut request, mut summary) = context::fit_prompt(
+        context,
+        config,
+        |context| render_request(buffer, cursor, context, config, shell_mode, route),
+        |request| tokens::request_tokens(counter.as_ref(), request.chat()),
+    );
+    match &mut request {
+        LlmRequest::Chat(chat) => sanitizer::sanitize_prepared(chat, &mut summary, &config.privacy),
+        LlmRequest::Fim(fim) => {
+            sanitizer::sanitize_prepared(&mut fim.chat, &mut summary, &config.privacy);
+            if config.privacy.sanitize_enabled {
+                // Native FIM endpoints get the text around the cursor on its own
+                for text in [&mut fim.prefix, &mut fim.suffix] {
+                    let (sanitized, events) =
+                        sanitizer::sanitize_field("prompt", text, &config.privacy);
+                    *text = sanitized;
+                    sanitizer::record(&mut summary, &events);
+                }
+            }
+        }
+    }
+    Prepared { request, summary }
+}
+
+fn render_request(
+    buffer: &str,
+    cursor: usize,
+    context: &ContextData,
+    config: &Config,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+) -> LlmRequest {
+    let system = prompts::completion::system_prompt(config);
+    let model = route.provider.model();
+
+    if model.fim.enabled {
+        if let Some((prefix, suffix)) = fim::split_at_cursor(buffer, cursor) {
+            return LlmRequest::Fim(FimRequest {
+                chat: ChatRequest {
+                    system,
+                    user: prompts::completion::fim_prompt(prefix, suffix, context),
+                    max_tokens: max_tokens(route, shell_mode),
+                    temperature: route.settings.temperature.unwrap_or(0.3),
+                    schema: None,
+                },
+                prefix: prefix.to_string(),
+                suffix: suffix.to_string(),
+            });
+        }
+    }
+
+    let structured = model.structured_output;
+    LlmRequest::Chat(ChatRequest {
+        system,
+        user: prompts::completion::user_prompt(buffer, context, shell_mode, structured),
+        max_tokens: max_tokens(route, shell_mode),
+        temperature: route.settings.temperature.unwrap_or(0.3),
+        schema: structured.then(completion_schema),
+    })
+}
+
+/// Send a completion request built by [`build_request`] for `buffer`
+pub async fn complete(
+    request: &LlmRequest,
+    buffer: &str,
+    config: &Config,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+) -> Result<CompletionDraft> {
+    let timeout = Duration::from_millis(route.timeout_ms(config));
+
+    let request = match request {
+        LlmRequest::Fim(request) => {
+            return complete_fim(request, shell_mode, route, timeout).await;
+        }
+        LlmRequest::Chat(request) => request,
+    };
+
+    let provider = route.provider;
+    let response = if route.settings.stream.unwrap_or(config.model.stream) {
+        retry::run(&route.retry, timeout, |timeout| {
+            provider.chat_stream(request, timeout, &first_answer_end)
+        })
+        .await?
+    } else {
+        retry::run(&route.retry, timeout, |timeout| {
+            provider.chat(request, timeout)
+        })
+        .await?
+    };
+    route.record_usage(request, &response);
+    let text = response.text;
+
+    info!(
+        "LLM raw completion output: shell_mode={} profile={} stopped_early={} structured={} content={:?}",
+        shell_mode.as_str(),
+        route.profile,
+        response.stopped_early,
+        response.structured,
+        text
+    );
+
+    // Schema-constrained answers must match the schema; everything else goes
+    // through the lenient parser with its plain text fallback.
+    let cleaned = if response.structured {
+        parse_structured_completion(&text)
+            .context("LLM response does not match the completion schema")?
+    } else {
+        parse_completion(&text, buffer)
+    };
+
+    info!(
+        "LLM parsed completion: shell_mode={} command={:?} summary_short={:?} reason_short={:?} additional_candidates={}",
+        shell_mode.as_str(),
+        cleaned.command,
+        cleaned.summary_short,
+        cleaned.reason_short,
+        cleaned.additional_candidates.len()
+    );
+
+    Ok(cleaned)
+}
+
+/// Fill the gap at the cursor instead of completing the end of the line
+async fn complete_fim(
+    request: &FimRequest,
+    shell_mode: ShellMode,
+    route: &Route<'_>,
+    timeout: Duration,
+) -> Result<CompletionDraft> {
+    let response = retry::run(&route.retry, timeout, |timeout| {
+        route.provider.fill_in_middle(request, timeout)
+    })
+    .await?;
+    route.record_usage(&request.chat, &response);
+
+    info!(
+        "LLM raw FIM output: shell_mode={} profile={} content={:?}",
+        shell_mode.as_str(),
+        route.profile,
+        response.text
+    );
+
+    let draft = fim::parse_insertion(&response.text, &request.prefix, &request.suffix);
+    info!(
+        "LLM parsed FIM completion: shell_mode={} command={:?}",
+        shell_mode.as_str(),
+        draft.command
+    );
+    Ok(draft)
+}
+
+/// Streaming cutoff matching what `parse_completion` keeps: the first complete
+/// JSON value, or the first non-empty line of plain text (after an optional
+/// markdown fence line).
+fn first_answer_end(text: &str) -> Option<usize> {
+    let start = text.len() - text.trim_start().len();
+    let rest = &text[start..];
+
+    if rest.starts_with("```") {
+        let body_start = start + rest.find('\n')? + 1;
+        return first_answer_end(&text[body_start..]).map(|end| body_start + end);
+    }
+    if rest.starts_with('{') || rest.starts_with('[') {
+        return json_value_end(rest).map(|end| start + end);
+    }
+
+    rest.find('\n').map(|end| start + end)
+}
+
+/// Byte length of the leading JSON object/array once its brackets balance.
+fn json_value_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, ch) in text.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(idx + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Answer shape sent as the response schema; mirrors [`CompletionDraft`]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructuredCompletion {
+    command: String,
+    summary_short: Option<String>,
+    reason_short: Option<String>,
+    #[serde(default)]
+    additional_candidates: Vec<StructuredCandidate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructuredCandidate {
+    command: String,
+    summary_short: Option<String>,
+    reason_short: Option<String>,
+}
+
+/// JSON schema for completion answers. Every property is required (optional
+/// ones are nullable) so the schema is also valid in OpenAI strict mode.
+fn completion_schema() -> ResponseSchema {
+    let candidate = |command: &str| {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {"type": "string", "description": command},
+                "summary_short": {
+                    "type": ["string", "null"],
+                    "description": "Short action-oriented description"
+                },
+                "reason_short": {
+                    "type": ["string", "null"],
+                    "description": "Why this command fits the current input"
+                }
+            },
+            "required": ["command", "summary_short", "reason_short"],
+            "additionalProperties": false
+        })
+    };
+
+    let mut schema = candidate("Best completed command");
+    schema["properties"]["additional_candidates"] = json!({
+        "type": "array",
+        "description": "Further completions ordered by relevance",
+        "items": candidate("Completed command")
+    });
+    schema["required"] = json!([
+        "command",
+        "summary_short",
+        "reason_short",
+        "additional_candidates"
+    ]);
+
+    ResponseSchema {
+        name: "command_completion",
+        description: "Return the completed shell command",
+        schema,
+    }
+}
+
+/// Validate an answer generated under [`completion_schema`].
+fn parse_structured_completion(text: &str) -> Result<CompletionDraft> {
+    let parsed: StructuredCompletion = serde_json::from_str(text.trim())?;
+    let command = parsed.command.trim();
+    if command.is_empty() {
+        anyhow::bail!("command is empty");
+    }
+
+    let mut seen = HashSet::new();
+    seen.insert(command.to_lowercase());
+    let additional_candidates = parsed
+        .additional_candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let command = candidate.command.trim();
+            if command.is_empty() || !seen.insert(command.to_lowercase()) {
+                return None;
+            }
+            Some(CandidateDraft {
+                command: command.to_string(),
+                summary_short: candidate
+                    .summary_short
+                    .as_deref()
+                    .and_then(sanitize_summary),
+                reason_short: candidate.reason_short.as_deref().and_then(sanitize_summary),
+            })
+        })
+        .collect();
+
+    Ok(CompletionDraft {
+        command: command.to_string(),
+        summary_short: parsed.summary_short.as_deref().and_then(sanitize_summary),
+        reason_short: parsed.reason_short.as_deref().and_then(sanitize_summary),
+        additional_candidates,
+    })
+}
+
+/// Parse completion payload from LLM output.
+fn parse_completion(text: &str, original_buffer: &str) -> CompletionDraft {
+    let text = text.trim();
+
+    // Remove markdown code blocks if present.
+    let text = if text.starts_with("```") {
+        text.lines()
+            .skip(1)
+            .take_while(|line| !line.starts_with("```"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        text.to_string()
+    };
+
+    if let Some(parsed) = parse_json_completion(&text) {
+        return parsed;
+    }
+
+    // Take only the first line if multiple lines are returned.
+    let text = text.lines().next().unwrap_or(&text).trim();
+
+    // If the completion is empty, return the original buffer.
+    if text.is_empty() {
+        return CompletionDraft::from_command(original_buffer.to_string());
+    }
+
+    CompletionDraft::from_command(text.to_string())
+}
+
+/// All commands in an answer listing candidates, best first; plain text
+/// answers give one candidate per line.
+pub fn parse_candidates(text: &str) -> Vec<CandidateDraft> {
+    let text = text.trim();
+    let text = if text.starts_with("```") {
+        text.lines()
+            .skip(1)
+            .take_while(|line| !line.starts_with("```"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        text.to_string()
+    };
+
+    if let Some(parsed) = parse_json_completion(&text) {
+        let primary = CandidateDraft {
+            command: parsed.command,
+            summary_short: parsed.summary_short,
+            reason_short: parsed.reason_short,
+        };
+        return std::iter::once(primary)
+            .chain(parsed.additional_candidates)
+            .collect();
+    }
+
+    let mut seen = HashSet::new();
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && seen.insert(line.to_lowercase()))
+        .map(|line| CandidateDraft {
+            command: line.to_string(),
+            summary_short: None,
+            reason_short: None,
+        })
+        .collect()
+}
+
+fn parse_json_completion(text: &str) -> Option<CompletionDraft> {
+    let value: Value = serde_json::from_str(text).ok().or_else(|| {
+        text.lines()
+            .next()
+            .and_then(|line| serde_json::from_str::<Value>(line.trim()).ok())
+    })?;
+
+    let mut parsed_candidates = parse_candidate_array(&value);
+    let primary = parse_candidate_value(&value).or_else(|| {
+        if parsed_candidates.is_empty() {
+            None
+        } else {
+            Some(parsed_candidates.remove(0))
+        }
+    })?;
+
+    let mut additional_candidates = Vec::new();
+    let mut seen = HashSet::new();
+    seen.insert(primary.command.to_lowercase());
+
+    for candidate in parsed_candidates {
+        let dedupe_key = candidate.command.to_lowercase();
+        if seen.insert(dedupe_key) {
+            additional_candidates.push(candidate);
+        }
+    }
+
+    Some(CompletionDraft {
+        command: primary.command,
+        summary_short: primary.summary_short,
+        reason_short: primary.reason_short,
+        additional_candidates,
+    })
+}
+
+fn sanitize_summary(summary: &str) -> Option<String> {
+    let normalized = summary.replace(['\t', '\n', '\r'], " ");
+    let trimmed = normalized.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let mut truncated = trimmed.chars().take(120).collect::<String>();
+    if truncated.len() < trimmed.len() {
+        truncated.push_str("...");
+    }
+    Some(truncated)
+}
+
+fn max_tokens(route: &Route<'_>, shell_mode: ShellMode) -> u32 {
+    route
+        .settings
+        .max_tokens
+        .unwrap_or_else(|| max_tokens_for_mode(shell_mode))
+}
+
+fn max_tokens_for_mode(shell_mode: ShellMode) -> u32 {
+    match shell_mode {
+        ShellMode::BashPopup => 320,
+        _ => 100,
+    }
+}
+
+fn parse_candidate_array(value: &Value) -> Vec<CandidateDraft> {
+    match value {
+        Value::Array(arr) => arr.iter().filter_map(parse_candidate_value).collect(),
+        Value::Object(obj) => [
+            "candidates",
+            "suggestions",
+            "alternatives",
+            "completions",
+            "choices",
+        ]
+        .iter()
+        .find_map(|key| obj.get(*key))
+        .and_then(Value::as_array)
+        .map(|arr| arr.iter().filter_map(parse_candidate_value).collect())
+        .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_candidate_value(value: &Value) -> Option<CandidateDraft> {
+    match value {
+        Value::String(raw) => {
+            let command = raw.trim();
+            if command.is_empty() {
+                return None;
+            }
+            Some(CandidateDraft {
+                command: command.to_string(),
+                summary_short: None,
+                reason_short: None,
+            })
+        }
+        Value::Object(obj) => {
+            let command = ["command", "text", "completion", "suggestion"]
+                .iter()
+                .find_map(|key| obj.get(*key).and_then(|v| v.as_str()))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())?
+                .to_string();
+
+            let summary_short = ["summary_short", "summary", "description", "why"]
+                .iter()
+                .find_map(|key| obj.get(*key).and_then(|v| v.as_str()))
+                .and_then(sanitize_summary);
+            let reason_short = ["reason_short", "reason", "why"]
+                .iter()
+                .find_map(|key| obj.get(*key).and_then(|v| v.as_str()))
+                .and_then(sanitize_summary);
+
+            Some(CandidateDraft {
+                command,
+                summary_short,
+                reason_short,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        completion_schema, first_answer_end, parse_candidates, parse_completion,
+        parse_structured_completion, CandidateDraft, CompletionDraft,
+    };
+
+    #[test]
+    fn parse_candidates_lists_json_and_plain_answers() {
+        let json = r#"{"candidates":[{"command":"du -sh *","summary_short":"Size of each entry"},{"command":"df -h"}]}"#;
+        let commands: Vec<_> = parse_candidates(json)
+            .into_iter()
+            .map(|c| (c.command, c.summary_short))
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                (
+                    "du -sh *".to_string(),
+                    Some("Size of each entry".to_string())
+                ),
+                ("df -h".to_string(), None),
+            ]
+        );
+
+        let plain = parse_candidates("```sh\nls -la\n\nls -la\nls -lt\n```");
+        let commands: Vec<_> = plain.into_iter().map(|c| c.command).collect();
+        assert_eq!(commands, vec!["ls -la", "ls -lt"]);
+        assert!(parse_candidates("  ").is_empty());
+    }
+
+    #[test]
+    fn parse_plain_completion_fallback() {
+        let parsed = parse_completion("git status\nextra line", "git st");
+        assert_eq!(
+            parsed,
+            CompletionDraft {
+                command: "git status".to_string(),
+                summary_short: None,
+                reason_short: None,
+                additional_candidates: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn stream_cutoff_waits_for_first_line() {
+        assert_eq!(first_answer_end("git sta"), None);
+        assert_eq!(first_answer_end("\n git status\nexplanation"), Some(12));
+        assert_eq!(first_answer_end("```bash\n"), None);
+
+        let fenced = "```bash\ngit status\n```";
+        let end = first_answer_end(fenced).unwrap();
+        assert_eq!(
+            parse_completion(&fenced[..end], "git st").command,
+            "git status"
+        );
+    }
+
+    #[test]
+    fn stream_cutoff_waits_for_balanced_json() {
+        let partial = r#"{"command":"echo \"}\"","summary_short":"#;
+        assert_eq!(first_answer_end(partial), None);
+
+        let full = r#"{"candidates":[{"command":"git status"},{"command":"git stash"}]} trailing"#;
+        let end = first_answer_end(full).unwrap();
+        let parsed = parse_completion(&full[..end], "git st");
+        assert_eq!(parsed.command, "git status");
+        assert_eq!(parsed.additional_candidates.len(), 1);
+    }
+
+    #[test]
+    fn structured_completion_is_validated_against_schema() {
+        let parsed = parse_structured_completion(
+            r#"{"command":"git status","summary_short":"Show tree state","reason_short":null,"additional_candidates":[{"command":"git stash","summary_short":null,"reason_short":null},{"command":"GIT STATUS","summary_short":null,"reason_short":null}]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.command, "git status");
+        assert_eq!(parsed.summary_short.as_deref(), Some("Show tree state"));
+        assert_eq!(parsed.reason_short, None);
+        assert_eq!(parsed.additional_candidates.len(), 1);
+
+        // Shapes only the lenient parser accepts are rejected.
+        assert!(parse_structured_completion(r#"{"candidates":[{"command":"ls"}]}"#).is_err());
+        assert!(parse_structured_completion(r#"{"text":"ls"}"#).is_err());
+        assert!(parse_structured_completion(r#"{"command":"  "}"#).is_err());
+        assert!(parse_structured_completion("ls -la").is_err());
+    }
+
+    #[test]
+    fn completion_schema_is_strict_mode_compatible() {
+        let schema = completion_schema().schema;
+        for object in [
+            &schema,
+            &schema["properties"]["additional_candidates"]["items"],
+        ] {
+            assert_eq!(object["additionalProperties"], false);
+            let properties = object["properties"].as_object().unwrap();
+            let required = object["required"].as_array().unwrap();
+            assert_eq!(properties.len(), required.len());
+            for key in properties.keys() {
+                assert!(required.iter().any(|r| r == key), "{} not required", key);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_json_completion_with_summary() {
+        let parsed = parse_completion(
+            r#"{"command":"git status","summary_short":"Check working tree state"}"#,
+            "git st",
+        );
+        assert_eq!(
+            parsed,
+            CompletionDraft {
+                command: "git status".to_string(),
+                summary_short: Some("Check working tree state".to_string()),
+                reason_short: None,
+                additional_candidates: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn parse_json_completion_with_reason() {
+        let parsed = parse_completion(
+            r#"{"command":"git status -sb","reason_short":"matches typed git st prefix"}"#,
+            "git st",
+        );
+        assert_eq!(
+            parsed,
+            CompletionDraft {
+                command: "git status -sb".to_string(),
+                summary_short: None,
+                reason_short: Some("matches typed git st prefix".to_string()),
+                additional_candidates: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn parse_json_completion_with_candidates_array() {
+        let parsed = parse_completion(
+            r#"{"candidates":[{"command":"git status -sb","reason_short":"best match"},{"command":"git stash list","summary_short":"List stashes"}]}"#,
+            "git st",
+        );
+        assert_eq!(
+            parsed,
+            CompletionDraft {
+                command: "git status -sb".to_string(),
+                summary_short: None,
+                reason_short: Some("best match".to_string()),
+                additional_candidates: vec![CandidateDraft {
+                    command: "git stash list".to_string(),
+                    summary_short: Some("List stashes".to_string()),
+                    reason_short: None
+                }]
+            }
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Ollama native API backend (`/api/chat`, plus `/api/generate` for native FIM).
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::provider::{
+    join_url, resolve_api_key, truncate_message, ChatRequest, ChatResponse, FimRequest,
+    LlmProvider, ProviderError, ResponseSchema, StructuredOutput, TokenUsage,
+};
+use super::stream::{self, Cutoff, Framing, StreamEvent};
+use crate::config::ModelConfig;
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    stream: bool,
+    options: Options,
+    /// JSON schema the answer must follow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct Options {
+    temperature: f32,
+    num_predict: u32,
+}
+
+/// `/api/generate` body; `suffix` makes the model's FIM template fill the gap
+#[derive(Debug, Serialize)]
+struct OllamaGenerateRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    suffix: &'a str,
+    stream: bool,
+    options: Options,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaGenerateResponse {
+    #[serde(default)]
+    response: String,
+    #[serde(flatten)]
+    counts: EvalCounts,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<ResponseMessage>,
+    #[serde(flatten)]
+    counts: EvalCounts,
+}
+
+/// Token counts Ollama reports on the final (or only) response object
+#[derive(Debug, Default, Deserialize)]
+struct EvalCounts {
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+}
+
+impl EvalCounts {
+    fn usage(&self) -> Option<TokenUsage> {
+        if self.prompt_eval_count.is_none() && self.eval_count.is_none() {
+            return None;
+        }
+        Some(TokenUsage {
+            input_tokens: self.prompt_eval_count.unwrap_or(0),
+            output_tokens: self.eval_count.unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: String,
+}
+
+/// Ollama native chat API provider
+pub(super) struct OllamaProvider {
+    client: Client,
+    model: ModelConfig,
+    structured: StructuredOutput,
+}
+
+impl OllamaProvider {
+    pub(super) fn new(client: Client, model: ModelConfig) -> Self {
+        Self {
+            client,
+            structured: StructuredOutput::new(&model),
+            model,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        stream: bool,
+    ) -> Result<(Response, bool), ProviderError> {
+        self.structured
+            .send(&self.model, request, |schema| async move {
+                let body = build_body(&self.model.model_name, request, schema, stream);
+                self.post(api_url(&self.model.endpoint, "/api/chat"), &body, timeout)
+                    .await
+            })
+            .await
+    }
+
+    async fn post<B: Serialize + Sync>(
+        &self,
+        url: String,
+        body: &B,
+        timeout: Duration,
+    ) -> Result<Response, ProviderError> {
+        let mut req_builder = self.client.post(url).timeout(timeout).json(body);
+        // Ollama itself is unauthenticated, but reverse proxies in front of it often are not.
+        if let Some(api_key) = resolve_api_key(&self.model).await {
+            req_builder = req_builder.bearer_auth(api_key);
+        }
+
+        let response = req_builder
+            .send()
+            .await
+            .map_err(ProviderError::from_transport)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            return Err(ProviderError::from_status(
+                status,
+                &headers,
+                error_message(&text),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn id(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn model(&self) -> &ModelConfig {
+        &self.model
+    }
+
+    async fn chat(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, false).await?;
+        let text = response
+            .text()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        Ok(ChatResponse {
+            structured,
+            ..parse_body(&text)?
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        cutoff: Cutoff<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, true).await?;
+        let response = if stream::is_streamed(&response, Framing::Ndjson) {
+            stream::read(response, Framing::Ndjson, cutoff, decode_line).await?
+        } else {
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            parse_body(&text)?
+        };
+        Ok(ChatResponse {
+            structured,
+            ..response
+        })
+    }
+
+    async fn fill_in_middle(
+        &self,
+        request: &FimRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ProviderError> {
+        if !self.model.fim.native {
+            return self.chat(&request.chat, timeout).await;
+        }
+        let body = OllamaGenerateRequest {
+            model: &self.model.model_name,
+            prompt: &request.prefix,
+            suffix: &request.suffix,
+            stream: false,
+            options: Options {
+                temperature: request.chat.temperature,
+                num_predict: request.chat.max_tokens,
+            },
+        };
+        let response = self
+            .post(
+                api_url(&self.model.endpoint, "/api/generate"),
+                &body,
+                timeout,
+            )
+            .await?;
+        let text = response
+            .text()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        let generated: OllamaGenerateResponse = serde_json::from_str(&text).map_err(|e| {
+            ProviderError::InvalidResponse(format!("Failed to parse Ollama response: {}", e))
+        })?;
+        Ok(ChatResponse {
+            usage: generated.counts.usage(),
+            text: generated.response,
+            ..ChatResponse::default()
+        })
+    }
+}
+
+/// The default endpoint is the OpenAI-compatible `/v1` base; the native API lives at the root.
+fn api_url(endpoint: &str, path: &str) -> String {
+    let base = endpoint.trim_end_matches('/');
+    let base = base.strip_suffix("/v1").unwrap_or(base);
+    join_url(base, path)
+}
+
+fn build_body<'a>(
+    model_name: &'a str,
+    request: &'a ChatRequest,
+    schema: Option<&'a ResponseSchema>,
+    stream: bool,
+) -> OllamaChatRequest<'a> {
+    OllamaChatRequest {
+        model: model_name,
+        messages: vec![
+            Message {
+                role: "system",
+                content: &request.system,
+            },
+            Message {
+                role: "user",
+                content: &request.user,
+            },
+        ],
+        stream,
+        options: Options {
+            temperature: request.temperature,
+            num_predict: request.max_tokens,
+        },
+        format: schema.map(|schema| &schema.schema),
+    }
+}
+
+fn parse_body(text: &str) -> Result<ChatResponse, ProviderError> {
+    let response: OllamaChatResponse = serde_json::from_str(text).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse Ollama response: {}", e))
+    })?;
+
+    Ok(ChatResponse {
+        usage: response.counts.usage(),
+        text: response.message.map(|m| m.content).unwrap_or_default(),
+        ..ChatResponse::default()
+    })
+}
+
+/// Decode one NDJSON line of a streamed `/api/chat` response.
+fn decode_line(_event: Option<&str>, line: &str) -> Result<StreamEvent, ProviderError> {
+    let chunk: Value = serde_json::from_str(line).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse Ollama stream chunk: {}", e))
+    })?;
+    if chunk.get("error").is_some() {
+        return Err(ProviderError::InvalidResponse(error_message(line)));
+    }
+
+    let delta = chunk
+        .pointer("/message/content")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if chunk.get("done").and_then(Value::as_bool).unwrap_or(false) && delta.is_empty() {
+        // The final object carries the counts; the body ends right after it.
+        let counts: EvalCounts = serde_json::from_value(chunk).unwrap_or_default();
+        return Ok(counts
+            .usage()
+            .map(StreamEvent::Usage)
+            .unwrap_or(StreamEvent::Done));
+    }
+    if delta.is_empty() {
+        return Ok(StreamEvent::Ignore);
+    }
+    Ok(StreamEvent::Text(delta.to_string()))
+}
+
+/// Ollama reports failures as `{"error": "..."}`.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| truncate_message(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_url_strips_openai_compat_suffix() {
+        assert_eq!(
+            api_url("http://localhost:11434/v1", "/api/chat"),
+            "http://localhost:11434/api/chat"
+        );
+        assert_eq!(
+            api_url("http://localhost:11434/", "/api/generate"),
+            "http://localhost:11434/api/generate"
+        );
+    }
+
+    #[test]
+    fn body_maps_limits_into_options() {
+        let request = ChatRequest {
+            system: "sys".to_string(),
+            user: "usr".to_string(),
+            max_tokens: 80,
+            temperature: 0.2,
+            schema: None,
+        };
+        let body = serde_json::to_value(build_body("codellama:7b", &request, None, false)).unwrap();
+        assert_eq!(body["options"]["num_predict"], 80);
+        assert_eq!(body["stream"], false);
+        assert!(body.get("format").is_none());
+
+        let schema = ResponseSchema {
+            name: "completion",
+            description: "",
+            schema: serde_json::json!({"type": "object"}),
+        };
+        let body = serde_json::to_value(build_body("codellama:7b", &request, Some(&schema), false))
+            .unwrap();
+        assert_eq!(body["format"]["type"], "object");
+    }
+
+    #[test]
+    fn decodes_stream_lines() {
+        assert_eq!(
+            decode_line(
+                None,
+                r#"{"message":{"role":"assistant","content":"ls"},"done":false}"#
+            )
+            .unwrap(),
+            StreamEvent::Text("ls".to_string())
+        );
+        assert_eq!(
+            decode_line(
+                None,
+                r#"{"message":{"role":"assistant","content":""},"done":true}"#
+            )
+            .unwrap(),
+            StreamEvent::Done
+        );
+        assert_eq!(
+            decode_line(
+                None,
+                r#"{"message":{"role":"assistant","content":""},"done":true,"prompt_eval_count":26,"eval_count":5}"#
+            )
+            .unwrap(),
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 26,
+                output_tokens: 5
+            })
+        );
+        assert!(decode_line(None, r#"{"error":"out of memory"}"#).is_err());
+    }
+
+    #[test]
+    fn parses_message_content_and_errors() {
+        let parsed =
+            parse_body(r#"{"message":{"role":"assistant","content":"ls -la"},"done":true}"#)
+                .unwrap();
+        assert_eq!(parsed.text, "ls -la");
+        assert_eq!(parsed.usage, None);
+        assert_eq!(
+            error_message(r#"{"error":"model 'x' not found"}"#),
+            "model 'x' not found"
+        );
+    }
+}
@@ -0,0 +1,466 @@
+//! OpenAI-compatible `/chat/completions` backend (`/completions` for native FIM).
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::provider::{
+    join_url, resolve_api_key, truncate_message, ChatRequest, ChatResponse, FimRequest,
+    LlmProvider, ProviderError, ResponseSchema, StructuredOutput, TokenUsage,
+};
+use super::stream::{self, Cutoff, Framing, StreamEvent};
+use crate::config::ModelConfig;
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    max_tokens: u32,
+    temperature: f32,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'a>>,
+}
+
+/// `{"type": "json_schema", "json_schema": {...}}`
+#[derive(Debug, Serialize)]
+struct ResponseFormat<'a> {
+    #[serde(rename = "type")]
+    format_type: &'static str,
+    json_schema: JsonSchema<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSchema<'a> {
+    name: &'a str,
+    strict: bool,
+    schema: &'a Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+/// Legacy `/completions` body; `suffix` enables fill-in-the-middle on models that support it
+#[derive(Debug, Serialize)]
+struct FimCompletionRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    suffix: &'a str,
+    max_tokens: u32,
+    temperature: f32,
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FimCompletionResponse {
+    #[serde(default)]
+    choices: Vec<FimChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FimChoice {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// OpenAI-compatible `/chat/completions` provider
+pub(super) struct OpenAiProvider {
+    client: Client,
+    model: ModelConfig,
+    structured: StructuredOutput,
+}
+
+impl OpenAiProvider {
+    pub(super) fn new(client: Client, model: ModelConfig) -> Self {
+        Self {
+            client,
+            structured: StructuredOutput::new(&model),
+            model,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        stream: bool,
+    ) -> Result<(Response, bool), ProviderError> {
+        self.structured
+            .send(&self.model, request, |schema| async move {
+                let body = build_body(&self.model.model_name, request, schema, stream);
+                self.post("/chat/completions", &body, timeout).await
+            })
+            .await
+    }
+
+    async fn post<B: Serialize + Sync>(
+        &self,
+        path: &str,
+        body: &B,
+        timeout: Duration,
+    ) -> Result<Response, ProviderError> {
+        let mut req_builder = self
+            .client
+            .post(join_url(&self.model.endpoint, path))
+            .timeout(timeout)
+            .json(body);
+        if let Some(api_key) = resolve_api_key(&self.model).await {
+            req_builder = req_builder.bearer_auth(api_key);
+        }
+
+        let response = req_builder
+            .send()
+            .await
+            .map_err(ProviderError::from_transport)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            return Err(ProviderError::from_status(
+                status,
+                &headers,
+                error_message(&text),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn id(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &ModelConfig {
+        &self.model
+    }
+
+    async fn chat(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, false).await?;
+        let text = response
+            .text()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        Ok(ChatResponse {
+            structured,
+            ..parse_body(&text)?
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        timeout: Duration,
+        cutoff: Cutoff<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let (response, structured) = self.send(request, timeout, true).await?;
+        let response = if stream::is_streamed(&response, Framing::Sse) {
+            stream::read(response, Framing::Sse, cutoff, decode_event).await?
+        } else {
+            let text = response
+                .text()
+                .await
+                .map_err(ProviderError::from_transport)?;
+            parse_body(&text)?
+        };
+        Ok(ChatResponse {
+            structured,
+            ..response
+        })
+    }
+
+    async fn fill_in_middle(
+        &self,
+        request: &FimRequest,
+        timeout: Duration,
+    ) -> Result<ChatResponse, ProviderError> {
+        if !self.model.fim.native {
+            return self.chat(&request.chat, timeout).await;
+        }
+        let body = FimCompletionRequest {
+            model: &self.model.model_name,
+            prompt: &request.prefix,
+            suffix: &request.suffix,
+            max_tokens: request.chat.max_tokens,
+            temperature: request.chat.temperature,
+            stream: false,
+        };
+        let response = self.post("/completions", &body, timeout).await?;
+        let text = response
+            .text()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        parse_fim_body(&text)
+    }
+}
+
+fn build_body<'a>(
+    model_name: &'a str,
+    request: &'a ChatRequest,
+    schema: Option<&'a ResponseSchema>,
+    stream: bool,
+) -> ChatCompletionRequest<'a> {
+    ChatCompletionRequest {
+        model: model_name,
+        messages: vec![
+            Message {
+                role: "system",
+                content: &request.system,
+            },
+            Message {
+                role: "user",
+                content: &request.user,
+            },
+        ],
+        max_tokens: request.max_tokens,
+        temperature: request.temperature,
+        stream,
+        response_format: schema.map(|schema| ResponseFormat {
+            format_type: "json_schema",
+            json_schema: JsonSchema {
+                name: schema.name,
+                strict: true,
+                schema: &schema.schema,
+            },
+        }),
+    }
+}
+
+fn parse_body(text: &str) -> Result<ChatResponse, ProviderError> {
+    let completion: ChatCompletionResponse = serde_json::from_str(text).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse LLM response: {}", e))
+    })?;
+
+    let text = completion
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.message.content)
+        .unwrap_or_default();
+
+    Ok(ChatResponse {
+        text,
+        usage: completion.usage.map(TokenUsage::from),
+        ..ChatResponse::default()
+    })
+}
+
+fn parse_fim_body(text: &str) -> Result<ChatResponse, ProviderError> {
+    let completion: FimCompletionResponse = serde_json::from_str(text).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse FIM response: {}", e))
+    })?;
+
+    Ok(ChatResponse {
+        text: completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.text)
+            .unwrap_or_default(),
+        usage: completion.usage.map(TokenUsage::from),
+        ..ChatResponse::default()
+    })
+}
+
+/// Decode one `chat.completion.chunk` SSE frame.
+fn decode_event(_event: Option<&str>, data: &str) -> Result<StreamEvent, ProviderError> {
+    if data.trim() == "[DONE]" {
+        return Ok(StreamEvent::Done);
+    }
+
+    let chunk: Value = serde_json::from_str(data).map_err(|e| {
+        ProviderError::InvalidResponse(format!("Failed to parse LLM stream chunk: {}", e))
+    })?;
+    if chunk.get("error").is_some() {
+        return Err(ProviderError::InvalidResponse(error_message(data)));
+    }
+
+    if let Some(delta) = chunk
+        .pointer("/choices/0/delta/content")
+        .and_then(Value::as_str)
+    {
+        return Ok(StreamEvent::Text(delta.to_string()));
+    }
+    // Sent as a final chunk with empty `choices` when the server includes usage
+    Ok(chunk
+        .get("usage")
+        .and_then(|usage| serde_json::from_value::<Usage>(usage.clone()).ok())
+        .map(|usage| StreamEvent::Usage(usage.into()))
+        .unwrap_or(StreamEvent::Ignore))
+}
+
+/// Extract `error.message` from an OpenAI-style error body.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| truncate_message(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_puts_system_prompt_in_messages() {
+        let request = ChatRequest {
+            system: "sys".to_string(),
+            user: "usr".to_string(),
+            max_tokens: 64,
+            temperature: 0.3,
+            schema: None,
+        };
+        let body = serde_json::to_value(build_body("gpt-4o-mini", &request, None, false)).unwrap();
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][0]["content"], "sys");
+        assert_eq!(body["messages"][1]["role"], "user");
+        assert_eq!(body["stream"], false);
+        assert!(body.get("response_format").is_none());
+    }
+
+    #[test]
+    fn body_carries_strict_json_schema() {
+        let schema = ResponseSchema {
+            name: "answer",
+            description: "",
+            schema: serde_json::json!({"type": "object"}),
+        };
+        let request = ChatRequest {
+            system: "sys".to_string(),
+            user: "usr".to_string(),
+            max_tokens: 64,
+            temperature: 0.3,
+            schema: None,
+        };
+        let body =
+            serde_json::to_value(build_body("gpt-4o-mini", &request, Some(&schema), true)).unwrap();
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "answer");
+        assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+        assert_eq!(
+            body["response_format"]["json_schema"]["schema"]["type"],
+            "object"
+        );
+    }
+
+    #[test]
+    fn parses_first_choice_content() {
+        let parsed =
+            parse_body(r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#)
+                .unwrap();
+        assert_eq!(parsed.text, "git status");
+        assert_eq!(parsed.usage, None);
+
+        let with_usage = parse_body(
+            r#"{"choices":[{"message":{"content":"ls"}}],"usage":{"prompt_tokens":120,"completion_tokens":4,"total_tokens":124}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            with_usage.usage,
+            Some(TokenUsage {
+                input_tokens: 120,
+                output_tokens: 4
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_stream_deltas_and_done() {
+        assert_eq!(
+            decode_event(
+                None,
+                r#"{"choices":[{"index":0,"delta":{"content":"git "}}]}"#
+            )
+            .unwrap(),
+            StreamEvent::Text("git ".to_string())
+        );
+        assert_eq!(
+            decode_event(
+                None,
+                r#"{"choices":[{"index":0,"delta":{"role":"assistant"}}]}"#
+            )
+            .unwrap(),
+            StreamEvent::Ignore
+        );
+        assert_eq!(
+            decode_event(
+                None,
+                r#"{"choices":[],"usage":{"prompt_tokens":50,"completion_tokens":6}}"#
+            )
+            .unwrap(),
+            StreamEvent::Usage(TokenUsage {
+                input_tokens: 50,
+                output_tokens: 6
+            })
+        );
+        assert_eq!(decode_event(None, "[DONE]").unwrap(), StreamEvent::Done);
+        assert!(decode_event(None, r#"{"error":{"message":"boom"}}"#).is_err());
+    }
+
+    #[test]
+    fn error_message_prefers_structured_field() {
+        assert_eq!(
+            error_message(r#"{"error":{"message":"Incorrect API key provided"}}"#),
+            "Incorrect API key provided"
+        );
+        assert_eq!(error_message("plain failure"), "plain failure");
+    }
+}
@@ -0,0 +1,121 @@
+//! Placeholder (tab-stop) markers in model output.
+//!
+//! The response contracts let the model mark values the user must supply as
+//! `{{name}}` or `{{name:default}}` instead of inventing them. Markers are
+//! replaced by their default (or their name) and reported as spans of the
+//! resulting command, so shell widgets can jump between them.
+
+use crate::protocol::{Placeholder, TextRange};
+
+const MAX_NAME_LEN: usize = 32;
+
+/// Replace placeholder markers in `command`, returning the plain command and
+/// the spans in order. Text that does not form a valid marker is kept as is,
+/// so shell syntax such as `${var}` or Go templates (`{{.Name}}`) survives.
+pub fn extract(command: &str) -> (String, Vec<Placeholder>) {
+    let mut text = String::with_capacity(command.len());
+    let mut placeholders = Vec::new();
+    let mut rest = command;
+
+    while let Some(open) = rest.find("{{") {
+        let (before, after_open) = rest.split_at(open);
+        text.push_str(before);
+
+        let Some((name, default, consumed)) = parse_marker(&after_open[2..]) else {
+            text.push_str("{{");
+            rest = &after_open[2..];
+            continue;
+        };
+
+        let value = default.unwrap_or(name);
+        let start = text.len();
+        let start_char = text.chars().count();
+        text.push_str(value);
+        placeholders.push(Placeholder {
+            name: name.to_string(),
+            default: default.map(str::to_string),
+            range: TextRange {
+                start,
+                end: text.len(),
+            },
+            char_range: TextRange {
+                start: start_char,
+                end: start_char + value.chars().count(),
+            },
+        });
+        rest = &after_open[2 + consumed..];
+    }
+    text.push_str(rest);
+
+    (text, placeholders)
+}
+
+/// Parse `name}}` or `name:default}}`; returns the parts and the bytes consumed.
+fn parse_marker(input: &str) -> Option<(&str, Option<&str>, usize)> {
+    let close = input.find("}}")?;
+    let body = &input[..close];
+    if body.contains(['{', '\n']) {
+        return None;
+    }
+
+    let (name, default) = match body.split_once(':') {
+        Some((name, default)) => (name, Some(default.trim()).filter(|d| !d.is_empty())),
+        None => (body, None),
+    };
+    let valid_name = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_name {
+        return None;
+    }
+
+    Some((name, default, close + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_become_spans_with_defaults() {
+        let (text, placeholders) =
+            extract(r#"git commit -m "{{message}}" && git push origin {{branch:main}}"#);
+        assert_eq!(text, r#"git commit -m "message" && git push origin main"#);
+        assert_eq!(placeholders.len(), 2);
+
+        assert_eq!(placeholders[0].name, "message");
+        assert_eq!(placeholders[0].default, None);
+        let first = placeholders[0].range;
+        assert_eq!(&text[first.start..first.end], "message");
+
+        assert_eq!(placeholders[1].name, "branch");
+        assert_eq!(placeholders[1].default.as_deref(), Some("main"));
+        let second = placeholders[1].range;
+        assert_eq!(&text[second.start..second.end], "main");
+    }
+
+    #[test]
+    fn char_ranges_account_for_multibyte_text() {
+        let (text, placeholders) = extract("echo 日本 {{word:ä}}");
+        assert_eq!(text, "echo 日本 ä");
+        assert_eq!(placeholders[0].range, TextRange { start: 12, end: 14 });
+        assert_eq!(placeholders[0].char_range, TextRange { start: 8, end: 9 });
+    }
+
+    #[test]
+    fn non_marker_braces_are_kept() {
+        for command in [
+            "docker inspect -f '{{.State.Status}}' web",
+            "echo ${HOME} {{ spaced }}",
+            "echo {{unclosed",
+            "awk '{print $1}'",
+        ] {
+            let (text, placeholders) = extract(command);
+            assert_eq!(text, command);
+            assert!(placeholders.is_empty(), "{}", command);
+        }
+    }
+}
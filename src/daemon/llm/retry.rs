@@ -0,0 +1,156 @@
+//! Bounded retries of rate-limited and overloaded requests.
+//!
+//! A retryable failure (429 or 5xx) is retried after a jittered exponential
+//! backoff, or after the provider's `Retry-After` when it sent one. Retries
+//! never extend the request deadline: each attempt gets the time that is
+//! left, and a wait that would not leave room for another attempt ends the
+//! loop with the last error. Auto-mode requests use [`RetryPolicy::disabled`]
+//! because the next keystroke supersedes them anyway.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use super::provider::{ChatResponse, ProviderError};
+use crate::config::RetryConfig;
+
+/// Retry settings for one routed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+        }
+    }
+
+    /// Single attempt, no retries
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    /// Wait before retry number `retry` (0-based): the provider's
+    /// `Retry-After` if present, otherwise half the exponential step plus a
+    /// random share of the other half.
+    pub fn delay(&self, retry: u32, error: &ProviderError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after;
+        }
+        let step = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        let half = step / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// Run `call` until it succeeds, fails with a non-retryable error, runs out of
+/// retries, or the next wait would pass the deadline `timeout` from now.
+///
+/// `call` receives the time left before the deadline as its timeout.
+pub async fn run<F, Fut, E>(
+    policy: &RetryPolicy,
+    timeout: Duration,
+    mut call: F,
+) -> Result<ChatResponse, E>
+where
+    F: FnMut(Duration) -> Fut,
+    Fut: Future<Output = Result<ChatResponse, E>>,
+    E: Borrow<ProviderError> + std::fmt::Display,
+{
+    let deadline = Instant::now() + timeout;
+    let mut retry = 0;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let error = match call(remaining).await {
+            Ok(response) => return Ok(response),
+            Err(error) => error,
+        };
+        if !error.borrow().is_retryable() || retry >= policy.max_retries {
+            if retry > 0 {
+                debug!("LLM request failed after {} retries: {}", retry, error);
+            }
+            return Err(error);
+        }
+
+        let delay = policy.delay(retry, error.borrow());
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if delay >= remaining {
+            debug!(
+                "Not retrying LLM request ({}): backoff {}ms exceeds remaining {}ms",
+                error,
+                delay.as_millis(),
+                remaining.as_millis()
+            );
+            return Err(error);
+        }
+
+        retry += 1;
+        debug!(
+            "Retrying LLM request ({}/{}) in {}ms after: {}",
+            retry,
+            policy.max_retries,
+            delay.as_millis(),
+            error
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Uniform value in `[0, 1)`; `RandomState` is seeded randomly per instance.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error() -> ProviderError {
+        ProviderError::Server {
+            status: 500,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_within_bounds_and_is_capped() {
+        let policy = RetryPolicy::new(&RetryConfig {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+        });
+        for (retry, step) in [(0, 100), (1, 200), (2, 300), (10, 300), (40, 300)] {
+            let delay = policy.delay(retry, &server_error());
+            let step = Duration::from_millis(step);
+            assert!(delay >= step / 2 && delay <= step, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn retry_after_overrides_backoff() {
+        let policy = RetryPolicy::new(&RetryConfig::default());
+        let error = ProviderError::RateLimited {
+            message: String::new(),
+            retry_after: Some(Duration::from_secs(7)),
+        };
+        assert_eq!(policy.delay(0, &error), Duration::from_secs(7));
+    }
+}
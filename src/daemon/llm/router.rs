@@ -0,0 +1,425 @@
+//! Routing of requests to named model profiles.
+//!
+//! `model.routes` maps a shell mode and/or request kind to a profile from
+//! `model.profiles`; the first matching rule wins and unmatched requests use
+//! the top-level `model`. Every referenced profile gets its own fallback
+//! chain, and all chains share the pooled HTTP clients.
+//!
+//! Once `usage.budget` is exhausted, requests either go to the budget's
+//! fallback profile or are refused so only cached suggestions are served.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use tracing::debug;
+
+use super::chain::ProviderChain;
+use super::provider::{ChatRequest, ChatResponse, HttpClients, TokenUsage};
+use super::retry::RetryPolicy;
+use crate::config::{
+    BudgetAction, Config, ModelConfig, ModelRouteConfig, RequestKind, DEFAULT_MODEL_PROFILE,
+};
+use crate::daemon::shell_mode::ShellMode;
+use crate::daemon::tokens;
+use crate::daemon::usage::UsageTracker;
+use crate::protocol::BackendStatus;
+
+/// Per-profile generation parameters; `None` keeps the request kind's built-in default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GenerationSettings {
+    pub timeout_ms: Option<u64>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stream: Option<bool>,
+}
+
+/// Profile selected for one request
+pub struct Route<'a> {
+    pub profile: &'a str,
+    pub provider: &'a ProviderChain,
+    pub settings: GenerationSettings,
+    pub kind: RequestKind,
+    /// Disabled for auto-mode shells
+    pub retry: RetryPolicy,
+    usage: Option<&'a UsageTracker>,
+}
+
+impl Route<'_> {
+    /// Timeout of one model call: the profile's `timeout_ms`, or the request
+    /// kind's own setting
+    pub fn timeout_ms(&self, config: &Config) -> u64 {
+        self.settings.timeout_ms.unwrap_or(match self.kind {
+            RequestKind::Completion => config.model.timeout_ms,
+            RequestKind::Diagnosis => config.diagnosis.timeout_ms,
+            RequestKind::Ask => config.ask.timeout_ms,
+            RequestKind::Explain => config.explain.timeout_ms,
+        })
+    }
+
+    /// Config key that sets [`Route::timeout_ms`]
+    pub fn timeout_key(&self) -> String {
+        if self.settings.timeout_ms.is_some() {
+            return format!("model.profiles.{}.timeout_ms", self.profile);
+        }
+        match self.kind {
+            RequestKind::Completion => "model.timeout_ms",
+            RequestKind::Diagnosis => "diagnosis.timeout_ms",
+            RequestKind::Ask => "ask.timeout_ms",
+            RequestKind::Explain => "explain.timeout_ms",
+        }
+        .to_string()
+    }
+
+    /// Count an answered request against the usage ledger.
+    /// Reported prompt sizes also calibrate the token estimate of the model
+    /// that answered, which is a fallback when the chain fell back.
+    pub fn record_usage(&self, request: &ChatRequest, response: &ChatResponse) {
+        let model = response
+            .model
+            .as_deref()
+            .unwrap_or(&self.provider.model().model_name);
+        if let Some(usage) = response.usage {
+            tokens::observe(model, request, usage.input_tokens);
+        }
+        let Some(tracker) = self.usage else {
+            return;
+        };
+        let tokens = response
+            .usage
+            .unwrap_or_else(|| TokenUsage::estimate(request, &response.text));
+        tracker.record(self.kind, model, tokens);
+    }
+}
+
+/// The usage budget is exhausted and `on_exceeded` is `cache`
+#[derive(Debug, thiserror::Error)]
+#[error("Usage budget exceeded: {0}")]
+pub struct BudgetExceeded(pub String);
+
+struct RoutedProfile {
+    chain: ProviderChain,
+    settings: GenerationSettings,
+    retry: RetryPolicy,
+}
+
+/// Maps shell mode and request kind to a profile's provider chain
+pub struct ModelRouter {
+    default: RoutedProfile,
+    profiles: BTreeMap<String, RoutedProfile>,
+    routes: Vec<ModelRouteConfig>,
+    clients: HttpClients,
+    usage: Option<UsageTracker>,
+}
+
+impl ModelRouter {
+    /// Build chains for the top-level model and every profile referenced by a route.
+    pub fn from_config(model: &ModelConfig) -> Result<Self> {
+        let clients = HttpClients::new(&model.http)?;
+        let default = RoutedProfile {
+            chain: ProviderChain::with_client(DEFAULT_MODEL_PROFILE, model, &clients),
+            settings: GenerationSettings {
+                // The top-level timeout only applies to completions; diagnosis
+                // keeps `diagnosis.timeout_ms`.
+                timeout_ms: None,
+                temperature: model.temperature,
+                max_tokens: model.max_tokens,
+                stream: None,
+            },
+            retry: RetryPolicy::new(&model.retry),
+        };
+
+        let mut router = Self {
+            default,
+            profiles: BTreeMap::new(),
+            routes: model.routes.clone(),
+            clients,
+            usage: None,
+        };
+        for route in &model.routes {
+            router.add_profile(model, &route.profile)?;
+        }
+        Ok(router)
+    }
+
+    /// Record token usage and enforce `usage.budget` (including its fallback profile).
+    pub fn with_usage(mut self, config: &Config) -> Result<Self> {
+        if !config.usage.enabled {
+            return Ok(self);
+        }
+        let budget = &config.usage.budget;
+        if budget.on_exceeded == BudgetAction::Fallback {
+            if let Some(profile) = &budget.fallback_profile {
+                self.add_profile(&config.model, profile)?;
+            }
+        }
+        self.usage = Some(UsageTracker::new(&config.usage));
+        Ok(self)
+    }
+
+    fn add_profile(&mut self, model: &ModelConfig, name: &str) -> Result<()> {
+        if name == DEFAULT_MODEL_PROFILE || self.profiles.contains_key(name) {
+            return Ok(());
+        }
+        let Some(profile) = model.profiles.get(name) else {
+            anyhow::bail!("Unknown model profile '{}'", name);
+        };
+        let resolved = model.with_profile(profile);
+        self.profiles.insert(
+            name.to_string(),
+            RoutedProfile {
+                chain: ProviderChain::with_client(name, &resolved, &self.clients),
+                settings: GenerationSettings {
+                    timeout_ms: profile.timeout_ms,
+                    temperature: resolved.temperature,
+                    max_tokens: resolved.max_tokens,
+                    stream: Some(resolved.stream),
+                },
+                retry: RetryPolicy::new(&resolved.retry),
+            },
+        );
+        Ok(())
+    }
+
+    /// Select the profile for a request.
+    pub fn route(
+        &self,
+        kind: RequestKind,
+        shell_mode: ShellMode,
+    ) -> Result<Route<'_>, BudgetExceeded> {
+        let (mut name, mut profile) = self
+            .routes
+            .iter()
+            .find(|rule| matches(rule, kind, shell_mode))
+            .and_then(|rule| self.named(&rule.profile))
+            .unwrap_or((DEFAULT_MODEL_PROFILE, &self.default));
+
+        if let Some(tracker) = &self.usage {
+            if let Some(reason) = tracker.budget_exceeded() {
+                let budget = tracker.budget();
+                let fallback = budget
+                    .fallback_profile
+                    .as_deref()
+                    .and_then(|fallback| self.named(fallback));
+                match (budget.on_exceeded, fallback) {
+                    (BudgetAction::Fallback, Some(fallback)) => {
+                        debug!("{}; routing to profile '{}'", reason, fallback.0);
+                        (name, profile) = fallback;
+                    }
+                    _ => return Err(BudgetExceeded(reason)),
+                }
+            }
+        }
+
+        Ok(Route {
+            profile: name,
+            provider: &profile.chain,
+            settings: profile.settings,
+            kind,
+            retry: if shell_mode.is_auto() {
+                RetryPolicy::disabled()
+            } else {
+                profile.retry
+            },
+            usage: self.usage.as_ref(),
+        })
+    }
+
+    fn named(&self, name: &str) -> Option<(&str, &RoutedProfile)> {
+        if name == DEFAULT_MODEL_PROFILE {
+            return Some((DEFAULT_MODEL_PROFILE, &self.default));
+        }
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    /// Backends of every profile, default first
+    pub fn status(&self) -> Vec<BackendStatus> {
+        std::iter::once(&self.default)
+            .chain(self.profiles.values())
+            .flat_map(|profile| profile.chain.status())
+            .collect()
+    }
+}
+
+fn matches(rule: &ModelRouteConfig, kind: RequestKind, shell_mode: ShellMode) -> bool {
+    rule.kind.is_none_or(|k| k == kind)
+        && rule
+            .shell_mode
+            .as_deref()
+            .is_none_or(|mode| mode == shell_mode.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelProfileConfig;
+
+    fn model_with_routes() -> ModelConfig {
+        let mut model = ModelConfig::default();
+        model.profiles.insert(
+            "fast".to_string(),
+            ModelProfileConfig {
+                model_name: Some("qwen2.5-coder:1.5b".to_string()),
+                timeout_ms: Some(800),
+                max_tokens: Some(48),
+                stream: Some(false),
+                ..ModelProfileConfig::default()
+            },
+        );
+        model.profiles.insert(
+            "strong".to_string(),
+            ModelProfileConfig {
+                model_name: Some("gpt-4o".to_string()),
+                temperature: Some(0.1),
+                ..ModelProfileConfig::default()
+            },
+        );
+        model.routes = vec![
+            ModelRouteConfig {
+                shell_mode: Some("zsh-auto".to_string()),
+                kind: Some(RequestKind::Completion),
+                profile: "fast".to_string(),
+            },
+            ModelRouteConfig {
+                shell_mode: None,
+                kind: Some(RequestKind::Diagnosis),
+                profile: "strong".to_string(),
+            },
+            ModelRouteConfig {
+                shell_mode: Some("bash-popup".to_string()),
+                kind: None,
+                profile: "strong".to_string(),
+            },
+        ];
+        model
+    }
+
+    #[test]
+    fn first_matching_rule_selects_profile() {
+        let router = ModelRouter::from_config(&model_with_routes()).unwrap();
+
+        let auto = router
+            .route(RequestKind::Completion, ShellMode::ZshAuto)
+            .unwrap();
+        assert_eq!(auto.profile, "fast");
+        assert_eq!(auto.settings.timeout_ms, Some(800));
+        assert_eq!(auto.settings.max_tokens, Some(48));
+        assert_eq!(auto.settings.stream, Some(false));
+        assert_eq!(auto.retry, RetryPolicy::disabled());
+
+        let diagnosis = router
+            .route(RequestKind::Diagnosis, ShellMode::ZshAuto)
+            .unwrap();
+        assert_eq!(diagnosis.profile, "strong");
+        assert_eq!(diagnosis.settings.temperature, Some(0.1));
+        assert_eq!(diagnosis.settings.timeout_ms, None);
+
+        let popup = router
+            .route(RequestKind::Completion, ShellMode::BashPopup)
+            .unwrap();
+        assert_eq!(popup.profile, "strong");
+    }
+
+    #[test]
+    fn unmatched_request_uses_default_model() {
+        let router = ModelRouter::from_config(&model_with_routes()).unwrap();
+        let route = router
+            .route(RequestKind::Completion, ShellMode::ZshInline)
+            .unwrap();
+        assert_eq!(route.profile, DEFAULT_MODEL_PROFILE);
+        assert_eq!(route.settings, GenerationSettings::default());
+        assert_eq!(route.retry.max_retries, 2);
+    }
+
+    #[test]
+    fn status_lists_default_then_routed_profiles() {
+        let router = ModelRouter::from_config(&model_with_routes()).unwrap();
+        let profiles: Vec<_> = router.status().into_iter().map(|b| b.profile).collect();
+        assert_eq!(profiles, vec!["default", "fast", "strong"]);
+    }
+
+    #[test]
+    fn usage_calibrates_the_model_that_answered() {
+        let model = ModelConfig {
+            model_name: "calibrate-primary".to_string(),
+            ..ModelConfig::default()
+        };
+        let router = ModelRouter::from_config(&model).unwrap();
+        let route = router
+            .route(RequestKind::Completion, ShellMode::ZshInline)
+            .unwrap();
+        let request = ChatRequest {
+            system: String::new(),
+            user: "x".repeat(400),
+            max_tokens: 10,
+            temperature: 0.0,
+            schema: None,
+        };
+        route.record_usage(
+            &request,
+            &ChatResponse {
+                usage: Some(TokenUsage {
+                    input_tokens: 500,
+                    output_tokens: 1,
+                }),
+                model: Some("calibrate-fallback".to_string()),
+                ..ChatResponse::default()
+            },
+        );
+
+        assert!(tokens::calibration("calibrate-fallback").is_some());
+        assert_eq!(tokens::calibration("calibrate-primary"), None);
+    }
+
+    #[test]
+    fn exhausted_budget_falls_back_or_refuses() {
+        use crate::config::{BudgetConfig, UsageConfig};
+
+        let dir = std::env::temp_dir().join(format!("nudge-router-budget-{}", std::process::id()));
+        let path = dir.join("usage.json");
+        let mut usage = UsageConfig {
+            enabled: true,
+            budget: BudgetConfig {
+                daily_requests: Some(1),
+                on_exceeded: BudgetAction::Fallback,
+                fallback_profile: Some("fast".to_string()),
+                ..BudgetConfig::default()
+            },
+        };
+
+        let model = model_with_routes();
+        let mut router = ModelRouter::from_config(&model).unwrap();
+        router.usage = Some(UsageTracker::with_path(&usage, path.clone()));
+        let route = router
+            .route(RequestKind::Diagnosis, ShellMode::ZshAuto)
+            .unwrap();
+        assert_eq!(route.profile, "strong");
+        route.record_usage(
+            &ChatRequest {
+                system: String::new(),
+                user: "x".repeat(40),
+                max_tokens: 10,
+                temperature: 0.0,
+                schema: None,
+            },
+            &ChatResponse::default(),
+        );
+
+        let route = router
+            .route(RequestKind::Diagnosis, ShellMode::ZshAuto)
+            .unwrap();
+        assert_eq!(route.profile, "fast");
+
+        // Dropping the tracker flushes its records for the next one.
+        router.usage = None;
+        usage.budget.on_exceeded = BudgetAction::Cache;
+        router.usage = Some(UsageTracker::with_path(&usage, path));
+        let err = router
+            .route(RequestKind::Completion, ShellMode::ZshInline)
+            .err()
+            .expect("cache-only budget refuses new requests");
+        assert!(err.to_string().contains("daily request budget reached"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
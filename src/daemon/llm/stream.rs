@@ -0,0 +1,237 @@
+//! Incremental decoding of streamed provider responses.
+//!
+//! OpenAI-compatible and Anthropic endpoints stream Server-Sent Events
+//! (`data:` lines), Ollama's native API streams newline-delimited JSON. Both
+//! are fed through [`read`], which accumulates text deltas and stops reading
+//! (dropping the connection, which aborts generation upstream) as soon as the
+//! caller's [`Cutoff`] reports that a usable answer has arrived.
+
+use reqwest::Response;
+use tracing::debug;
+
+use super::provider::{ChatResponse, ProviderError, TokenUsage};
+
+/// Inspect accumulated text; return the byte length to keep once it holds a
+/// complete answer, or `None` to keep reading.
+pub type Cutoff<'a> = &'a (dyn Fn(&str) -> Option<usize> + Send + Sync);
+
+/// Wire framing of a streamed response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Framing {
+    /// `text/event-stream`
+    Sse,
+    /// `application/x-ndjson`
+    Ndjson,
+}
+
+/// Decoded meaning of one stream frame
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum StreamEvent {
+    Text(String),
+    /// Token counts, usually sent with or right before the final frame
+    Usage(TokenUsage),
+    Done,
+    Ignore,
+}
+
+/// Read a streamed body, feeding each frame (`event` name, `data` payload) to `decode`.
+pub(super) async fn read<F>(
+    mut response: Response,
+    framing: Framing,
+    cutoff: Cutoff<'_>,
+    mut decode: F,
+) -> Result<ChatResponse, ProviderError>
+where
+    F: FnMut(Option<&str>, &str) -> Result<StreamEvent, ProviderError> + Send,
+{
+    let mut lines = LineBuffer::default();
+    let mut frames = FrameParser::new(framing);
+    let mut text = String::new();
+    let mut usage: Option<TokenUsage> = None;
+
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(ProviderError::from_transport)?;
+        let pending = match &chunk {
+            Some(bytes) => lines.push(bytes),
+            None => lines.finish(),
+        };
+
+        for line in pending {
+            let Some((event, data)) = frames.feed(&line) else {
+                continue;
+            };
+            match decode(event.as_deref(), &data)? {
+                StreamEvent::Text(delta) => {
+                    text.push_str(&delta);
+                    if let Some(end) = cutoff(&text) {
+                        debug!("Stream cut off after {} bytes", end);
+                        text.truncate(end);
+                        return Ok(ChatResponse {
+                            text,
+                            stopped_early: true,
+                            usage,
+                            model: None,
+                            structured: false,
+                        });
+                    }
+                }
+                StreamEvent::Usage(reported) => {
+                    usage = Some(usage.unwrap_or_default().merge(reported));
+                }
+                StreamEvent::Done => {
+                    return Ok(ChatResponse {
+                        text,
+                        stopped_early: false,
+                        usage,
+                        model: None,
+                        structured: false,
+                    })
+                }
+                StreamEvent::Ignore => {}
+            }
+        }
+
+        if chunk.is_none() {
+            return Ok(ChatResponse {
+                text,
+                stopped_early: false,
+                usage,
+                model: None,
+                structured: false,
+            });
+        }
+    }
+}
+
+/// Splits a byte stream into complete lines without breaking UTF-8 sequences.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line[..line.len() - 1]);
+            lines.push(line.trim_end_matches('\r').to_string());
+        }
+        lines
+    }
+
+    /// Flush a trailing line that was not newline-terminated.
+    fn finish(&mut self) -> Vec<String> {
+        if self.buf.is_empty() {
+            return Vec::new();
+        }
+        let line = String::from_utf8_lossy(&self.buf).trim_end().to_string();
+        self.buf.clear();
+        // A blank line also dispatches any SSE event still being assembled.
+        vec![line, String::new()]
+    }
+}
+
+/// Assembles lines into frames according to the framing.
+#[derive(Debug)]
+struct FrameParser {
+    framing: Framing,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl FrameParser {
+    fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            event: None,
+            data: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, line: &str) -> Option<(Option<String>, String)> {
+        if self.framing == Framing::Ndjson {
+            let line = line.trim();
+            return (!line.is_empty()).then(|| (None, line.to_string()));
+        }
+
+        if line.is_empty() {
+            if self.data.is_empty() {
+                self.event = None;
+                return None;
+            }
+            let data = std::mem::take(&mut self.data).join("\n");
+            return Some((self.event.take(), data));
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Whether the response body is streamed in `framing` (servers may ignore `stream: true`).
+pub(super) fn is_streamed(response: &Response, framing: Framing) -> bool {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    match framing {
+        Framing::Sse => content_type.starts_with("text/event-stream"),
+        Framing::Ndjson => !content_type.starts_with("application/json"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_buffer_handles_split_utf8_and_crlf() {
+        let mut lines = LineBuffer::default();
+        let bytes = "data: ä\r\n".as_bytes();
+        assert!(lines.push(&bytes[..7]).is_empty());
+        assert_eq!(lines.push(&bytes[7..]), vec!["data: ä".to_string()]);
+        assert!(lines.finish().is_empty());
+    }
+
+    #[test]
+    fn sse_frames_join_data_lines_and_keep_event_name() {
+        let mut parser = FrameParser::new(Framing::Sse);
+        assert_eq!(parser.feed(": keep-alive"), None);
+        assert_eq!(parser.feed("event: content_block_delta"), None);
+        assert_eq!(parser.feed("data: {\"a\":"), None);
+        assert_eq!(parser.feed("data: 1}"), None);
+        assert_eq!(
+            parser.feed(""),
+            Some((
+                Some("content_block_delta".to_string()),
+                "{\"a\":\n1}".to_string()
+            ))
+        );
+        assert_eq!(parser.feed(""), None);
+    }
+
+    #[test]
+    fn ndjson_frames_are_single_lines() {
+        let mut parser = FrameParser::new(Framing::Ndjson);
+        assert_eq!(
+            parser.feed("{\"done\":true}"),
+            Some((None, "{\"done\":true}".to_string()))
+        );
+        assert_eq!(parser.feed("  "), None);
+    }
+}
@@ -1,14 +1,22 @@
+pub mod ask;
 pub mod context;
 pub mod diagnosis;
+pub mod explain;
 pub mod llm;
 pub mod plugins;
+pub mod policy;
+pub mod predictor;
 pub mod prompts;
 pub mod safety;
 pub mod sanitizer;
 pub mod server;
 pub mod session;
 pub mod shell_mode;
+pub mod shell_words;
 pub mod suggestion_cache;
+pub mod tokens;
+pub mod usage;
+pub mod validation;
 
 use std::fs;
 use std::process::Command;
@@ -22,11 +30,15 @@ use crate::config::Config;
 /// Config is passed in to avoid duplicate loading
 pub async fn run(config: Config, foreground: bool, fork: bool) -> Result<()> {
     // Validate LLM configuration
-    if let Err(e) = config.validate_llm_config() {
-        eprintln!("\n\x1b[1;31mError: LLM configuration issue\x1b[0m\n");
-        eprintln!("{}", e);
-        eprintln!();
-        anyhow::bail!("Cannot start daemon without valid LLM configuration");
+    match config.validate_llm_config() {
+        Ok(Some(source)) => info!("API key source: {}", source),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("\n\x1b[1;31mError: LLM configuration issue\x1b[0m\n");
+            eprintln!("{:#}", e);
+            eprintln!();
+            anyhow::bail!("Cannot start daemon without valid LLM configuration");
+        }
     }
 
     // Ensure config directory exists (Unix only - socket is a filesystem path)
@@ -112,7 +124,7 @@ fn validate_config_before_start() -> Result<Config> {
     // Validate LLM configuration
     if let Err(e) = config.validate_llm_config() {
         eprintln!("\n\x1b[1;31mError: LLM configuration issue\x1b[0m\n");
-        eprintln!("{}", e);
+        eprintln!("{:#}", e);
         eprintln!();
         anyhow::bail!("Cannot start daemon without valid LLM configuration");
     }
@@ -266,6 +278,15 @@ pub async fn status() -> Result<()> {
     let (running, pid) = is_running_with_cleanup();
     if running {
         println!("Nudge daemon is running (pid: {})", pid);
+        match crate::client::ipc::send_status_request().await {
+            Ok(status) => {
+                println!("LLM backends (* = active):");
+                for backend in &status.backends {
+                    println!("  {}", backend.summary());
+                }
+            }
+            Err(e) => println!("LLM backend status unavailable: {}", e),
+        }
         Ok(())
     } else {
         println!("Nudge daemon is not running");
@@ -0,0 +1,290 @@
+//! Privacy policy: what a request may send to the model, and where.
+//!
+//! `privacy.excluded_paths` picks a mode for working directories matching a
+//! glob: no model request at all, the buffer alone, or the gathered context
+//! without the opted-out fields. `privacy.local_only` refuses every model
+//! endpoint that is not on a loopback address.
+
+use std::path::Path;
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::llm::provider::is_loopback;
+use super::llm::router::Route;
+use crate::config::{expand_home, ExcludedMode, ExcludedPath, PrivacyConfig};
+
+/// Policy applied to one request
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedPolicy {
+    /// `privacy.excluded_paths` glob matching the working directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_path: Option<String>,
+    pub mode: ExcludedMode,
+    pub no_files: bool,
+    pub no_history: bool,
+    pub local_only: bool,
+}
+
+impl AppliedPolicy {
+    /// Policy for a request from `cwd`: the first matching excluded path, if any.
+    /// Symlinks and `..` in `cwd` are resolved first, so they cannot lead
+    /// around a rule; the path as given still matches as well.
+    pub fn resolve(privacy: &PrivacyConfig, cwd: &Path) -> Self {
+        let canonical = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+        let rule = privacy
+            .excluded_paths
+            .iter()
+            .find(|rule| glob_matches(&rule.path, &canonical) || glob_matches(&rule.path, cwd));
+        let ExcludedPath {
+            path,
+            mode,
+            no_files,
+            no_history,
+        } = rule.cloned().unwrap_or_else(|| ExcludedPath {
+            mode: ExcludedMode::Context,
+            ..Default::default()
+        });
+        Self {
+            excluded_path: rule.map(|_| path),
+            mode,
+            no_files,
+            no_history,
+            local_only: privacy.local_only,
+        }
+    }
+
+    /// Whether gathered context may be sent
+    pub fn sends_context(&self) -> bool {
+        self.mode == ExcludedMode::Context
+    }
+
+    /// Why no model request may be made from this directory, if so
+    pub fn refusal(&self) -> Option<String> {
+        match (&self.excluded_path, self.mode) {
+            (Some(path), ExcludedMode::Off) => Some(format!(
+                "Model requests are disabled here by privacy.excluded_paths ({})",
+                path
+            )),
+            _ => None,
+        }
+    }
+
+    /// One-line summary, e.g. `~/clients/** (buffer_only, no_history), local_only`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match &self.excluded_path {
+            Some(path) => {
+                let mut options = vec![match self.mode {
+                    ExcludedMode::Off => "off",
+                    ExcludedMode::BufferOnly => "buffer_only",
+                    ExcludedMode::Context => "context",
+                }];
+                if self.no_files {
+                    options.push("no_files");
+                }
+                if self.no_history {
+                    options.push("no_history");
+                }
+                parts.push(format!("{} ({})", path, options.join(", ")));
+            }
+            None => parts.push("default".to_string()),
+        }
+        if self.local_only {
+            parts.push("local_only".to_string());
+        }
+        parts.join(", ")
+    }
+
+    /// Why `route` may not be used, if `local_only` rules out one of its
+    /// endpoints. Loopback endpoints never go through `model.http.proxy`,
+    /// so the proxy does not matter for a route `local_only` allows.
+    pub fn check_route(&self, route: &Route<'_>) -> Result<(), String> {
+        self.check_endpoints(&route.provider.endpoints())
+    }
+
+    fn check_endpoints(&self, endpoints: &[String]) -> Result<(), String> {
+        if !self.local_only {
+            return Ok(());
+        }
+        match endpoints.iter().find(|endpoint| !is_loopback(endpoint)) {
+            Some(endpoint) => Err(format!(
+                "privacy.local_only refuses the non-loopback endpoint {}",
+                endpoint
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether `path` matches `pattern`: `*` and `?` stay within one component,
+/// `**` spans any number of them, and a leading `~/` is the home directory.
+/// A pattern also matches everything below the directories it matches.
+pub fn glob_matches(pattern: &str, path: &Path) -> bool {
+    let pattern = expand_home(Path::new(pattern));
+    let pattern = pattern.to_string_lossy();
+    let pattern = pattern.trim_end_matches('/');
+    if pattern.is_empty() {
+        return false;
+    }
+
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str("(?:/.*)?$");
+
+    Regex::new(&regex)
+        .map(|regex| regex.is_match(&path.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn privacy(rules: Vec<ExcludedPath>) -> PrivacyConfig {
+        PrivacyConfig {
+            excluded_paths: rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn globs_match_directories_and_their_contents() {
+        assert!(glob_matches("/srv/customers", Path::new("/srv/customers")));
+        assert!(glob_matches(
+            "/srv/customers/",
+            Path::new("/srv/customers/acme/src")
+        ));
+        assert!(!glob_matches(
+            "/srv/customers",
+            Path::new("/srv/customers-old")
+        ));
+        assert!(glob_matches(
+            "/srv/*/secrets",
+            Path::new("/srv/acme/secrets/x")
+        ));
+        assert!(!glob_matches(
+            "/srv/*/secrets",
+            Path::new("/srv/a/b/secrets")
+        ));
+        assert!(glob_matches(
+            "/srv/**/secrets",
+            Path::new("/srv/a/b/secrets")
+        ));
+        assert!(glob_matches("/tmp/case-?", Path::new("/tmp/case-7")));
+        assert!(!glob_matches("", Path::new("/")));
+
+        if let Some(dirs) = directories::BaseDirs::new() {
+            let home: PathBuf = dirs.home_dir().join("work/creds");
+            assert!(glob_matches("~/work/creds", &home));
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_applies() {
+        let privacy = privacy(vec![
+            ExcludedPath {
+                path: "/srv/customers/**".to_string(),
+                mode: ExcludedMode::Off,
+                ..Default::default()
+            },
+            ExcludedPath {
+                path: "/srv".to_string(),
+                mode: ExcludedMode::Context,
+                no_files: true,
+                no_history: false,
+            },
+        ]);
+
+        let policy = AppliedPolicy::resolve(&privacy, Path::new("/srv/customers/acme"));
+        assert_eq!(policy.excluded_path.as_deref(), Some("/srv/customers/**"));
+        assert!(policy.refusal().is_some());
+        assert!(!policy.sends_context());
+
+        let policy = AppliedPolicy::resolve(&privacy, Path::new("/srv/tools"));
+        assert!(policy.refusal().is_none());
+        assert!(policy.sends_context());
+        assert!(policy.no_files && !policy.no_history);
+
+        let policy = AppliedPolicy::resolve(&privacy, Path::new("/home/dev"));
+        assert_eq!(policy.excluded_path, None);
+        assert_eq!(policy.mode, ExcludedMode::Context);
+        assert!(!policy.no_files);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_and_dot_dot_do_not_escape_a_rule() {
+        let root = std::env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .join(format!("nudge-policy-{}", std::process::id()));
+        let acme = root.join("customers/acme");
+        std::fs::create_dir_all(&acme).unwrap();
+        std::fs::create_dir_all(root.join("tools")).unwrap();
+        let link = root.join("link");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(root.join("customers"), &link).unwrap();
+
+        let privacy = privacy(vec![ExcludedPath {
+            path: root.join("customers").to_string_lossy().into_owned(),
+            mode: ExcludedMode::Off,
+            ..Default::default()
+        }]);
+        for cwd in [link.join("acme"), root.join("tools/../customers/acme")] {
+            let policy = AppliedPolicy::resolve(&privacy, &cwd);
+            assert!(policy.refusal().is_some(), "{}", cwd.display());
+        }
+        assert!(AppliedPolicy::resolve(&privacy, &root.join("tools"))
+            .refusal()
+            .is_none());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn loopback_endpoints() {
+        assert!(is_loopback("http://localhost:11434"));
+        assert!(is_loopback("http://127.0.0.1:8080/v1"));
+        assert!(is_loopback("http://[::1]:8080"));
+        assert!(!is_loopback("https://api.openai.com/v1"));
+        assert!(!is_loopback("http://192.168.1.10:11434"));
+        assert!(!is_loopback("http://localhost.evil.com"));
+        assert!(!is_loopback("not a url"));
+        assert!(is_loopback("socks5://127.0.0.1:1080"));
+        assert!(!is_loopback("http://proxy.corp:3128"));
+    }
+
+    #[test]
+    fn local_only_refuses_remote_endpoints_only() {
+        let mut policy = AppliedPolicy::resolve(&PrivacyConfig::default(), Path::new("/tmp"));
+        let local = vec!["http://localhost:11434".to_string()];
+        let remote = vec![
+            "http://127.0.0.1:8080".to_string(),
+            "https://api.openai.com/v1".to_string(),
+        ];
+        assert!(policy.check_endpoints(&remote).is_ok());
+
+        policy.local_only = true;
+        // A corporate proxy is not used for loopback endpoints.
+        assert!(policy.check_endpoints(&local).is_ok());
+        let err = policy.check_endpoints(&remote).unwrap_err();
+        assert!(
+            err.contains("endpoint https://api.openai.com/v1"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Offline command prediction from shell history.
+//!
+//! Two models are built over the most recent history entries, both weighted
+//! by frecency (every run of a command counts `0.5^(age / half_life)`, with
+//! the age measured in commands):
+//!
+//! - a prefix trie over whole commands, answering with previously run
+//!   commands that start with the buffer;
+//! - an n-gram model over whitespace-separated tokens (trigrams backing off
+//!   to bigrams) that extends the buffer token by token when no previously
+//!   run command matches.
+//!
+//! Predictions need no network, so they serve as the fallback when the LLM is
+//! unavailable and as a fast first answer (see `predictor.mode`).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use tracing::debug;
+
+use crate::config::PredictorConfig;
+use crate::daemon::context::history;
+
+/// Commands remembered per trie node
+const TOP_PER_NODE: usize = 8;
+/// Weight of a bigram guess when the trigram context was never seen
+const BACKOFF: f64 = 0.4;
+/// Tokens appended after the first guessed one
+const MAX_EXTENSION: usize = 4;
+/// Share of the next-token weight a token needs to be appended
+const EXTEND_SHARE: f64 = 0.6;
+/// Start-of-command marker in n-gram contexts
+const START: &str = "^";
+/// History lines longer than this are not indexed
+const MAX_COMMAND_LEN: usize = 500;
+
+/// A predicted command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub text: String,
+    /// Relative likelihood in (0, 1]
+    pub score: f64,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: Vec<(char, u32)>,
+    /// Best commands below this node, by descending frecency
+    top: Vec<u32>,
+}
+
+/// Prefix trie and n-gram model over one history
+pub struct Predictor {
+    /// Unique commands by descending frecency
+    commands: Vec<(String, f64)>,
+    nodes: Vec<TrieNode>,
+    /// Context (`"prev"` or `"prev2 prev"`) -> next token -> weight
+    ngrams: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Predictor {
+    /// Index `history` (oldest first).
+    pub fn build(history: &[String], half_life: usize) -> Self {
+        let half_life = half_life.max(1) as f64;
+        let mut frecency: HashMap<&str, f64> = HashMap::new();
+        let mut ngrams: HashMap<String, HashMap<String, f64>> = HashMap::new();
+
+        for (age, entry) in history.iter().rev().enumerate() {
+            let command = entry.trim();
+            if command.is_empty() || command.len() > MAX_COMMAND_LEN {
+                continue;
+            }
+            let weight = 0.5_f64.powf(age as f64 / half_life);
+            *frecency.entry(command).or_default() += weight;
+
+            let (mut prev2, mut prev1) = ("", START);
+            for token in command.split_whitespace() {
+                if !prev2.is_empty() {
+                    *ngrams
+                        .entry(format!("{} {}", prev2, prev1))
+                        .or_default()
+                        .entry(token.to_string())
+                        .or_default() += weight;
+                }
+                *ngrams
+                    .entry(prev1.to_string())
+                    .or_default()
+                    .entry(token.to_string())
+                    .or_default() += weight;
+                (prev2, prev1) = (prev1, token);
+            }
+        }
+
+        let mut commands: Vec<(String, f64)> = frecency
+            .into_iter()
+            .map(|(command, score)| (command.to_string(), score))
+            .collect();
+        commands.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        // Commands are inserted best first, so each node keeps its first TOP_PER_NODE.
+        let mut nodes = vec![TrieNode::default()];
+        for (idx, (command, _)) in commands.iter().enumerate() {
+            let mut node = 0;
+            push_top(&mut nodes[node], idx);
+            for c in command.chars() {
+                node = match nodes[node].children.iter().find(|(ch, _)| *ch == c) {
+                    Some(&(_, child)) => child as usize,
+                    None => {
+                        nodes.push(TrieNode::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children.push((c, child as u32));
+                        child
+                    }
+                };
+                push_top(&mut nodes[node], idx);
+            }
+        }
+
+        Self {
+            commands,
+            nodes,
+            ngrams,
+        }
+    }
+
+    /// Number of distinct commands indexed
+    pub fn command_count(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Up to `limit` predictions for `buffer`: previously run commands
+    /// starting with it first, then n-gram extensions.
+    pub fn predict(&self, buffer: &str, limit: usize) -> Vec<Prediction> {
+        let typed = buffer.trim_start();
+        let mut predictions: Vec<Prediction> = Vec::new();
+
+        if let Some(node) = self.find(typed) {
+            let hits: Vec<&(String, f64)> = node
+                .top
+                .iter()
+                .map(|&idx| &self.commands[idx as usize])
+                .filter(|(command, _)| command != typed)
+                .collect();
+            let total: f64 = hits.iter().map(|(_, score)| score).sum();
+            predictions.extend(hits.into_iter().map(|(command, score)| Prediction {
+                text: command.clone(),
+                score: score / total,
+            }));
+        }
+
+        if !typed.trim().is_empty() {
+            for prediction in self.extend(typed) {
+                if !predictions.iter().any(|p| p.text == prediction.text) {
+                    predictions.push(prediction);
+                }
+            }
+        }
+
+        predictions.truncate(limit);
+        predictions
+    }
+
+    fn find(&self, prefix: &str) -> Option<&TrieNode> {
+        let mut node = &self.nodes[0];
+        for c in prefix.chars() {
+            let &(_, child) = node.children.iter().find(|(ch, _)| *ch == c)?;
+            node = &self.nodes[child as usize];
+        }
+        Some(node)
+    }
+
+    /// Complete the token under the cursor (or guess the next one) and keep
+    /// appending tokens while one clearly dominates.
+    fn extend(&self, typed: &str) -> Vec<Prediction> {
+        let split = if typed.ends_with(char::is_whitespace) {
+            typed.len()
+        } else {
+            typed
+                .char_indices()
+                .rev()
+                .find(|(_, c)| c.is_whitespace())
+                .map_or(0, |(pos, c)| pos + c.len_utf8())
+        };
+        let (done, partial) = typed.split_at(split);
+        let tokens: Vec<&str> = done.split_whitespace().collect();
+
+        let matching = |next: Vec<(&str, f64)>| -> Vec<(String, f64)> {
+            next.into_iter()
+                .filter(|(token, _)| token.len() > partial.len() && token.starts_with(partial))
+                .map(|(token, score)| (token.to_string(), score))
+                .collect()
+        };
+        let (prev2, prev1) = context(&tokens);
+        let mut options = matching(self.next_tokens(prev2, prev1, false));
+        if options.is_empty() && !prev2.is_empty() {
+            options = matching(self.next_tokens(prev2, prev1, true));
+        }
+
+        options
+            .into_iter()
+            .map(|(token, score)| {
+                let mut text = format!("{}{}", done, token);
+                let mut tokens: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+                tokens.push(token);
+                for _ in 0..MAX_EXTENSION {
+                    let refs: Vec<&str> = tokens.iter().map(String::as_str).collect();
+                    let (prev2, prev1) = context(&refs);
+                    let next = match self.next_tokens(prev2, prev1, false).first() {
+                        Some(&(next, share)) if share >= EXTEND_SHARE => next.to_string(),
+                        _ => break,
+                    };
+                    text.push(' ');
+                    text.push_str(&next);
+                    tokens.push(next);
+                }
+                Prediction { text, score }
+            })
+            .collect()
+    }
+
+    /// Next-token distribution after `prev2 prev1`, best first. Uses trigram
+    /// weights when that pair was seen (unless `backoff` is set), else bigram
+    /// weights scaled by `BACKOFF`.
+    fn next_tokens(&self, prev2: &str, prev1: &str, backoff: bool) -> Vec<(&str, f64)> {
+        let table = if prev2.is_empty() {
+            self.ngrams.get(prev1).map(|table| (table, 1.0))
+        } else {
+            let trigram = (!backoff)
+                .then(|| self.ngrams.get(&format!("{} {}", prev2, prev1)))
+                .flatten();
+            match trigram {
+                Some(table) => Some((table, 1.0)),
+                None => self.ngrams.get(prev1).map(|table| (table, BACKOFF)),
+            }
+        };
+        let Some((table, scale)) = table else {
+            return Vec::new();
+        };
+
+        let total: f64 = table.values().sum();
+        let mut next: Vec<(&str, f64)> = table
+            .iter()
+            .map(|(token, weight)| (token.as_str(), scale * weight / total))
+            .collect();
+        next.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        next
+    }
+}
+
+fn push_top(node: &mut TrieNode, idx: usize) {
+    if node.top.len() < TOP_PER_NODE {
+        node.top.push(idx as u32);
+    }
+}
+
+/// The two tokens before the next one (`""` when there is no second)
+fn context<'a>(tokens: &[&'a str]) -> (&'a str, &'a str) {
+    match tokens {
+        [] => ("", START),
+        [only] => (START, only),
+        [.., prev2, prev1] => (prev2, prev1),
+    }
+}
+
+struct Index {
+    /// Modification time and size of the history file when indexed
+    stamp: (Option<SystemTime>, u64),
+    predictor: Arc<Predictor>,
+}
+
+/// Predictors per history file, rebuilt when the file changes
+#[derive(Clone, Default)]
+pub struct PredictorStore {
+    indexes: Arc<Mutex<HashMap<PathBuf, Index>>>,
+}
+
+impl PredictorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Predictions for `buffer` from the history of `session_id`
+    pub fn predict(
+        &self,
+        session_id: &str,
+        buffer: &str,
+        config: &PredictorConfig,
+    ) -> Vec<Prediction> {
+        self.predictor(session_id, config)
+            .map(|predictor| predictor.predict(buffer, config.max_suggestions))
+            .unwrap_or_default()
+    }
+
+    fn predictor(&self, session_id: &str, config: &PredictorConfig) -> Option<Arc<Predictor>> {
+        let path = history::get_history_path(session_id).ok()?;
+        let metadata = std::fs::metadata(&path).ok()?;
+        let stamp = (metadata.modified().ok(), metadata.len());
+
+        let mut indexes = self.indexes.lock().ok()?;
+        if let Some(index) = indexes.get(&path).filter(|index| index.stamp == stamp) {
+            return Some(index.predictor.clone());
+        }
+
+        let entries = history::read_entries(session_id, config.history_size).ok()?;
+        let predictor = Arc::new(Predictor::build(&entries, config.half_life));
+        debug!(
+            "Indexed {} history commands from {}",
+            predictor.command_count(),
+            path.display()
+        );
+        indexes.insert(
+            path,
+            Index {
+                stamp,
+                predictor: predictor.clone(),
+            },
+        );
+        Some(predictor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Prediction, Predictor};
+
+    fn history(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    fn texts(predictions: &[Prediction]) -> Vec<&str> {
+        predictions.iter().map(|p| p.text.as_str()).collect()
+    }
+
+    #[test]
+    fn prefix_matches_rank_by_frecency() {
+        let predictor = Predictor::build(
+            &history(&[
+                "git stash",
+                "git stash",
+                "git stash",
+                "cargo test",
+                "git status",
+                "git status",
+            ]),
+            2,
+        );
+        // Three old runs lose against two recent ones with a short half-life.
+        let predictions = predictor.predict("git st", 5);
+        assert_eq!(texts(&predictions), vec!["git status", "git stash"]);
+        assert!(predictions[0].score > predictions[1].score);
+
+        let predictor = Predictor::build(
+            &history(&["git status", "git stash", "git stash", "git stash"]),
+            1000,
+        );
+        assert_eq!(predictor.predict("git st", 1)[0].text, "git stash");
+        // The buffer itself is not a prediction.
+        assert!(predictor.predict("git stash", 5).is_empty());
+    }
+
+    #[test]
+    fn ngrams_extend_unseen_commands() {
+        let predictor = Predictor::build(
+            &history(&[
+                "docker compose up -d",
+                "docker compose logs -f web",
+                "docker compose up -d",
+                "kubectl logs -f api",
+            ]),
+            500,
+        );
+        // No history line starts with this, but "compose" after "docker" does.
+        let predictions = predictor.predict("sudo docker comp", 3);
+        assert_eq!(predictions[0].text, "sudo docker compose up -d");
+
+        // A new token after a space; "logs -f" always continues with the same flag.
+        let predictions = predictor.predict("kubectl -n prod logs ", 3);
+        assert!(texts(&predictions).contains(&"kubectl -n prod logs -f"));
+    }
+
+    #[test]
+    fn empty_history_and_buffer() {
+        let predictor = Predictor::build(&[], 500);
+        assert!(predictor.predict("git", 3).is_empty());
+
+        let predictor = Predictor::build(&history(&["ls", "make", "make"]), 500);
+        assert_eq!(texts(&predictor.predict("", 1)), vec!["make"]);
+        assert!(predictor.predict("zzz", 3).is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+use serde_json::json;
+
+use super::completion::context_vars;
+use super::render;
+use crate::daemon::context::ContextData;
+use crate::daemon::shell_mode::ShellMode;
+
+pub const SYSTEM: &str = "ask/system.md";
+pub const USER: &str = "ask/user.md";
+
+/// System prompt; templates get `max_candidates`.
+pub fn system_prompt(max_candidates: usize) -> String {
+    render(SYSTEM, &json!({ "max_candidates": max_candidates }))
+}
+
+/// User prompt for a task description. Templates get `query` and the
+/// completion context variables (`context`, `sections`, `history`, ...).
+pub fn user_prompt(query: &str, context: &ContextData, shell_mode: ShellMode) -> String {
+    let mut vars = context_vars(context, shell_mode);
+    vars["query"] = json!(query);
+    render(USER, &vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_carry_query_limit_and_context() {
+        assert!(system_prompt(4).contains("Return 1-4 candidates"));
+        assert!(system_prompt(4).contains("{{name:default}}"));
+
+        let context = ContextData {
+            files: vec!["big.iso".to_string()],
+            ..Default::default()
+        };
+        let prompt = user_prompt("find large files", &context, ShellMode::ZshInline);
+        assert!(prompt.contains("big.iso"));
+        assert!(prompt.contains("## Task\nfind large files\n\n"));
+    }
+}
@@ -0,0 +1,105 @@
+use serde_json::{json, Value};
+
+use super::completion::capitalize_first;
+use super::render;
+use crate::daemon::context::{untrusted, ContextData};
+
+pub const SYSTEM: &str = "diagnosis/system.md";
+pub const USER: &str = "diagnosis/user.md";
+
+/// Bytes of stderr kept in the prompt
+const MAX_STDERR: usize = 2000;
+/// Bytes of each plugin's JSON kept in the prompt
+const MAX_PLUGIN_JSON: usize = 500;
+
+pub fn system_prompt() -> String {
+    render(SYSTEM, &json!({}))
+}
+
+/// User prompt for a failed command. Besides the gathered context, templates
+/// get `command`, `exit_code`, `stderr`, `error_record` (pretty JSON) and
+/// `plugin_json` (`[{id, name, json}]`, git excluded since it has its own
+/// section); `history` is cut to the last 10 commands.
+pub fn user_prompt(
+    command: &str,
+    exit_code: i32,
+    stderr: Option<&str>,
+    error_record: Option<&Value>,
+    context: &ContextData,
+) -> String {
+    let mut vars = serde_json::to_value(context).unwrap_or_else(|_| json!({}));
+    vars["command"] = json!(command);
+    vars["exit_code"] = json!(exit_code);
+    vars["stderr"] = json!(stderr.map(|s| truncate(&untrusted::clean_text(s), MAX_STDERR)));
+    vars["error_record"] = json!(error_record.map(|r| {
+        let record = untrusted::clean_value(r, MAX_STDERR);
+        serde_json::to_string_pretty(&record).unwrap_or_default()
+    }));
+    vars["history"] = json!(context.history.iter().take(10).collect::<Vec<_>>());
+
+    let mut plugins: Vec<_> = context
+        .plugins
+        .iter()
+        .filter(|(plugin_id, _)| plugin_id.as_str() != "git")
+        .collect();
+    plugins.sort_by(|a, b| a.0.cmp(b.0));
+    vars["plugin_json"] = plugins
+        .into_iter()
+        .map(|(plugin_id, data)| {
+            let pretty = serde_json::to_string_pretty(data).unwrap_or_default();
+            json!({
+                "id": plugin_id,
+                "name": capitalize_first(plugin_id),
+                "json": truncate(&pretty, MAX_PLUGIN_JSON),
+            })
+        })
+        .collect();
+
+    render(USER, &vars)
+}
+
+/// Cut `text` to at most `max` bytes on a character boundary.
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n... (truncated)", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_includes_failure_and_context() {
+        let mut context = ContextData {
+            cwd: "/work".into(),
+            history: (0..15).map(|i| format!("cmd{}", i)).collect(),
+            ..Default::default()
+        };
+        context
+            .plugins
+            .insert("node".to_string(), json!({"package_manager": "npm"}));
+        context
+            .plugins
+            .insert("git".to_string(), json!({"branch": "main"}));
+
+        let stderr = "é".repeat(1500);
+        let prompt = user_prompt("gti status", 127, Some(&stderr), None, &context);
+        assert!(
+            prompt.starts_with("## Failed Command\n```\ngti status\n```\n\n## Exit Code\n127\n\n")
+        );
+        assert!(prompt.contains("... (truncated)"));
+        assert!(prompt.contains("## Current Directory\n/work\n\n"));
+        assert!(prompt.contains("- cmd9\n"));
+        assert!(!prompt.contains("cmd10"));
+        assert!(prompt.contains("## Node Project Context\n{"));
+        assert!(!prompt.contains("Git Project Context"));
+        assert!(!prompt.contains("PowerShell Error Record"));
+        assert!(prompt.ends_with(r#"{"diagnosis": "...", "suggestion": "..."}"#));
+    }
+}
@@ -0,0 +1,51 @@
+use serde_json::json;
+
+use super::completion::context_vars;
+use super::render;
+use crate::daemon::context::ContextData;
+use crate::daemon::shell_mode::ShellMode;
+
+pub const SYSTEM: &str = "explain/system.md";
+pub const USER: &str = "explain/user.md";
+
+/// System prompt
+pub fn system_prompt() -> String {
+    render(SYSTEM, &json!({}))
+}
+
+/// User prompt for a command split into `parts`. Templates get `command`,
+/// `parts` (`index`, `text`) and the completion context variables
+/// (`context`, `sections`, `history`, ...).
+pub fn user_prompt(
+    command: &str,
+    parts: &[String],
+    context: &ContextData,
+    shell_mode: ShellMode,
+) -> String {
+    let mut vars = context_vars(context, shell_mode);
+    vars["command"] = json!(command);
+    vars["parts"] = parts
+        .iter()
+        .enumerate()
+        .map(|(index, text)| json!({ "index": index, "text": text }))
+        .collect();
+    render(USER, &vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_prompt_numbers_parts() {
+        let context = ContextData {
+            files: vec!["Cargo.toml".to_string()],
+            ..Default::default()
+        };
+        let parts = vec!["git".to_string(), "push".to_string(), "-f".to_string()];
+        let prompt = user_prompt("git push -f", &parts, &context, ShellMode::ZshInline);
+        assert!(prompt.contains("Cargo.toml"));
+        assert!(prompt.contains("## Command\ngit push -f\n\n## Parts\n0. git\n1. push\n2. -f\n"));
+        assert!(system_prompt().contains("\"side_effects\""));
+    }
+}
@@ -1 +1,152 @@
+//! Prompt templates.
+//!
+//! Every prompt is rendered from a named template (see [`template`] for the
+//! syntax). Built-in templates are compiled in; a file with the same name
+//! under [`template_dir`] replaces the built-in one, so users can adjust a
+//! single section or contract without rebuilding. A user template that fails
+//! to parse is logged and the built-in one is used instead.
+
+pub mod ask;
 pub mod completion;
+pub mod diagnosis;
+pub mod explain;
+pub mod template;
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tracing::warn;
+
+use crate::paths::AppPaths;
+
+/// Built-in templates, by name relative to the template directory
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        completion::SYSTEM,
+        include_str!("templates/completion/system.md"),
+    ),
+    (
+        completion::USER,
+        include_str!("templates/completion/user.md"),
+    ),
+    (
+        completion::USER_FIM,
+        include_str!("templates/completion/user-fim.md"),
+    ),
+    (
+        "completion/contracts/bash-popup.md",
+        include_str!("templates/completion/contracts/bash-popup.md"),
+    ),
+    (
+        "completion/contracts/zsh.md",
+        include_str!("templates/completion/contracts/zsh.md"),
+    ),
+    (
+        "completion/contracts/inline.md",
+        include_str!("templates/completion/contracts/inline.md"),
+    ),
+    (
+        completion::CONTRACT_FIM,
+        include_str!("templates/completion/contracts/fim.md"),
+    ),
+    (
+        completion::CONTRACT_STRUCTURED,
+        include_str!("templates/completion/contracts/structured.md"),
+    ),
+    (
+        "completion/sections/system.md",
+        include_str!("templates/completion/sections/system.md"),
+    ),
+    (
+        "completion/sections/history.md",
+        include_str!("templates/completion/sections/history.md"),
+    ),
+    (
+        "completion/sections/similar.md",
+        include_str!("templates/completion/sections/similar.md"),
+    ),
+    (
+        "completion/sections/files.md",
+        include_str!("templates/completion/sections/files.md"),
+    ),
+    (
+        "completion/sections/exit_code.md",
+        include_str!("templates/completion/sections/exit_code.md"),
+    ),
+    (
+        "completion/sections/plugins.md",
+        include_str!("templates/completion/sections/plugins.md"),
+    ),
+    (
+        diagnosis::SYSTEM,
+        include_str!("templates/diagnosis/system.md"),
+    ),
+    (diagnosis::USER, include_str!("templates/diagnosis/user.md")),
+    (ask::SYSTEM, include_str!("templates/ask/system.md")),
+    (ask::USER, include_str!("templates/ask/user.md")),
+    (explain::SYSTEM, include_str!("templates/explain/system.md")),
+    (explain::USER, include_str!("templates/explain/user.md")),
+];
+
+/// Directory holding user templates (`~/.nudge/config/prompts`)
+pub fn template_dir() -> PathBuf {
+    AppPaths::config_dir().join("prompts")
+}
+
+/// Names of all templates
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTIN.iter().map(|(name, _)| *name)
+}
+
+/// Compiled-in text of template `name`
+pub fn builtin(name: &str) -> &'static str {
+    BUILTIN
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, text)| *text)
+        .unwrap_or_default()
+}
+
+/// Templates the user has replaced in `dir`
+pub fn overridden(dir: &Path) -> Vec<&'static str> {
+    names().filter(|name| dir.join(name).is_file()).collect()
+}
+
+/// Text of template `name`: the user's copy if present, else the built-in one
+pub fn load(name: &str) -> Cow<'static, str> {
+    let path = template_dir().join(name);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Cow::Owned(text),
+        Err(_) => Cow::Borrowed(builtin(name)),
+    }
+}
+
+/// Render template `name` with `vars`, trimming trailing whitespace.
+pub fn render(name: &str, vars: &Value) -> String {
+    let text = load(name);
+    let rendered = template::render(&text, vars).unwrap_or_else(|e| {
+        warn!(
+            "Ignoring prompt template {}: {:#}",
+            template_dir().join(name).display(),
+            e
+        );
+        template::render(builtin(name), vars).unwrap_or_default()
+    });
+    rendered.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builtin, names, template};
+    use serde_json::json;
+
+    #[test]
+    fn builtin_templates_parse() {
+        for name in names() {
+            assert!(!builtin(name).is_empty(), "{} is empty", name);
+            template::render(builtin(name), &json!({}))
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Minimal template language for prompt files.
+//!
+//! - `{{path.to.value}}` inserts a variable. Strings are inserted as-is,
+//!   arrays as a comma-separated list and objects as compact JSON.
+//! - `{{#if path}} ... {{else}} ... {{/if}}` renders a branch depending on
+//!   whether the value is present and non-empty (`false`, `null`, `""`, `[]`
+//!   and `{}` are false; every number is true).
+//! - `{{#each path}} ... {{/each}}` repeats its body for every array item or
+//!   object entry. Inside, `this` is the current item, `@index` its position
+//!   and `@key` its key; other names are looked up on the item first.
+//! - `\{{` produces a literal `{{`.
+//!
+//! A newline directly after a block tag is dropped, so block tags can sit on
+//! their own lines. Tags that are not a known variable (such as the
+//! `{{name:default}}` placeholder markers in response contracts) are kept
+//! verbatim.
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    /// Trimmed tag content and the tag as written
+    Tag(String, String),
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var {
+        path: String,
+        raw: String,
+    },
+    If {
+        path: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        path: String,
+        body: Vec<Node>,
+    },
+}
+
+/// Current `{{#each}}` item
+struct Scope {
+    item: Value,
+    index: usize,
+    key: Option<String>,
+}
+
+/// Render `template` with `vars`; fails on unbalanced block tags.
+pub fn render(template: &str, vars: &Value) -> Result<String> {
+    let mut tokens = tokenize(template).into_iter();
+    let (nodes, _) = parse_until(&mut tokens, None)?;
+    let mut out = String::with_capacity(template.len());
+    render_nodes(&nodes, vars, &mut Vec::new(), &mut out);
+    Ok(out)
+}
+
+fn tokenize(template: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            text.push_str(&rest[..start - 1]);
+            text.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+
+        let end = start + 2 + len + 2;
+        let inner = rest[start + 2..end - 2].trim().to_string();
+        let is_block = inner.starts_with('#') || inner.starts_with('/') || inner == "else";
+        tokens.push(Token::Tag(inner, rest[start..end].to_string()));
+        rest = &rest[end..];
+        if is_block {
+            rest = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+                .unwrap_or(rest);
+        }
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Parse until `{{/end}}`; the flag says parsing stopped at `{{else}}`.
+fn parse_until(
+    tokens: &mut std::vec::IntoIter<Token>,
+    end: Option<&str>,
+) -> Result<(Vec<Node>, bool)> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (tag, raw) = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Token::Tag(tag, raw) => (tag, raw),
+        };
+
+        if tag == "else" {
+            if end == Some("if") {
+                return Ok((nodes, true));
+            }
+            bail!("{{{{else}}}} outside of {{{{#if}}}}");
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            if Some(name.trim()) == end {
+                return Ok((nodes, false));
+            }
+            bail!("unexpected {}", raw);
+        }
+        if let Some(path) = tag.strip_prefix("#if ") {
+            let (then, has_else) = parse_until(tokens, Some("if"))?;
+            let otherwise = if has_else {
+                let (otherwise, again) = parse_until(tokens, Some("if"))?;
+                if again {
+                    bail!("more than one {{{{else}}}} in {}", raw);
+                }
+                otherwise
+            } else {
+                Vec::new()
+            };
+            nodes.push(Node::If {
+                path: path.trim().to_string(),
+                then,
+                otherwise,
+            });
+        } else if let Some(path) = tag.strip_prefix("#each ") {
+            let (body, _) = parse_until(tokens, Some("each"))?;
+            nodes.push(Node::Each {
+                path: path.trim().to_string(),
+                body,
+            });
+        } else if tag.starts_with('#') {
+            bail!("unknown block {}", raw);
+        } else {
+            nodes.push(Node::Var { path: tag, raw });
+        }
+    }
+
+    match end {
+        Some(end) => bail!("missing {{{{/{}}}}}", end),
+        None => Ok((nodes, false)),
+    }
+}
+
+fn render_nodes(nodes: &[Node], root: &Value, scopes: &mut Vec<Scope>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { path, raw } => match lookup(path, root, scopes) {
+                Some(value) => out.push_str(&display(&value)),
+                None => out.push_str(raw),
+            },
+            Node::If {
+                path,
+                then,
+                otherwise,
+            } => {
+                let branch = if lookup(path, root, scopes).is_some_and(|v| truthy(&v)) {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, root, scopes, out);
+            }
+            Node::Each { path, body } => {
+                let items: Vec<(Option<String>, Value)> = match lookup(path, root, scopes) {
+                    Some(Value::Array(items)) => items.into_iter().map(|v| (None, v)).collect(),
+                    Some(Value::Object(map)) => {
+                        map.into_iter().map(|(k, v)| (Some(k), v)).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                for (index, (key, item)) in items.into_iter().enumerate() {
+                    scopes.push(Scope { item, index, key });
+                    render_nodes(body, root, scopes, out);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
+
+fn lookup(path: &str, root: &Value, scopes: &[Scope]) -> Option<Value> {
+    let valid = |segment: &str| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '@'))
+    };
+    if !path.split('.').all(valid) {
+        return None;
+    }
+
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let base = match first {
+        "this" => scopes
+            .last()
+            .map_or_else(|| root.clone(), |s| s.item.clone()),
+        "@index" => return scopes.last().map(|s| Value::from(s.index)),
+        "@key" => return scopes.last()?.key.clone().map(Value::String),
+        name => scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.item.get(name))
+            .or_else(|| root.get(name))?
+            .clone(),
+    };
+    segments.try_fold(base, |value, segment| match &value {
+        Value::Array(items) => segment
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| items.get(i).cloned()),
+        _ => value.get(segment).cloned(),
+    })
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(_) => true,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use serde_json::json;
+
+    #[test]
+    fn substitutes_nested_paths_and_lists() {
+        let vars = json!({
+            "shell_mode": "zsh-inline",
+            "files": ["Cargo.toml", "src"],
+            "plugins": {"git": {"branch": "main"}},
+            "count": 0
+        });
+        assert_eq!(
+            render(
+                "{{shell_mode}} {{ plugins.git.branch }}: {{files}} ({{count}}) {{files.1}}",
+                &vars
+            )
+            .unwrap(),
+            "zsh-inline main: Cargo.toml, src (0) src"
+        );
+    }
+
+    #[test]
+    fn unknown_tags_and_escapes_stay_literal() {
+        let vars = json!({"name": "x"});
+        assert_eq!(
+            render("{{missing}} {{port:8080}} \\{{name}}", &vars).unwrap(),
+            "{{missing}} {{port:8080}} {{name}}"
+        );
+    }
+
+    #[test]
+    fn blocks_drop_their_own_line_breaks() {
+        let template =
+            "{{#if history}}\n## Recent\n{{#each history}}\n{{@index}}. {{this}}\n{{/each}}\n{{else}}\nnone\n{{/if}}\n";
+        assert_eq!(
+            render(template, &json!({"history": ["ls", "pwd"]})).unwrap(),
+            "## Recent\n0. ls\n1. pwd\n"
+        );
+        assert_eq!(render(template, &json!({"history": []})).unwrap(), "none\n");
+    }
+
+    #[test]
+    fn each_item_fields_shadow_outer_names() {
+        let vars = json!({
+            "name": "outer",
+            "sections": [{"name": "Git", "fields": [{"label": "Branch", "value": "main"}]}],
+            "env": {"A": "1"}
+        });
+        assert_eq!(
+            render(
+                "{{#each sections}}{{name}}:{{#each fields}} {{label}}={{value}}{{/each}}{{/each}} {{name}}{{#each env}} {{@key}}={{this}}{{/each}}",
+                &vars
+            )
+            .unwrap(),
+            "Git: Branch=main outer A=1"
+        );
+    }
+
+    #[test]
+    fn unbalanced_blocks_are_errors() {
+        let vars = json!({});
+        assert!(render("{{#if x}}open", &vars).is_err());
+        assert!(render("{{/each}}", &vars).is_err());
+        assert!(render("{{else}}", &vars).is_err());
+        assert!(render("{{#unless x}}{{/unless}}", &vars).is_err());
+    }
+}
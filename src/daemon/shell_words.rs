@@ -0,0 +1,341 @@
+//! Shell command line splitting.
+//!
+//! Splits a command line into words and control operators the way POSIX
+//! shells read it, keeping each token's byte range in the original line.
+//! Quotes and backslash escapes stay part of the word text; an unterminated
+//! quote runs to the end of the line. Expansions are not performed.
+//!
+//! [`command_names`] finds the words the shell will run as commands and
+//! [`unbalanced`] reports unterminated quotes and unmatched brackets.
+
+use std::ops::Range;
+
+/// Kind of a command line token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordKind {
+    /// Command name, argument or assignment
+    Word,
+    /// Control or redirection operator (`|`, `&&`, `;`, `>`, `2>`, ...)
+    Operator,
+}
+
+/// A token of a command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    /// Token as written, including quotes
+    pub text: String,
+    /// Byte range in the command line
+    pub range: Range<usize>,
+    pub kind: WordKind,
+}
+
+/// Operators, longest first
+const OPERATORS: &[&str] = &[
+    "&&", "||", ";;", ">>", "<<", "&>", ">&", "|&", "|", "&", ";", "<", ">", "(", ")",
+];
+
+/// Split `line` into words and operators.
+pub fn split(line: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let rest = &line[pos..];
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+        if c == '#' {
+            // Comment to the end of the line
+            break;
+        }
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            words.push(Word {
+                text: op.to_string(),
+                range: pos..pos + op.len(),
+                kind: WordKind::Operator,
+            });
+            pos += op.len();
+            continue;
+        }
+
+        let end = pos + word_len(rest);
+        // A file descriptor number directly before a redirection belongs to it (`2>`)
+        let redirect = &line[end..];
+        let is_fd = line[pos..end].bytes().all(|b| b.is_ascii_digit())
+            && (redirect.starts_with('>') || redirect.starts_with('<'));
+        if is_fd {
+            let op_len = if redirect.starts_with(">>") || redirect.starts_with(">&") {
+                2
+            } else {
+                1
+            };
+            words.push(Word {
+                text: line[pos..end + op_len].to_string(),
+                range: pos..end + op_len,
+                kind: WordKind::Operator,
+            });
+            pos = end + op_len;
+            continue;
+        }
+
+        words.push(Word {
+            text: line[pos..end].to_string(),
+            range: pos..end,
+            kind: WordKind::Word,
+        });
+        pos = end;
+    }
+    words
+}
+
+/// Operators after which a new command starts
+const COMMAND_SEPARATORS: &[&str] = &["&&", "||", ";;", "|&", "|", "&", ";", "("];
+/// Commands that run the word after them as a command
+const PREFIX_COMMANDS: &[&str] = &[
+    "sudo", "doas", "env", "nohup", "time", "command", "exec", "nice", "builtin", "noglob",
+];
+/// Reserved words that are not commands themselves
+const KEYWORDS: &[&str] = &[
+    "!", "{", "}", "if", "then", "else", "elif", "fi", "while", "until", "do", "done", "esac",
+];
+/// Reserved words that start constructs too complex to follow
+const COMPOUND_KEYWORDS: &[&str] = &["for", "case", "select", "function", "coproc"];
+
+/// Words of `line` that the shell runs as commands: the first word of each
+/// pipeline stage or list item, after variable assignments and prefix
+/// commands such as `sudo` (which are returned too). Words with quotes,
+/// expansions or globs are skipped, and so is the rest of a command whose
+/// prefix command is given options, since an option may take a value.
+pub fn command_names(line: &str) -> Vec<Word> {
+    let mut names = Vec::new();
+    let mut expect_command = true;
+    let mut after_prefix = false;
+    let mut redirect_target = false;
+
+    for word in split(line) {
+        if word.kind == WordKind::Operator {
+            if COMMAND_SEPARATORS.contains(&word.text.as_str()) {
+                expect_command = true;
+                after_prefix = false;
+            } else if word.text != ")" {
+                redirect_target = true;
+            }
+            continue;
+        }
+        if std::mem::take(&mut redirect_target) || !expect_command {
+            continue;
+        }
+
+        let text = word.text.as_str();
+        if is_assignment(text) || KEYWORDS.contains(&text) {
+            continue;
+        }
+        if COMPOUND_KEYWORDS.contains(&text)
+            || (after_prefix && text.starts_with('-'))
+            || text.contains(['\'', '"', '$', '`', '\\', '*', '?'])
+        {
+            expect_command = false;
+            continue;
+        }
+
+        after_prefix = PREFIX_COMMANDS.contains(&text);
+        expect_command = after_prefix;
+        names.push(word);
+    }
+    names
+}
+
+/// Whether `word` is a variable assignment (`NAME=value`)
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// First unterminated quote or unmatched bracket in `line`, described for
+/// the user; `None` when quotes and `()`, `{}`, `[]` are balanced outside
+/// of quotes. `case` patterns use unmatched `)`, so brackets are not checked
+/// in lines containing `case`.
+pub fn unbalanced(line: &str) -> Option<String> {
+    let check_brackets = !split(line).iter().any(|word| word.text == "case");
+    let mut stack: Vec<char> = Vec::new();
+    let mut quote: Option<char> = None;
+    // `$'...'` strings allow backslash escapes
+    let mut ansi_quote = false;
+    let mut backquote = false;
+    let mut prev: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('\'') if c == '\\' && ansi_quote => {
+                chars.next();
+            }
+            Some('\'') if c == '\'' => quote = None,
+            Some('"') if c == '\\' => {
+                chars.next();
+            }
+            Some('"') if c == '"' => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\\' => {
+                    chars.next();
+                }
+                '\'' | '"' => {
+                    ansi_quote = c == '\'' && prev == Some('$');
+                    quote = Some(c);
+                }
+                '`' => backquote = !backquote,
+                '#' if prev.is_none_or(char::is_whitespace) => break,
+                '(' | '{' | '[' if check_brackets => stack.push(c),
+                ')' | '}' | ']' if check_brackets => {
+                    let open = match c {
+                        ')' => '(',
+                        '}' => '{',
+                        _ => '[',
+                    };
+                    if stack.pop() != Some(open) {
+                        return Some(format!("unmatched '{}'", c));
+                    }
+                }
+                _ => {}
+            },
+        }
+        prev = Some(c);
+    }
+
+    match quote {
+        Some('\'') => Some("unterminated single quote".to_string()),
+        Some(_) => Some("unterminated double quote".to_string()),
+        None if backquote => Some("unterminated backquote".to_string()),
+        None => stack.last().map(|open| format!("unclosed '{}'", open)),
+    }
+}
+
+/// Byte length of the word at the start of `text`
+fn word_len(text: &str) -> usize {
+    let mut quote: Option<char> = None;
+    let mut chars = text.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match quote {
+            Some('\'') if c == '\'' => quote = None,
+            Some('\'') => {}
+            Some(_) if c == '"' => quote = None,
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '\\' => {
+                    chars.next();
+                }
+                c if c.is_whitespace() || "|&;<>()".contains(c) => return idx,
+                _ => {}
+            },
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_names, split, unbalanced, WordKind};
+
+    fn texts(line: &str) -> Vec<String> {
+        split(line).into_iter().map(|w| w.text).collect()
+    }
+
+    #[test]
+    fn splits_words_quotes_and_operators() {
+        assert_eq!(
+            texts(r#"git commit -m "fix: a | b" && echo 'done'>log 2>&1"#),
+            vec![
+                "git",
+                "commit",
+                "-m",
+                "\"fix: a | b\"",
+                "&&",
+                "echo",
+                "'done'",
+                ">",
+                "log",
+                "2>&",
+                "1"
+            ]
+        );
+        assert_eq!(
+            texts(r"ls my\ dir|wc -l # count"),
+            vec!["ls", r"my\ dir", "|", "wc", "-l"]
+        );
+    }
+
+    #[test]
+    fn keeps_byte_ranges_and_kinds() {
+        let line = "écho  \"x y\" ; ls";
+        let words = split(line);
+        for word in &words {
+            assert_eq!(&line[word.range.clone()], word.text);
+        }
+        assert_eq!(words[1].range, 7..12);
+        assert_eq!(words[2].kind, WordKind::Operator);
+        assert_eq!(words[3].kind, WordKind::Word);
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_end() {
+        assert_eq!(texts("echo \"a b | c"), vec!["echo", "\"a b | c"]);
+    }
+
+    #[test]
+    fn finds_command_names() {
+        let names = |line: &str| -> Vec<String> {
+            command_names(line).into_iter().map(|w| w.text).collect()
+        };
+        assert_eq!(
+            names("FOO=1 sudo make install && ls -la | grep x > out; (cd src)"),
+            vec!["sudo", "make", "ls", "grep", "cd"]
+        );
+        assert_eq!(names("sudo -u root whoami"), vec!["sudo"]);
+        assert_eq!(names("if test -f x; then echo y; fi"), vec!["test", "echo"]);
+        assert_eq!(
+            names("$EDITOR file; \"my tool\" x; ./run.sh"),
+            vec!["./run.sh"]
+        );
+        assert_eq!(names("for f in *; do rm $f; done"), vec!["rm"]);
+    }
+
+    #[test]
+    fn reports_unbalanced_quotes_and_brackets() {
+        assert_eq!(
+            unbalanced(r#"echo "it's (fine)" 'a"b' $(date) ${HOME} [x]"#),
+            None
+        );
+        assert_eq!(unbalanced(r"echo \( $'it\'s' # (comment"), None);
+        assert_eq!(unbalanced("case $x in a) ls;; esac"), None);
+        assert_eq!(
+            unbalanced("echo \"unterminated").as_deref(),
+            Some("unterminated double quote")
+        );
+        assert_eq!(
+            unbalanced("echo 'a").as_deref(),
+            Some("unterminated single quote")
+        );
+        assert_eq!(
+            unbalanced("echo `date").as_deref(),
+            Some("unterminated backquote")
+        );
+        assert_eq!(unbalanced("echo $(date").as_deref(), Some("unclosed '('"));
+        assert_eq!(unbalanced("echo ${HOME)").as_deref(), Some("unmatched ')'"));
+        assert_eq!(unbalanced("ls )").as_deref(), Some("unmatched ')'"));
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 
+use crate::config::PrivacyConfig;
 use crate::daemon::sanitizer;
 use crate::protocol::CompletionRequest;
 use crate::protocol::CompletionResponse;
@@ -19,31 +20,40 @@ impl SuggestionKey {
         time_bucket: Option<u64>,
         prefix_bytes: usize,
     ) -> String {
-        Self::build_with_patterns(
+        Self::build_with_privacy(
             req,
             git_root,
             git_state,
             shell_mode,
             time_bucket,
             prefix_bytes,
-            &[],
+            &PrivacyConfig::default(),
         )
     }
 
-    pub fn build_with_patterns(
+    pub fn build_with_privacy(
         req: &CompletionRequest,
         git_root: Option<&PathBuf>,
         git_state: Option<&str>,
         shell_mode: &str,
         time_bucket: Option<u64>,
         prefix_bytes: usize,
-        custom_patterns: &[String],
+        privacy: &PrivacyConfig,
     ) -> String {
-        let cursor = req.cursor_pos.min(req.buffer.len());
-        let prefix_raw = &req.buffer[..cursor];
-
-        let (sanitized_prefix, _) = sanitizer::sanitize_string(prefix_raw, custom_patterns);
-        let truncated = truncate_utf8(&sanitized_prefix, prefix_bytes);
+        let mut cursor = req.cursor_pos.min(req.buffer.len());
+        while !req.buffer.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let (prefix_raw, suffix_raw) = req.buffer.split_at(cursor);
+
+        let (sanitized_prefix, _) = sanitizer::sanitize_string(prefix_raw, privacy);
+        let mut truncated = truncate_utf8(&sanitized_prefix, prefix_bytes);
+        // Mid-line completions depend on the text after the cursor as well.
+        if !suffix_raw.trim().is_empty() {
+            let (sanitized_suffix, _) = sanitizer::sanitize_string(suffix_raw, privacy);
+            truncated.push('\0');
+            truncated.push_str(&sanitized_suffix);
+        }
         let prefix_hash = hash_hex_16(truncated.as_bytes());
 
         let path_for_hash = git_root.unwrap_or(&req.cwd);
@@ -214,6 +224,21 @@ impl SuggestionCache {
         self.order.push_back(key);
     }
 
+    /// Insert a short-lived entry whose replacement is already being computed,
+    /// so hits on it do not start another refresh.
+    pub fn insert_pending(
+        &mut self,
+        key: String,
+        response: CompletionResponse,
+        now_ms: u64,
+        ttl_ms: u64,
+    ) {
+        self.insert(key.clone(), response, now_ms, ttl_ms, true);
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.refreshing = true;
+        }
+    }
+
     fn touch(&mut self, key: &str) {
         if let Some(pos) = self.order.iter().position(|k| k == key) {
             self.order.remove(pos);
@@ -272,6 +297,23 @@ mod tests {
         assert_ne!(key_manual, key_auto);
     }
 
+    #[test]
+    fn test_text_after_cursor_changes_key() {
+        let build = |buffer: &str| {
+            let req = CompletionRequest::new(
+                "zsh-1".into(),
+                buffer.into(),
+                4,
+                PathBuf::from("/tmp"),
+                None,
+            );
+            SuggestionKey::build(&req, None, None, "zsh-inline", None, 80)
+        };
+
+        assert_ne!(build("git  main"), build("git  origin"));
+        assert_eq!(build("git "), build("git    "));
+    }
+
     #[test]
     fn test_cache_ttl_expiry() {
         let mut cache = SuggestionCache::new(2, 0.8);
@@ -290,4 +332,15 @@ mod tests {
         assert!(hit.is_stale);
         assert!(hit.should_refresh);
     }
+
+    #[test]
+    fn test_pending_entry_does_not_refresh() {
+        let mut cache = SuggestionCache::new(2, 0.8);
+        let response = CompletionResponse::success("req".into(), vec![], 0);
+        cache.insert_pending("k".into(), response, 1000, 10);
+        let hit = cache.get_with_state("k", 1009).unwrap();
+        assert!(hit.is_stale);
+        assert!(!hit.should_refresh);
+        assert!(hit.negative);
+    }
 }
@@ -0,0 +1,251 @@
+//! Prompt token counting.
+//!
+//! No tokenizer vocabulary ships with nudge, so counts are estimated:
+//!
+//! - [`TextClassCounter`] splits text the way BPE pre-tokenizers do (words
+//!   with their leading space or punctuation mark, digit groups, punctuation
+//!   runs, CJK characters) and charges each piece what common vocabularies
+//!   spend on it. Paths, JSON and CJK text come out far closer than a word
+//!   count would.
+//! - [`CharRatioCounter`] divides the character count by a fixed ratio.
+//!
+//! With `context.tokenizer.calibrate`, every response that reports its
+//! prompt token count updates a per-model correction factor for the
+//! text-class estimate, so counts converge on the model's real tokenizer.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+use crate::config::{TokenizerConfig, TokenizerMethod};
+use crate::daemon::llm::provider::ChatRequest;
+
+/// Tokens a chat request spends on message framing
+const MESSAGE_OVERHEAD: usize = 8;
+/// Bounds of the learned correction factor
+const FACTOR_RANGE: (f64, f64) = (0.5, 2.0);
+/// Weight of a new observation in the factor's moving average
+const FACTOR_SMOOTHING: f64 = 0.2;
+
+lazy_static! {
+    /// Learned correction factor per model
+    static ref CALIBRATION: Mutex<HashMap<String, f64>> = Mutex::new(HashMap::new());
+}
+
+/// Counts the tokens of a piece of text
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Estimate from character classes, following how BPE vocabularies split text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextClassCounter;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Letter,
+    Digit,
+    Cjk,
+    OtherLetter,
+    Space,
+    Newline,
+    Punct,
+}
+
+fn class(c: char) -> Class {
+    match c {
+        '\n' | '\r' => Class::Newline,
+        c if c.is_whitespace() => Class::Space,
+        c if c.is_ascii_alphabetic() || c == '_' => Class::Letter,
+        c if c.is_ascii_digit() => Class::Digit,
+        c if is_cjk(c) => Class::Cjk,
+        c if c.is_alphanumeric() => Class::OtherLetter,
+        _ => Class::Punct,
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // Hiragana, Katakana
+        | 0x3400..=0x4DBF    // CJK Extension A
+        | 0x4E00..=0x9FFF    // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF    // Hangul syllables
+        | 0xF900..=0xFAFF    // CJK Compatibility Ideographs
+        | 0xFF00..=0xFFEF    // Fullwidth forms
+        | 0x20000..=0x2FFFF) // CJK Extensions B-F
+}
+
+impl TokenCounter for TextClassCounter {
+    fn count(&self, text: &str) -> usize {
+        let chars: Vec<char> = text.chars().collect();
+        let mut total = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let kind = class(chars[i]);
+            let start = i;
+            while i < chars.len() && class(chars[i]) == kind {
+                i += 1;
+            }
+            let len = i - start;
+            let next = chars.get(i).copied().map(class);
+            total += match kind {
+                // Long words split into several pieces
+                Class::Letter => 1 + len.saturating_sub(6).div_ceil(4),
+                // Numbers are split into groups of up to three digits
+                Class::Digit => len.div_ceil(3),
+                Class::Cjk => len,
+                Class::OtherLetter => len.div_ceil(2),
+                // A single space or punctuation mark merges into the word after it
+                Class::Space | Class::Punct
+                    if len == 1 && matches!(next, Some(Class::Letter | Class::Digit)) =>
+                {
+                    0
+                }
+                Class::Space => 1,
+                Class::Newline => 1,
+                Class::Punct => len.div_ceil(2),
+            };
+        }
+        total
+    }
+}
+
+/// Fixed number of characters per token
+#[derive(Debug, Clone, Copy)]
+pub struct CharRatioCounter(pub f32);
+
+impl TokenCounter for CharRatioCounter {
+    fn count(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.0).ceil() as usize
+    }
+}
+
+/// Another counter scaled by a correction factor
+struct Scaled<C> {
+    inner: C,
+    factor: f64,
+}
+
+impl<C: TokenCounter> TokenCounter for Scaled<C> {
+    fn count(&self, text: &str) -> usize {
+        (self.inner.count(text) as f64 * self.factor).round() as usize
+    }
+}
+
+/// Counter configured by `context.tokenizer` for prompts sent to `model`
+pub fn counter(config: &TokenizerConfig, model: &str) -> Box<dyn TokenCounter> {
+    match config.method {
+        TokenizerMethod::Chars => Box::new(CharRatioCounter(config.chars_per_token)),
+        TokenizerMethod::Estimate => match calibration(model).filter(|_| config.calibrate) {
+            Some(factor) => Box::new(Scaled {
+                inner: TextClassCounter,
+                factor,
+            }),
+            None => Box::new(TextClassCounter),
+        },
+    }
+}
+
+/// Tokens of everything a chat request sends: both prompts, the response
+/// schema and message framing
+pub fn request_tokens(counter: &dyn TokenCounter, request: &ChatRequest) -> usize {
+    let schema = request
+        .schema
+        .as_ref()
+        .map_or(0, |schema| counter.count(&schema.schema.to_string()));
+    counter.count(&request.system) + counter.count(&request.user) + schema + MESSAGE_OVERHEAD
+}
+
+/// Learned correction factor of the text-class estimate for `model`
+pub fn calibration(model: &str) -> Option<f64> {
+    CALIBRATION.lock().ok()?.get(model).copied()
+}
+
+/// Update the correction factor of `model` from a request whose prompt the
+/// provider reported as `reported` tokens.
+pub fn observe(model: &str, request: &ChatRequest, reported: u64) {
+    let estimated = request_tokens(&TextClassCounter, request);
+    if reported == 0 || estimated == 0 {
+        return;
+    }
+    let observed = (reported as f64 / estimated as f64).clamp(FACTOR_RANGE.0, FACTOR_RANGE.1);
+    if let Ok(mut factors) = CALIBRATION.lock() {
+        factors
+            .entry(model.to_string())
+            .and_modify(|factor| {
+                *factor = *factor * (1.0 - FACTOR_SMOOTHING) + observed * FACTOR_SMOOTHING
+            })
+            .or_insert(observed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calibration, counter, observe, CharRatioCounter, TextClassCounter, TokenCounter};
+    use crate::config::{TokenizerConfig, TokenizerMethod};
+    use crate::daemon::llm::provider::ChatRequest;
+
+    fn count(text: &str) -> usize {
+        TextClassCounter.count(text)
+    }
+
+    #[test]
+    fn counts_words_paths_json_and_cjk() {
+        assert_eq!(count("git status"), 2);
+        assert_eq!(count("git commit -m \"fix typo\""), 8);
+        // Each path segment merges with its slash.
+        assert_eq!(count("/usr/local/bin/python3"), 5);
+        // A word count (× 1.3) would give 3 for both of these.
+        assert_eq!(count(r#"{"branch": "main", "ahead": 12}"#), 11);
+        assert_eq!(count("查看当前目录的文件"), 9);
+        assert_eq!(count("1234567"), 3);
+        assert_eq!(count(""), 0);
+    }
+
+    #[test]
+    fn char_ratio_and_configured_counter() {
+        assert_eq!(CharRatioCounter(4.0).count("abcdefghi"), 3);
+
+        let config = TokenizerConfig {
+            method: TokenizerMethod::Chars,
+            chars_per_token: 2.0,
+            calibrate: true,
+        };
+        assert_eq!(counter(&config, "any").count("abcd"), 2);
+    }
+
+    #[test]
+    fn calibration_scales_estimates_per_model() {
+        let request = ChatRequest {
+            system: "You complete shell commands.".to_string(),
+            user: "git st".to_string(),
+            max_tokens: 10,
+            temperature: 0.0,
+            schema: None,
+        };
+        let model = "calibration-test-model";
+        let config = TokenizerConfig::default();
+        let text = "kubectl get pods --all-namespaces";
+        let uncalibrated = counter(&config, model).count(text);
+        assert!(calibration(model).is_none());
+
+        // The provider reports twice the estimate.
+        let estimated = super::request_tokens(&TextClassCounter, &request) as u64;
+        observe(model, &request, estimated * 2);
+        assert_eq!(calibration(model), Some(2.0));
+        assert_eq!(counter(&config, model).count(text), uncalibrated * 2);
+
+        // Outliers are clamped and later observations are smoothed.
+        observe(model, &request, estimated * 100);
+        assert_eq!(calibration(model), Some(2.0));
+        observe(model, &request, estimated);
+        assert!((calibration(model).unwrap() - 1.8).abs() < 1e-9);
+
+        let config = TokenizerConfig {
+            calibrate: false,
+            ..TokenizerConfig::default()
+        };
+        assert_eq!(counter(&config, model).count(text), uncalibrated);
+    }
+}
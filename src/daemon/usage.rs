@@ -0,0 +1,596 @@
+//! Token usage accounting and budget enforcement.
+//!
+//! Every answered LLM request adds its token counts to a per-day ledger at
+//! `AppPaths::data_dir()/usage.json`, broken down by model and by request
+//! kind. Providers that do not report usage (or streams dropped before the
+//! final usage frame) are counted with the prompt token estimate of
+//! [`super::tokens`].
+//!
+//! Each process keeps the ledger in memory and a background thread merges
+//! new records into the file under an advisory lock (`usage.json.lock`), so
+//! the daemon and FFI contexts running in other processes add to the same
+//! totals without touching the file on every request.
+//!
+//! A ledger that cannot be parsed is moved aside to `usage.json.corrupt`.
+//! Until the budget period it belongs to is over, the budget counts as
+//! exhausted, so `on_exceeded` applies instead of starting again from zero.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::llm::provider::TokenUsage;
+use crate::config::{BudgetConfig, RequestKind, UsageConfig};
+use crate::paths::AppPaths;
+
+const USAGE_FILE: &str = "usage.json";
+
+/// How long new records wait before being written, so bursts share one write
+const FLUSH_DELAY: Duration = Duration::from_secs(1);
+
+/// How stale the in-memory totals may get before budget checks trigger a
+/// re-read that picks up other processes' usage
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Days kept in the ledger (covers the previous and current month)
+const RETENTION_DAYS: i64 = 62;
+
+/// Request and token counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageCounters {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl UsageCounters {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    fn add(&mut self, other: &UsageCounters) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Totals of one day
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayUsage {
+    pub by_model: BTreeMap<String, UsageCounters>,
+    pub by_kind: BTreeMap<String, UsageCounters>,
+}
+
+impl DayUsage {
+    pub fn total(&self) -> UsageCounters {
+        let mut total = UsageCounters::default();
+        for counters in self.by_kind.values() {
+            total.add(counters);
+        }
+        total
+    }
+}
+
+/// Per-day usage history, keyed by local date
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageLedger {
+    pub days: BTreeMap<NaiveDate, DayUsage>,
+}
+
+impl UsageLedger {
+    /// Load the ledger; a missing file starts a new one. A file that does
+    /// not parse is moved aside to [`corrupt_path`] and reported as an error.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Cannot read {}", path.display()));
+            }
+        };
+        serde_json::from_str(&content).or_else(|e| {
+            let corrupt = corrupt_path(path);
+            fs::rename(path, &corrupt)
+                .with_context(|| format!("Failed to move {} aside", path.display()))?;
+            // The rename keeps the old timestamp; the budget period starts now.
+            if let Ok(file) = File::options().append(true).open(&corrupt) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            anyhow::bail!(
+                "Unreadable usage ledger {} ({}), moved to {}",
+                path.display(),
+                e,
+                corrupt.display()
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let tmp = path.with_extension(format!(
+            "json.{}.{}.tmp",
+            std::process::id(),
+            SAVES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Add every counter of `other`.
+    fn merge(&mut self, other: &UsageLedger) {
+        for (date, usage) in &other.days {
+            let day = self.days.entry(*date).or_default();
+            for (model, counters) in &usage.by_model {
+                day.by_model.entry(model.clone()).or_default().add(counters);
+            }
+            for (kind, counters) in &usage.by_kind {
+                day.by_kind.entry(kind.clone()).or_default().add(counters);
+            }
+        }
+    }
+
+    pub fn record(&mut self, date: NaiveDate, kind: RequestKind, model: &str, usage: TokenUsage) {
+        let counters = UsageCounters {
+            requests: 1,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        };
+        let day = self.days.entry(date).or_default();
+        day.by_model
+            .entry(model.to_string())
+            .or_default()
+            .add(&counters);
+        day.by_kind
+            .entry(kind.as_str().to_string())
+            .or_default()
+            .add(&counters);
+    }
+
+    /// Drop days older than the retention window.
+    pub fn prune(&mut self, today: NaiveDate) {
+        let oldest = today - chrono::Duration::days(RETENTION_DAYS);
+        self.days.retain(|date, _| *date > oldest);
+    }
+
+    pub fn day(&self, date: NaiveDate) -> UsageCounters {
+        self.days
+            .get(&date)
+            .map(DayUsage::total)
+            .unwrap_or_default()
+    }
+
+    /// Totals of the calendar month containing `date`
+    pub fn month(&self, date: NaiveDate) -> UsageCounters {
+        let mut total = UsageCounters::default();
+        for (day, usage) in &self.days {
+            if day.year() == date.year() && day.month() == date.month() {
+                total.add(&usage.total());
+            }
+        }
+        total
+    }
+
+    /// Reason the budget is exhausted on `today`, if it is.
+    pub fn budget_exceeded(&self, budget: &BudgetConfig, today: NaiveDate) -> Option<String> {
+        let day = self.day(today);
+        let month = self.month(today);
+        [
+            ("daily token", budget.daily_tokens, day.total_tokens()),
+            ("daily request", budget.daily_requests, day.requests),
+            ("monthly token", budget.monthly_tokens, month.total_tokens()),
+            ("monthly request", budget.monthly_requests, month.requests),
+        ]
+        .into_iter()
+        .find_map(|(label, limit, used)| {
+            let limit = limit?;
+            (used >= limit).then(|| format!("{} budget reached ({}/{})", label, used, limit))
+        })
+    }
+}
+
+/// Today's local date
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Default ledger location
+pub fn usage_path() -> PathBuf {
+    AppPaths::data_dir().join(USAGE_FILE)
+}
+
+/// Where an unparsable ledger is moved
+pub fn corrupt_path(path: &Path) -> PathBuf {
+    path.with_extension("json.corrupt")
+}
+
+/// Why the budget cannot be checked: a ledger was moved aside as corrupt
+/// during the budget period containing `today`.
+pub fn budget_unknown(path: &Path, budget: &BudgetConfig, today: NaiveDate) -> Option<String> {
+    let corrupt = corrupt_path(path);
+    let modified = fs::metadata(&corrupt).and_then(|m| m.modified()).ok()?;
+    let date = chrono::DateTime::<Local>::from(modified).date_naive();
+    let daily = budget.daily_tokens.is_some() || budget.daily_requests.is_some();
+    let monthly = budget.monthly_tokens.is_some() || budget.monthly_requests.is_some();
+    let same_month = date.year() == today.year() && date.month() == today.month();
+    ((daily && date == today) || (monthly && same_month)).then(|| {
+        format!(
+            "budget unknown, the usage ledger was unreadable (see {})",
+            corrupt.display()
+        )
+    })
+}
+
+/// Load the ledger at `path` along with why the budget cannot be checked, if
+/// it cannot; an unreadable ledger loads as empty but never as "within budget".
+pub fn load_checked(path: &Path, budget: &BudgetConfig) -> (UsageLedger, Option<String>) {
+    match UsageLedger::load(path) {
+        Ok(ledger) => (ledger, budget_unknown(path, budget, today())),
+        Err(e) => {
+            warn!("{:#}", e);
+            let unknown = budget_unknown(path, budget, today()).or_else(|| {
+                budget
+                    .is_limited()
+                    .then(|| format!("budget unknown, {:#}", e))
+            });
+            (UsageLedger::default(), unknown)
+        }
+    }
+}
+
+/// Records usage for one process and answers budget checks
+pub struct UsageTracker {
+    shared: Arc<SharedLedger>,
+    /// Wakes the background flush; `None` when the thread could not start
+    flusher: Option<Sender<()>>,
+}
+
+struct SharedLedger {
+    path: PathBuf,
+    budget: BudgetConfig,
+    state: Mutex<LedgerState>,
+}
+
+struct LedgerState {
+    /// Totals as of the last sync plus the pending records
+    ledger: UsageLedger,
+    /// Records not yet merged into the file
+    pending: UsageLedger,
+    /// Why the on-disk totals are unknown
+    unknown: Option<String>,
+    synced: Instant,
+    /// A flush has been requested and has not started yet
+    scheduled: bool,
+}
+
+impl UsageTracker {
+    pub fn new(config: &UsageConfig) -> Self {
+        Self::with_path(config, usage_path())
+    }
+
+    pub fn with_path(config: &UsageConfig, path: PathBuf) -> Self {
+        let (ledger, unknown) = load_checked(&path, &config.budget);
+        let shared = Arc::new(SharedLedger {
+            path,
+            budget: config.budget.clone(),
+            state: Mutex::new(LedgerState {
+                ledger,
+                pending: UsageLedger::default(),
+                unknown,
+                synced: Instant::now(),
+                scheduled: false,
+            }),
+        });
+        let flusher = spawn_flusher(Arc::downgrade(&shared));
+        Self { shared, flusher }
+    }
+
+    pub fn budget(&self) -> &BudgetConfig {
+        &self.shared.budget
+    }
+
+    /// Add one answered request to today's totals; the file is updated in
+    /// the background.
+    pub fn record(&self, kind: RequestKind, model: &str, usage: TokenUsage) {
+        let today = today();
+        let schedule = {
+            let mut state = self.shared.lock();
+            state.ledger.record(today, kind, model, usage);
+            state.pending.record(today, kind, model, usage);
+            !std::mem::replace(&mut state.scheduled, true)
+        };
+        if schedule {
+            self.schedule_flush();
+        }
+    }
+
+    /// Reason the configured budget is exhausted (or cannot be checked), if
+    /// any limit is set.
+    pub fn budget_exceeded(&self) -> Option<String> {
+        let budget = self.budget();
+        if !budget.is_limited() {
+            return None;
+        }
+        let (reason, schedule) = {
+            let mut state = self.shared.lock();
+            let reason = state
+                .unknown
+                .clone()
+                .or_else(|| state.ledger.budget_exceeded(budget, today()));
+            let stale = state.synced.elapsed() >= REFRESH_INTERVAL;
+            let schedule = stale && !std::mem::replace(&mut state.scheduled, true);
+            (reason, schedule)
+        };
+        if schedule {
+            self.schedule_flush();
+        }
+        reason
+    }
+
+    fn schedule_flush(&self) {
+        let sent = self
+            .flusher
+            .as_ref()
+            .is_some_and(|flusher| flusher.send(()).is_ok());
+        if !sent {
+            self.shared.sync();
+        }
+    }
+}
+
+impl Drop for UsageTracker {
+    fn drop(&mut self) {
+        if !self.shared.lock().pending.days.is_empty() {
+            self.shared.sync();
+        }
+    }
+}
+
+fn spawn_flusher(shared: Weak<SharedLedger>) -> Option<Sender<()>> {
+    let (sender, wakeups) = mpsc::channel::<()>();
+    let spawned = std::thread::Builder::new()
+        .name("nudge-usage".to_string())
+        .spawn(move || {
+            while wakeups.recv().is_ok() {
+                std::thread::sleep(FLUSH_DELAY);
+                while wakeups.try_recv().is_ok() {}
+                let Some(shared) = shared.upgrade() else {
+                    break;
+                };
+                shared.sync();
+            }
+        });
+    match spawned {
+        Ok(_) => Some(sender),
+        Err(e) => {
+            warn!("Failed to start the usage flush thread: {}", e);
+            None
+        }
+    }
+}
+
+impl SharedLedger {
+    fn lock(&self) -> std::sync::MutexGuard<'_, LedgerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Merge pending records into the file and reload the totals, which
+    /// picks up what other processes recorded in the meantime.
+    fn sync(&self) {
+        let pending = {
+            let mut state = self.lock();
+            state.scheduled = false;
+            std::mem::take(&mut state.pending)
+        };
+        match self.write(&pending) {
+            Ok(mut ledger) => {
+                let unknown = budget_unknown(&self.path, &self.budget, today());
+                let mut state = self.lock();
+                // Records made while the file was written stay pending.
+                ledger.merge(&state.pending);
+                state.ledger = ledger;
+                state.unknown = unknown;
+                state.synced = Instant::now();
+            }
+            Err(e) => {
+                warn!("Failed to record token usage: {:#}", e);
+                let mut state = self.lock();
+                state.pending.merge(&pending);
+                state.unknown = Some(format!("budget unknown, {:#}", e));
+                state.synced = Instant::now();
+            }
+        }
+    }
+
+    /// Add `pending` to the file under the advisory lock and return the new totals.
+    fn write(&self, pending: &UsageLedger) -> Result<UsageLedger> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let lock_path = self.path.with_extension("json.lock");
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        let mut ledger = match UsageLedger::load(&self.path) {
+            Ok(ledger) => ledger,
+            // Moved aside as corrupt: start over, `budget_unknown` keeps
+            // the budget closed for the rest of the period.
+            Err(e) if !self.path.exists() => {
+                warn!("{:#}", e);
+                UsageLedger::default()
+            }
+            Err(e) => return Err(e),
+        };
+        if pending.days.is_empty() {
+            return Ok(ledger);
+        }
+        let today = today();
+        ledger.merge(pending);
+        ledger.prune(today);
+        ledger.save(&self.path)?;
+        Ok(ledger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn tokens(input_tokens: u64, output_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    #[test]
+    fn totals_by_model_kind_day_and_month() {
+        let mut ledger = UsageLedger::default();
+        let day = date("2026-03-14");
+        ledger.record(day, RequestKind::Completion, "gpt-4o-mini", tokens(100, 10));
+        ledger.record(day, RequestKind::Completion, "qwen", tokens(50, 5));
+        ledger.record(day, RequestKind::Diagnosis, "gpt-4o-mini", tokens(200, 40));
+        ledger.record(
+            date("2026-03-01"),
+            RequestKind::Completion,
+            "qwen",
+            tokens(1, 1),
+        );
+        ledger.record(
+            date("2026-02-28"),
+            RequestKind::Completion,
+            "qwen",
+            tokens(7, 7),
+        );
+
+        let usage = &ledger.days[&day];
+        assert_eq!(usage.by_model["gpt-4o-mini"].requests, 2);
+        assert_eq!(usage.by_kind["completion"].total_tokens(), 165);
+        assert_eq!(ledger.day(day).total_tokens(), 405);
+        assert_eq!(ledger.month(day).requests, 4);
+        assert_eq!(ledger.month(day).total_tokens(), 407);
+    }
+
+    #[test]
+    fn budget_reports_first_exhausted_limit() {
+        let mut ledger = UsageLedger::default();
+        let day = date("2026-03-14");
+        ledger.record(day, RequestKind::Completion, "m", tokens(900, 100));
+
+        let mut budget = BudgetConfig {
+            daily_tokens: Some(2000),
+            monthly_requests: Some(5),
+            ..BudgetConfig::default()
+        };
+        assert_eq!(ledger.budget_exceeded(&budget, day), None);
+
+        budget.daily_tokens = Some(1000);
+        assert_eq!(
+            ledger.budget_exceeded(&budget, day).as_deref(),
+            Some("daily token budget reached (1000/1000)")
+        );
+        // A new day starts a new daily budget.
+        assert_eq!(ledger.budget_exceeded(&budget, date("2026-03-15")), None);
+    }
+
+    #[test]
+    fn tracker_persists_and_prunes() {
+        let dir = std::env::temp_dir().join(format!("nudge-usage-{}", std::process::id()));
+        let path = dir.join("usage.json");
+        let config = UsageConfig {
+            enabled: true,
+            budget: BudgetConfig {
+                daily_requests: Some(2),
+                ..BudgetConfig::default()
+            },
+        };
+
+        let mut old = UsageLedger::default();
+        old.record(
+            today() - chrono::Duration::days(RETENTION_DAYS + 1),
+            RequestKind::Completion,
+            "m",
+            tokens(1, 1),
+        );
+        old.save(&path).unwrap();
+
+        let tracker = UsageTracker::with_path(&config, path.clone());
+        tracker.record(RequestKind::Completion, "m", tokens(10, 2));
+        assert_eq!(tracker.budget_exceeded(), None);
+        // Another process adding to the same ledger
+        let other = UsageTracker::with_path(&config, path.clone());
+        other.record(RequestKind::Diagnosis, "m", tokens(10, 2));
+        assert!(other.budget_exceeded().is_none());
+        tracker.record(RequestKind::Diagnosis, "m", tokens(10, 2));
+        assert!(tracker.budget_exceeded().is_some());
+        drop(tracker);
+        drop(other);
+
+        let ledger = UsageLedger::load(&path).unwrap();
+        assert_eq!(ledger.days.len(), 1);
+        assert_eq!(ledger.day(today()).requests, 3);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn corrupt_ledger_is_moved_aside_and_closes_the_budget() {
+        let dir = std::env::temp_dir().join(format!("nudge-usage-corrupt-{}", std::process::id()));
+        let path = dir.join("usage.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "{\"days\": {\"2026-03-14\": ").unwrap();
+        let mut config = UsageConfig {
+            enabled: true,
+            budget: BudgetConfig {
+                daily_requests: Some(100),
+                ..BudgetConfig::default()
+            },
+        };
+
+        let tracker = UsageTracker::with_path(&config, path.clone());
+        let reason = tracker.budget_exceeded().expect("budget should be unknown");
+        assert!(reason.contains("budget unknown"), "{}", reason);
+        assert!(corrupt_path(&path).exists());
+        tracker.record(RequestKind::Completion, "m", tokens(1, 1));
+        drop(tracker);
+
+        // The new ledger keeps counting, but the budget stays closed until
+        // the period the lost totals belong to is over.
+        assert_eq!(UsageLedger::load(&path).unwrap().day(today()).requests, 1);
+        let tracker = UsageTracker::with_path(&config, path.clone());
+        assert!(tracker.budget_exceeded().is_some());
+        config.budget = BudgetConfig::default();
+        let tracker = UsageTracker::with_path(&config, path);
+        assert_eq!(tracker.budget_exceeded(), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,382 @@
+//! Validation of model suggestions.
+//!
+//! Before model suggestions are returned they are checked for commands that
+//! do not exist, unbalanced quotes and brackets, and optionally with the
+//! shell's own parser (`bash -n` / `zsh -n`). Suggestions that fail are
+//! demoted behind the valid ones or dropped (`validation.on_failure`).
+//! PowerShell and cmd quoting differs, so only bash and zsh modes are checked.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::process::Command;
+use tracing::debug;
+
+use super::shell_mode::ShellMode;
+use super::shell_words;
+use crate::config::{ValidationAction, ValidationConfig};
+use crate::protocol::{Suggestion, SuggestionSource, Validation};
+
+/// Confidence factor of suggestions demoted for failing validation
+const DEMOTED_CONFIDENCE: f32 = 0.5;
+
+/// bash and zsh builtins and commands every shell provides
+const BUILTINS: &[&str] = &[
+    ".",
+    ":",
+    "[",
+    "[[",
+    "alias",
+    "autoload",
+    "bg",
+    "bind",
+    "bindkey",
+    "break",
+    "builtin",
+    "caller",
+    "cd",
+    "command",
+    "compdef",
+    "compgen",
+    "complete",
+    "continue",
+    "declare",
+    "dirs",
+    "disown",
+    "echo",
+    "emulate",
+    "enable",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fc",
+    "fg",
+    "getopts",
+    "hash",
+    "help",
+    "history",
+    "jobs",
+    "kill",
+    "let",
+    "local",
+    "logout",
+    "mapfile",
+    "noglob",
+    "popd",
+    "print",
+    "printf",
+    "pushd",
+    "pwd",
+    "read",
+    "readarray",
+    "readonly",
+    "rehash",
+    "return",
+    "set",
+    "setopt",
+    "shift",
+    "shopt",
+    "source",
+    "suspend",
+    "test",
+    "time",
+    "times",
+    "trap",
+    "true",
+    "type",
+    "typeset",
+    "ulimit",
+    "umask",
+    "unalias",
+    "unset",
+    "unsetopt",
+    "wait",
+    "whence",
+    "where",
+    "which",
+    "zle",
+    "zmodload",
+];
+
+/// Checks model suggestions for one request
+pub struct Validator<'a> {
+    config: &'a ValidationConfig,
+    /// Shell whose parser runs with `shell_syntax`
+    shell: &'static str,
+    cwd: &'a Path,
+    /// Commands the user has run, which covers their aliases and functions
+    history_commands: HashSet<String>,
+}
+
+impl<'a> Validator<'a> {
+    /// Validator for requests in `shell_mode`, or `None` when validation is
+    /// disabled or the shell is not bash or zsh
+    pub fn new(
+        config: &'a ValidationConfig,
+        shell_mode: ShellMode,
+        cwd: &'a Path,
+        history: &[String],
+    ) -> Option<Self> {
+        let shell = match shell_mode {
+            _ if !config.enabled => return None,
+            ShellMode::ZshAuto | ShellMode::ZshInline => "zsh",
+            ShellMode::BashInline | ShellMode::BashPopup | ShellMode::Unknown => "bash",
+            ShellMode::PsInline | ShellMode::CmdInline => return None,
+        };
+        let history_commands = history
+            .iter()
+            .flat_map(|line| shell_words::command_names(line))
+            .map(|word| word.text)
+            .collect();
+        Some(Self {
+            config,
+            shell,
+            cwd,
+            history_commands,
+        })
+    }
+
+    /// Run every enabled check on `command`
+    pub async fn check(&self, command: &str) -> Validation {
+        let mut issues = Vec::new();
+
+        if self.config.check_commands {
+            for word in shell_words::command_names(command) {
+                if let Some(issue) = self.unknown_command(&word.text) {
+                    issues.push(issue);
+                }
+            }
+        }
+
+        match shell_words::unbalanced(command) {
+            Some(issue) => issues.push(issue),
+            // The shell would only repeat the quoting problem
+            None if self.config.shell_syntax => {
+                if let Some(issue) = self.shell_syntax(command).await {
+                    issues.push(issue);
+                }
+            }
+            None => {}
+        }
+
+        Validation {
+            valid: issues.is_empty(),
+            issues,
+        }
+    }
+
+    /// Why `name` does not resolve to a command, if it does not
+    fn unknown_command(&self, name: &str) -> Option<String> {
+        if name.contains('/') {
+            let path = match name.strip_prefix("~/") {
+                Some(rest) => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(rest)),
+                None => Some(self.cwd.join(name)),
+            };
+            return match path {
+                Some(path) if !path.is_file() => Some(format!("no such file '{}'", name)),
+                _ => None,
+            };
+        }
+
+        let known = BUILTINS.contains(&name)
+            || self.config.known_commands.iter().any(|known| known == name)
+            || self.history_commands.contains(name)
+            || on_path(name);
+        (!known).then(|| format!("unknown command '{}'", name))
+    }
+
+    /// First error `<shell> -n` reports for `command`. A missing shell or a
+    /// timeout gives no verdict.
+    async fn shell_syntax(&self, command: &str) -> Option<String> {
+        let output = Command::new(self.shell)
+            .args(["-n", "-c", command])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output();
+        let timeout = Duration::from_millis(self.config.shell_syntax_timeout_ms);
+        let output = match tokio::time::timeout(timeout, output).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                debug!("Cannot run {} -n: {}", self.shell, e);
+                return None;
+            }
+            Err(_) => return None,
+        };
+        if output.status.success() {
+            return None;
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Drop the `bash: -c: line 1:` / `zsh:1:` location prefix
+        let message = stderr
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split(": ")
+            .skip_while(|part| {
+                part.starts_with(self.shell) || *part == "-c" || part.starts_with("line ")
+            })
+            .collect::<Vec<_>>()
+            .join(": ");
+        Some(format!("{} -n: {}", self.shell, message))
+    }
+}
+
+/// Whether an executable `name` is on the daemon's `PATH`
+fn on_path(name: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| is_executable(&dir.join(name)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+/// Validate the model suggestions among `suggestions`, then demote or drop
+/// the ones that fail. History and predictor suggestions were run before and
+/// are left alone.
+pub async fn apply(suggestions: &mut Vec<Suggestion>, validator: &Validator<'_>) {
+    for suggestion in suggestions.iter_mut() {
+        if suggestion.source != SuggestionSource::Llm {
+            continue;
+        }
+        let validation = validator.check(&suggestion.text).await;
+        if !validation.valid {
+            debug!(
+                "Suggestion '{}' failed validation: {}",
+                suggestion.text,
+                validation.issues.join("; ")
+            );
+        }
+        suggestion.validation = Some(validation);
+    }
+
+    let failed = |suggestion: &Suggestion| {
+        suggestion
+            .validation
+            .as_ref()
+            .is_some_and(|validation| !validation.valid)
+    };
+    match validator.config.on_failure {
+        ValidationAction::Drop => suggestions.retain(|suggestion| !failed(suggestion)),
+        ValidationAction::Demote => {
+            for suggestion in suggestions.iter_mut().filter(|s| failed(s)) {
+                suggestion.confidence = suggestion
+                    .confidence
+                    .map(|confidence| confidence * DEMOTED_CONFIDENCE);
+            }
+            // Stable, so valid suggestions keep their order
+            suggestions.sort_by_key(failed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ValidationConfig {
+        ValidationConfig {
+            known_commands: vec!["ll".to_string()],
+            ..ValidationConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn flags_unknown_commands_and_bad_quoting() {
+        let config = config();
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let history = vec!["mytool --sync".to_string()];
+        let validator = Validator::new(&config, ShellMode::BashPopup, cwd, &history).unwrap();
+
+        for command in [
+            "sh -c true | cat",
+            "cd src && ll",
+            "mytool --check",
+            "FOO=1 ./Cargo.toml",
+        ] {
+            assert_eq!(
+                validator.check(command).await,
+                Validation {
+                    valid: true,
+                    issues: vec![]
+                },
+                "{}",
+                command
+            );
+        }
+
+        let validation = validator
+            .check("gti status && sh -c 'echo \"x' ./missing.sh")
+            .await;
+        assert!(!validation.valid);
+        assert_eq!(validation.issues, vec!["unknown command 'gti'"]);
+
+        let validation = validator.check("echo \"unterminated").await;
+        assert_eq!(validation.issues, vec!["unterminated double quote"]);
+        let validation = validator.check("./missing.sh --run").await;
+        assert_eq!(validation.issues, vec!["no such file './missing.sh'"]);
+
+        assert!(Validator::new(&config, ShellMode::PsInline, cwd, &history).is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_suggestions_are_demoted_or_dropped() {
+        let mut config = config();
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let suggestions = vec![
+            Suggestion::new("gti status".to_string()).with_confidence(1.0),
+            Suggestion::new("git status".to_string()).with_confidence(0.9),
+            Suggestion::new("gitk".to_string()).with_source(SuggestionSource::History),
+        ];
+
+        let validator = Validator::new(&config, ShellMode::BashPopup, cwd, &[]).unwrap();
+        let mut demoted = suggestions.clone();
+        apply(&mut demoted, &validator).await;
+        let texts: Vec<&str> = demoted.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["git status", "gitk", "gti status"]);
+        assert_eq!(demoted[2].confidence, Some(0.5));
+        assert!(demoted[0].validation.as_ref().unwrap().valid);
+        assert!(demoted[1].validation.is_none());
+
+        config.on_failure = ValidationAction::Drop;
+        let validator = Validator::new(&config, ShellMode::BashPopup, cwd, &[]).unwrap();
+        let mut dropped = suggestions;
+        apply(&mut dropped, &validator).await;
+        assert_eq!(dropped.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn shell_parser_reports_syntax_errors() {
+        let config = ValidationConfig {
+            shell_syntax: true,
+            shell_syntax_timeout_ms: 2000,
+            ..config()
+        };
+        let validator =
+            Validator::new(&config, ShellMode::BashInline, Path::new("."), &[]).unwrap();
+        if !on_path("bash") {
+            return;
+        }
+        assert!(validator.check("if true; then echo ok; fi").await.valid);
+        let validation = validator.check("if true; then echo ok").await;
+        assert!(!validation.valid);
+        assert!(validation.issues[0].starts_with("bash -n: syntax error"));
+    }
+}
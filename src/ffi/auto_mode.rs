@@ -323,8 +323,18 @@ pub unsafe extern "C" fn nudge_auto_trigger(
 
         // Run completion
         let result = context.runtime.block_on(async {
-            completion::complete(&buffer, cursor, &cwd, &session_id, &context.config).await
+            completion::complete(
+                &buffer,
+                cursor,
+                &cwd,
+                &session_id,
+                &context.config,
+                &context.router,
+                &context.predictor,
+            )
+            .await
         });
+        context.set_last_edit(result.edit.clone());
 
         // Store suggestion for later retrieval
         if result.error.is_none() && !result.suggestion.is_empty() {
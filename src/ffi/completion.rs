@@ -3,15 +3,21 @@
 //! This module implements the completion logic for FFI calls, reusing
 //! the existing daemon code for context gathering, sanitization, and LLM calls.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::config::Config;
+use tracing::debug;
+
+use crate::config::{Config, PredictorMode, RequestKind};
 use crate::daemon::context::{self, GatherParams};
 use crate::daemon::llm;
+use crate::daemon::llm::placeholder;
+use crate::daemon::llm::router::ModelRouter;
+use crate::daemon::policy::AppliedPolicy;
+use crate::daemon::predictor::PredictorStore;
 use crate::daemon::safety;
 use crate::daemon::sanitizer;
 use crate::daemon::shell_mode::ShellMode;
-use crate::protocol::CompletionRequest;
+use crate::protocol::{CompletionRequest, SuggestionEdit};
 
 /// Result of a completion operation
 pub struct CompletionResult {
@@ -21,6 +27,8 @@ pub struct CompletionResult {
     pub warning: Option<String>,
     /// Error message if completion failed
     pub error: Option<String>,
+    /// How the suggestion changes the buffer, when it differs from it
+    pub edit: Option<SuggestionEdit>,
 }
 
 impl CompletionResult {
@@ -30,6 +38,7 @@ impl CompletionResult {
             suggestion,
             warning,
             error: None,
+            edit: None,
         }
     }
 
@@ -39,6 +48,7 @@ impl CompletionResult {
             suggestion: String::new(),
             warning: None,
             error: Some(message),
+            edit: None,
         }
     }
 }
@@ -48,7 +58,7 @@ impl CompletionResult {
 /// This function:
 /// 1. Gathers context (history, CWD, git, plugins)
 /// 2. Sanitizes sensitive data
-/// 3. Calls the LLM API
+/// 3. Calls the LLM API, falling back to history predictions when it fails
 /// 4. Checks for dangerous commands
 ///
 /// # Arguments
@@ -57,13 +67,36 @@ impl CompletionResult {
 /// * `cwd` - Current working directory
 /// * `session_id` - Shell session identifier
 /// * `config` - Loaded configuration
+/// * `router` - Shared LLM model router
+/// * `predictor` - History predictor indexes
 pub async fn complete(
     buffer: &str,
     cursor: usize,
     cwd: &str,
     session_id: &str,
     config: &Config,
+    router: &ModelRouter,
+    predictor: &PredictorStore,
 ) -> CompletionResult {
+    // Best history prediction, unless the predictor is off
+    let predict = || match config.predictor.mode {
+        PredictorMode::Off => None,
+        _ => predictor
+            .predict(session_id, buffer, &config.predictor)
+            .into_iter()
+            .next()
+            .map(|prediction| finish(buffer, cursor, prediction.text, config)),
+    };
+    if config.predictor.mode == PredictorMode::Only {
+        return predict().unwrap_or_else(|| CompletionResult::success(String::new(), None));
+    }
+
+    let policy = AppliedPolicy::resolve(&config.privacy, Path::new(cwd));
+    if let Some(reason) = policy.refusal() {
+        debug!("{}", reason);
+        return CompletionResult::success(String::new(), None);
+    }
+
     // Create completion request
     let request = CompletionRequest::new(
         session_id.to_string(),
@@ -82,23 +115,64 @@ pub async fn complete(
         }
     };
 
-    // Sanitize context
-    let (sanitized_context, _events) = if config.privacy.sanitize_enabled {
-        sanitizer::sanitize(&context, &config.privacy.custom_patterns)
+    // Sanitize context and the buffer
+    let mut redactor = sanitizer::Redactor::reversible(&config.privacy);
+    let (sanitized_context, sanitized_buffer, sanitized_cursor) = if config.privacy.sanitize_enabled
+    {
+        let context = redactor.context(&context);
+        let (buffer, cursor) = redactor.buffer(buffer, cursor);
+        (context, buffer, cursor)
     } else {
-        (context, Vec::new())
+        (context, buffer.to_string(), cursor)
     };
 
     // Call LLM
     let shell_mode = ShellMode::resolve(None, session_id);
-    let completion = match llm::complete(buffer, &sanitized_context, config, shell_mode).await {
+    let route = match router.route(RequestKind::Completion, shell_mode) {
+        Ok(route) => route,
+        Err(e) => {
+            return predict().unwrap_or_else(|| CompletionResult::error(e.to_string()));
+        }
+    };
+    if let Err(reason) = policy.check_route(&route) {
+        return predict().unwrap_or_else(|| CompletionResult::error(reason));
+    }
+    let prepared = llm::build_request(
+        &sanitized_buffer,
+        sanitized_cursor,
+        &sanitized_context,
+        config,
+        shell_mode,
+        &route,
+    );
+    let completion = match llm::complete(
+        &prepared.request,
+        &sanitized_buffer,
+        config,
+        shell_mode,
+        &route,
+    )
+    .await
+    {
         Ok(s) => s,
         Err(e) => {
-            return CompletionResult::error(format!("LLM completion failed: {}", e));
+            return predict().unwrap_or_else(|| {
+                CompletionResult::error(format!("LLM completion failed: {}", e))
+            });
         }
     };
-    let suggestion = completion.command;
+    // Tab stops cannot be reported through the callback, so only their text is kept
+    let command = redactor.secrets.rehydrate(&completion.command);
+    let (suggestion, _placeholders) = placeholder::extract(command.trim());
+    if let Some(reason) = safety::guard(&suggestion, Some(buffer), &config.privacy) {
+        debug!("Dropped suggestion '{}': {}", suggestion, reason);
+        return predict().unwrap_or_else(|| CompletionResult::success(String::new(), None));
+    }
+    finish(buffer, cursor, suggestion, config)
+}
 
+/// Attach the safety warning and buffer edit to a suggestion
+fn finish(buffer: &str, cursor: usize, suggestion: String, config: &Config) -> CompletionResult {
     // Check for dangerous commands
     let warning = if config.privacy.block_dangerous {
         safety::check(&suggestion, &config.privacy.custom_blocked).map(|w| w.message)
@@ -106,7 +180,11 @@ pub async fn complete(
         None
     };
 
-    CompletionResult::success(suggestion, warning)
+    let edit = SuggestionEdit::between(buffer, &suggestion, cursor);
+    CompletionResult {
+        edit,
+        ..CompletionResult::success(suggestion, warning)
+    }
 }
 
 /// Simple hash function for cache keys
@@ -11,24 +11,36 @@ use tokio::runtime::Runtime;
 
 use super::auto_mode::AutoModeState;
 use crate::config::Config;
+use crate::daemon::llm::router::ModelRouter;
+use crate::daemon::predictor::PredictorStore;
+use crate::protocol::SuggestionEdit;
 
 /// Context for FFI operations
 ///
 /// This struct holds all state needed for FFI completion calls:
 /// - Configuration loaded from file
 /// - Tokio runtime for async operations
+/// - LLM model router with a pooled HTTP client reused across calls
+/// - History predictor indexes used when the LLM is unavailable
 /// - Cache for completion results (keyed by hash of input)
 /// - Last error message for error retrieval
+/// - Structured edit of the last completion
 /// - Auto mode state for background completion
 pub struct NudgeContext {
     /// Loaded configuration
     pub config: Config,
     /// Tokio runtime for async operations
     pub runtime: Runtime,
+    /// LLM model router (built once, shares its connection pool across calls)
+    pub router: ModelRouter,
+    /// History predictor indexes (rebuilt when the history file changes)
+    pub predictor: PredictorStore,
     /// Simple cache for recent completions (hash -> suggestion)
     pub cache: Arc<Mutex<HashMap<u64, String>>>,
     /// Last error message (for nudge_get_error)
     pub last_error: Arc<Mutex<Option<String>>>,
+    /// Edit of the last completion (for nudge_get_edit)
+    pub last_edit: Mutex<Option<SuggestionEdit>>,
     /// Auto mode state
     pub auto_mode: AutoModeState,
     /// Auto mode delay in milliseconds
@@ -42,14 +54,24 @@ impl NudgeContext {
         let runtime =
             Runtime::new().map_err(|e| format!("Failed to create Tokio runtime: {}", e))?;
 
+        let router = {
+            let _guard = runtime.enter();
+            ModelRouter::from_config(&config.model)
+                .and_then(|router| router.with_usage(&config))
+                .map_err(|e| format!("Failed to create LLM provider: {}", e))?
+        };
+
         // Get auto delay from config (convert u64 to u32, clamping if necessary)
         let auto_delay_ms = config.trigger.auto_delay_ms.min(u32::MAX as u64) as u32;
 
         Ok(Self {
             config,
             runtime,
+            router,
+            predictor: PredictorStore::new(),
             cache: Arc::new(Mutex::new(HashMap::new())),
             last_error: Arc::new(Mutex::new(None)),
+            last_edit: Mutex::new(None),
             auto_mode: AutoModeState::new(),
             auto_delay_ms: AtomicU32::new(auto_delay_ms),
         })
@@ -78,6 +100,13 @@ impl NudgeContext {
         }
     }
 
+    /// Remember the edit of the latest completion, replacing the previous one
+    pub fn set_last_edit(&self, edit: Option<SuggestionEdit>) {
+        if let Ok(mut guard) = self.last_edit.lock() {
+            *guard = edit;
+        }
+    }
+
     /// Get a cached completion result
     pub fn get_cached(&self, hash: u64) -> Option<String> {
         if let Ok(guard) = self.cache.lock() {
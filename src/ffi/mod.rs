@@ -52,9 +52,10 @@ use std::panic;
 use std::path::PathBuf;
 
 use crate::config::Config;
+use crate::protocol::TextPosition;
 
 pub use context::NudgeContext;
-pub use types::{CompletionCallback, NudgeContextHandle, NudgeError};
+pub use types::{CompletionCallback, NudgeContextHandle, NudgeEdit, NudgeError};
 
 /// Initialize a new NudgeContext
 ///
@@ -209,9 +210,12 @@ pub unsafe extern "C" fn nudge_complete(
                 cwd_str,
                 session_str,
                 &context.config,
+                &context.router,
+                &context.predictor,
             )
             .await
         });
+        context.set_last_edit(result.edit.clone());
 
         // Prepare callback arguments
         let suggestion_cstr = CString::new(result.suggestion.as_str()).unwrap_or_default();
@@ -250,6 +254,92 @@ pub unsafe extern "C" fn nudge_complete(
     }
 }
 
+/// Get the structured edit of the last completion
+///
+/// Lets callers apply a suggestion exactly (e.g. a mid-line insertion)
+/// instead of replacing the whole buffer.
+///
+/// # Arguments
+/// * `ctx` - NudgeContext handle from `nudge_init`
+/// * `out` - Receives the edit when one is available
+///
+/// # Returns
+/// * 1 if an edit was written to `out`
+/// * 0 if the last completion did not change the buffer (or none ran yet)
+/// * Negative error code on failure
+///
+/// # Safety
+/// * `ctx` must be a valid handle from `nudge_init`
+/// * `out` must point to a writable `NudgeEdit`
+/// * `out->insert` is a copy owned by the caller: it stays valid across later
+///   completions and must be released with `nudge_free_edit`
+#[no_mangle]
+pub unsafe extern "C" fn nudge_get_edit(ctx: NudgeContextHandle, out: *mut NudgeEdit) -> c_int {
+    let result = panic::catch_unwind(|| {
+        if ctx.is_null() || out.is_null() {
+            error::set_error("Context handle or output pointer is null");
+            return NudgeError::NullPointer.into();
+        }
+
+        // SAFETY: Caller guarantees ctx is a valid NudgeContext pointer
+        let context = &*(ctx as *const NudgeContext);
+        let Ok(guard) = context.last_edit.lock() else {
+            return NudgeError::RuntimeError.into();
+        };
+        let Some(edit) = guard.as_ref() else {
+            return 0;
+        };
+        let Ok(insert) = CString::new(edit.insert.as_str()) else {
+            error::set_error("Edit text contains a null byte");
+            return 0;
+        };
+        let cursor = edit.cursor.unwrap_or(TextPosition {
+            byte: edit.range.start + edit.insert.len(),
+            char: edit.char_range.start + edit.insert.chars().count(),
+        });
+
+        // SAFETY: Caller guarantees out points to a writable NudgeEdit
+        *out = NudgeEdit {
+            replace_start: edit.range.start,
+            replace_end: edit.range.end,
+            replace_start_char: edit.char_range.start,
+            replace_end_char: edit.char_range.end,
+            cursor: cursor.byte,
+            cursor_char: cursor.char,
+            insert: insert.into_raw(),
+        };
+        1
+    });
+
+    match result {
+        Ok(code) => code,
+        Err(_) => {
+            error::set_error("Panic while reading edit");
+            NudgeError::RuntimeError.into()
+        }
+    }
+}
+
+/// Release the insert text of an edit from `nudge_get_edit`
+///
+/// # Arguments
+/// * `edit` - Edit filled by `nudge_get_edit`; its `insert` is set to NULL
+///
+/// # Safety
+/// * `edit` must be NULL or point to a `NudgeEdit` whose `insert` is NULL or
+///   came from `nudge_get_edit` and was not released yet
+#[no_mangle]
+pub unsafe extern "C" fn nudge_free_edit(edit: *mut NudgeEdit) {
+    let _ = panic::catch_unwind(|| {
+        if edit.is_null() || (*edit).insert.is_null() {
+            return;
+        }
+        // SAFETY: Caller guarantees insert came from CString::into_raw in nudge_get_edit
+        let _ = CString::from_raw((*edit).insert);
+        (*edit).insert = std::ptr::null_mut();
+    });
+}
+
 /// Get the last error message
 ///
 /// # Arguments
@@ -354,6 +444,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_edit_outlives_the_next_completion() {
+        use crate::protocol::{SuggestionEdit, TextRange};
+
+        unsafe {
+            let ctx = nudge_init(std::ptr::null());
+            if ctx.is_null() {
+                return;
+            }
+            let context = &*(ctx as *const NudgeContext);
+            context.set_last_edit(Some(SuggestionEdit {
+                range: TextRange { start: 4, end: 4 },
+                char_range: TextRange { start: 4, end: 4 },
+                insert: "status".to_string(),
+                cursor: None,
+            }));
+
+            let mut edit = std::mem::MaybeUninit::<NudgeEdit>::uninit();
+            assert_eq!(nudge_get_edit(ctx, edit.as_mut_ptr()), 1);
+            let mut edit = edit.assume_init();
+            // A later completion replaces the context's edit but not the copy
+            context.set_last_edit(None);
+            assert_eq!(CStr::from_ptr(edit.insert).to_str().unwrap(), "status");
+            assert_eq!(edit.cursor, 10);
+
+            nudge_free_edit(&mut edit);
+            assert!(edit.insert.is_null());
+            nudge_free_edit(&mut edit);
+            nudge_free(ctx);
+        }
+    }
+
     #[test]
     fn test_version() {
         let version = nudge_version();
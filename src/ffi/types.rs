@@ -25,6 +25,30 @@ pub type CompletionCallback = extern "C" fn(
     user_data: *mut c_void,
 );
 
+/// Structured edit of the buffer for the last completion (see `nudge_get_edit`)
+///
+/// Offsets are given both in bytes and in characters. Replacing
+/// `replace_start..replace_end` with `insert` yields the suggestion.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct NudgeEdit {
+    /// Byte offset where the replaced range starts
+    pub replace_start: usize,
+    /// Byte offset where the replaced range ends (exclusive)
+    pub replace_end: usize,
+    /// Character offset where the replaced range starts
+    pub replace_start_char: usize,
+    /// Character offset where the replaced range ends (exclusive)
+    pub replace_end_char: usize,
+    /// Byte offset of the cursor after accepting
+    pub cursor: usize,
+    /// Character offset of the cursor after accepting
+    pub cursor_char: usize,
+    /// Text to insert (null-terminated C string, owned by the caller and
+    /// released with `nudge_free_edit`)
+    pub insert: *mut c_char,
+}
+
 /// Error codes returned by FFI functions
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
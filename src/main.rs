@@ -11,7 +11,7 @@ use clap::Parser;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use crate::cli::{Cli, Command};
+use crate::cli::{Cli, Command, PrivacyAction, PromptAction};
 use crate::config::Config;
 
 /// Initialize logging based on command type and configuration
@@ -143,7 +143,7 @@ async fn main() -> Result<()> {
             daemon::status().await?;
         }
         Command::Info { json, field } => {
-            commands::info::run_info(json, field)?;
+            commands::info::run_info(json, field).await?;
         }
         Command::Context {
             buffer,
@@ -154,12 +154,92 @@ async fn main() -> Result<()> {
         } => {
             commands::context::run_context(buffer, cwd, session, last_exit_code, json).await?;
         }
+        Command::Prompt { action } => match action {
+            PromptAction::Render {
+                buffer,
+                cursor,
+                cwd,
+                session,
+                shell_mode,
+                last_exit_code,
+                diagnose,
+                ask,
+                explain,
+                stderr,
+                json,
+            } => {
+                commands::prompt::run_prompt_render(commands::prompt::RenderOptions {
+                    buffer,
+                    cursor,
+                    cwd,
+                    session,
+                    shell_mode,
+                    last_exit_code,
+                    diagnose,
+                    ask,
+                    explain,
+                    stderr,
+                    json,
+                })
+                .await?;
+            }
+            PromptAction::Init { force } => {
+                commands::prompt::run_prompt_init(force).await?;
+            }
+        },
+        Command::Privacy { action } => match action {
+            PrivacyAction::Audit {
+                buffer,
+                cursor,
+                cwd,
+                session,
+                shell_mode,
+                last_exit_code,
+                diff,
+                json,
+            } => {
+                commands::privacy::run_privacy_audit(commands::privacy::AuditOptions {
+                    buffer,
+                    cursor,
+                    cwd,
+                    session,
+                    shell_mode,
+                    last_exit_code,
+                    diff,
+                    json,
+                })
+                .await?;
+            }
+        },
+        Command::Usage { json, days } => {
+            commands::usage::run_usage(json, days).await?;
+        }
         Command::Doctor { shell } => {
             commands::doctor::run_doctor(shell).await?;
         }
         Command::Setup { shell, force } => {
             commands::setup::run_setup(shell, force).await?;
         }
+        Command::Ask {
+            query,
+            cwd,
+            session,
+            shell_mode,
+            format,
+        } => {
+            let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
+            client::ask(query, cwd, session, shell_mode, format).await?;
+        }
+        Command::Explain {
+            command,
+            cwd,
+            session,
+            shell_mode,
+            format,
+        } => {
+            let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
+            client::explain(command, cwd, session, shell_mode, format).await?;
+        }
         Command::Diagnose {
             exit_code,
             command,
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
@@ -127,6 +127,18 @@ pub struct Suggestion {
     /// Warning if command is potentially dangerous
     #[serde(skip_serializing_if = "Option::is_none")]
     pub warning: Option<Warning>,
+    /// Edit that turns the request buffer into `text`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edit: Option<SuggestionEdit>,
+    /// Values the user still has to fill in (tab stops), in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub placeholders: Vec<Placeholder>,
+    /// Engine that produced the suggestion
+    #[serde(default)]
+    pub source: SuggestionSource,
+    /// Outcome of the checks run before the suggestion was returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<Validation>,
 }
 
 impl Suggestion {
@@ -137,6 +149,10 @@ impl Suggestion {
             reason_short: None,
             confidence: None,
             warning: None,
+            edit: None,
+            placeholders: Vec::new(),
+            source: SuggestionSource::Llm,
+            validation: None,
         }
     }
 
@@ -160,6 +176,157 @@ impl Suggestion {
         self.warning = Some(warning);
         self
     }
+
+    pub fn with_placeholders(mut self, placeholders: Vec<Placeholder>) -> Self {
+        self.placeholders = placeholders;
+        self
+    }
+
+    pub fn with_source(mut self, source: SuggestionSource) -> Self {
+        self.source = source;
+        self
+    }
+}
+
+/// Where a suggestion came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionSource {
+    /// Answer of the language model
+    #[default]
+    Llm,
+    /// Similar command from shell history, ranked next to the model answer
+    History,
+    /// Offline history predictor (prefix trie and n-gram model)
+    Predictor,
+}
+
+/// Result of validating a suggestion before it is returned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validation {
+    /// Whether every check passed
+    pub valid: bool,
+    /// Problems found (e.g. `unknown command 'gti'`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+/// Span of `Suggestion::text` the user is expected to replace.
+///
+/// The span holds `default` when the model proposed one, otherwise `name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placeholder {
+    /// What the value stands for (e.g. `message`, `pod`)
+    pub name: String,
+    /// Value proposed by the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Span in `text` in bytes
+    pub range: TextRange,
+    /// Span in `text` in characters
+    pub char_range: TextRange,
+}
+
+/// Replace `range` of the request buffer with `insert` to obtain the suggestion.
+///
+/// Offsets are given both in bytes (Rust, bash `READLINE_POINT`, C) and in
+/// characters (zsh `CURSOR`, PowerShell), so each caller can splice without
+/// re-deriving them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuggestionEdit {
+    /// Replaced range of the buffer in bytes
+    pub range: TextRange,
+    /// Replaced range of the buffer in characters
+    pub char_range: TextRange,
+    /// Text inserted in place of the range
+    pub insert: String,
+    /// Cursor position in the accepted command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<TextPosition>,
+}
+
+/// Half-open `[start, end)` range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Position as both a byte and a character offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPosition {
+    pub byte: usize,
+    pub char: usize,
+}
+
+impl SuggestionEdit {
+    /// Smallest single-range edit from `buffer` to `text`, or `None` if they are equal.
+    ///
+    /// The unchanged prefix never extends past `cursor`, so when the same
+    /// characters repeat around the cursor the edit lands where the user is
+    /// typing. The cursor is placed right after the inserted text.
+    pub fn between(buffer: &str, text: &str, cursor: usize) -> Option<Self> {
+        if buffer == text {
+            return None;
+        }
+
+        let cursor = floor_char_boundary(buffer, cursor);
+        let mut start = common_prefix_len(buffer, text).min(cursor);
+        start = floor_char_boundary(buffer, start);
+        let suffix = common_suffix_len(&buffer[start..], &text[start..]);
+
+        let end = buffer.len() - suffix;
+        let insert = &text[start..text.len() - suffix];
+        let start_char = buffer[..start].chars().count();
+        let end_char = start_char + buffer[start..end].chars().count();
+        let cursor_byte = start + insert.len();
+
+        Some(Self {
+            range: TextRange { start, end },
+            char_range: TextRange {
+                start: start_char,
+                end: end_char,
+            },
+            insert: insert.to_string(),
+            cursor: Some(TextPosition {
+                byte: cursor_byte,
+                char: start_char + insert.chars().count(),
+            }),
+        })
+    }
+
+    /// Apply the edit to `buffer` (byte offsets).
+    #[allow(dead_code)]
+    pub fn apply(&self, buffer: &str) -> Option<String> {
+        let head = buffer.get(..self.range.start)?;
+        let tail = buffer.get(self.range.end..)?;
+        Some(format!("{}{}{}", head, self.insert, tail))
+    }
+}
+
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, ca), cb)| ca != cb)
+        .map(|((idx, _), _)| idx)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(ca, cb)| ca == cb)
+        .map(|(ca, _)| ca.len_utf8())
+        .sum()
 }
 
 /// Warning about a potentially dangerous command
@@ -213,7 +380,8 @@ pub struct ErrorInfo {
     pub code: ErrorCode,
     /// Human-readable error message
     pub message: String,
-    /// Whether the error is recoverable (retry may succeed)
+    /// Whether the error is recoverable (retry may succeed). Rate-limit and
+    /// server errors stay recoverable after the daemon's own retries ran out.
     pub recoverable: bool,
 }
 
@@ -251,6 +419,14 @@ impl ErrorInfo {
     pub fn internal_error(msg: impl Into<String>) -> Self {
         Self::new(ErrorCode::InternalError, msg, false)
     }
+
+    pub fn budget_exceeded(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::BudgetExceeded, msg, false)
+    }
+
+    pub fn policy_refused(msg: impl Into<String>) -> Self {
+        Self::new(ErrorCode::PolicyRefused, msg, false)
+    }
 }
 
 /// Error code enumeration
@@ -262,6 +438,10 @@ pub enum ErrorCode {
     LlmTimeout,
     ConfigError,
     InternalError,
+    /// `usage.budget` is exhausted and new LLM requests are refused
+    BudgetExceeded,
+    /// The `privacy` policy refuses the request (`local_only`, `excluded_paths`)
+    PolicyRefused,
 }
 
 /// Summary of context used for completion (debugging)
@@ -285,6 +465,9 @@ pub struct ContextSummary {
     /// Number of sensitive items that were redacted
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sanitized_count: Option<usize>,
+    /// Redacted items per field (`history`, `plugins.<id>`, `buffer`, `prompt`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitized_fields: Option<BTreeMap<String, usize>>,
 }
 
 /// Context data from plugins (dynamic JSON structure)
@@ -336,6 +519,63 @@ impl DiagnosisRequest {
     }
 }
 
+/// Request for commands that perform a natural-language task description;
+/// answered with a [`CompletionResponse`] of ranked candidates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AskRequest {
+    /// Unique identifier for the shell session
+    pub session_id: String,
+    /// ISO 8601 timestamp when request was created
+    pub timestamp: DateTime<Utc>,
+    /// What the command should do, in natural language
+    pub query: String,
+    /// Current working directory absolute path
+    pub cwd: PathBuf,
+    /// Shell mode (zsh-inline, bash-popup, ps-inline, etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell_mode: Option<String>,
+}
+
+impl AskRequest {
+    pub fn new(session_id: String, query: String, cwd: PathBuf) -> Self {
+        Self {
+            session_id,
+            timestamp: Utc::now(),
+            query,
+            cwd,
+            shell_mode: None,
+        }
+    }
+}
+
+/// Request for a breakdown of what a command line does before it is run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainRequest {
+    /// Unique identifier for the shell session
+    pub session_id: String,
+    /// ISO 8601 timestamp when request was created
+    pub timestamp: DateTime<Utc>,
+    /// Full command line to explain
+    pub command: String,
+    /// Current working directory absolute path
+    pub cwd: PathBuf,
+    /// Shell mode (zsh-inline, bash-popup, ps-inline, etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell_mode: Option<String>,
+}
+
+impl ExplainRequest {
+    pub fn new(session_id: String, command: String, cwd: PathBuf) -> Self {
+        Self {
+            session_id,
+            timestamp: Utc::now(),
+            command,
+            cwd,
+            shell_mode: None,
+        }
+    }
+}
+
 /// Response with diagnosis and suggested fix
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiagnosisResponse {
@@ -384,3 +624,177 @@ impl DiagnosisResponse {
         }
     }
 }
+
+/// Breakdown of a command line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainResponse {
+    /// Unique identifier for this request
+    pub request_id: String,
+    /// What the command does as a whole
+    pub summary: String,
+    /// Words and operators of the command, in order
+    pub parts: Vec<CommandPart>,
+    /// Changes the command makes outside its own output (files, remotes, processes)
+    pub side_effects: Vec<String>,
+    /// Risk of running the command, assessed locally by the safety rules
+    pub risk: RiskAssessment,
+    /// Processing time in milliseconds
+    pub processing_time_ms: u64,
+    /// Error if the explanation failed; `parts` and `risk` are still filled in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+}
+
+impl ExplainResponse {
+    /// Breakdown without the LLM's summary and part explanations
+    pub fn local(request_id: String, parts: Vec<CommandPart>, risk: RiskAssessment) -> Self {
+        Self {
+            request_id,
+            summary: String::new(),
+            parts,
+            side_effects: Vec::new(),
+            risk,
+            processing_time_ms: 0,
+            error: None,
+        }
+    }
+
+    pub fn with_error(mut self, error: ErrorInfo) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+/// A word or operator of an explained command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPart {
+    /// Part as written, including quotes
+    pub text: String,
+    /// Span in the command in bytes
+    pub range: TextRange,
+    /// Span in the command in characters
+    pub char_range: TextRange,
+    /// Whether the part is a shell operator (`|`, `&&`, `>`, ...) rather than a word
+    pub operator: bool,
+    /// What this part does in the command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+/// How risky running a command is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskAssessment {
+    pub level: RiskLevel,
+    /// Rules that raised the level
+    pub reasons: Vec<String>,
+}
+
+/// Risk level, ordered from harmless to dangerous
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Reads state or makes easily undone changes
+    Low,
+    /// Deletes, overwrites or escalates privileges; check the arguments
+    Medium,
+    /// Matches a dangerous command pattern
+    High,
+}
+
+impl RiskLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+/// Request for daemon runtime status (LLM backends, circuit state)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusRequest {}
+
+/// Daemon runtime status
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusResponse {
+    /// Daemon process id
+    pub pid: u32,
+    /// Configured LLM backends in fallback order
+    pub backends: Vec<BackendStatus>,
+}
+
+impl StatusResponse {
+    /// Backend that served the most recent request (or will serve the next one)
+    pub fn active_backend(&self) -> Option<&BackendStatus> {
+        self.backends.iter().find(|backend| backend.active)
+    }
+}
+
+/// Health of a single LLM backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendStatus {
+    /// Model profile the backend belongs to
+    #[serde(default = "default_profile_name")]
+    pub profile: String,
+    /// Provider API flavor
+    pub provider: String,
+    /// Endpoint URL
+    pub endpoint: String,
+    /// Model name
+    pub model_name: String,
+    /// Circuit breaker state
+    pub circuit: CircuitState,
+    /// Consecutive failures recorded by the breaker
+    pub consecutive_failures: u32,
+    /// Time until an open circuit allows a half-open probe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_ms: Option<u64>,
+    /// Whether this backend is currently serving requests
+    pub active: bool,
+}
+
+impl BackendStatus {
+    /// One-line description for `nudge status` / `nudge info`
+    pub fn summary(&self) -> String {
+        let mut circuit = self.circuit.as_str().to_string();
+        if self.consecutive_failures > 0 {
+            circuit.push_str(&format!(", {} failures", self.consecutive_failures));
+        }
+        if let Some(retry_in_ms) = self.retry_in_ms {
+            circuit.push_str(&format!(", probe in {}s", retry_in_ms.div_ceil(1000)));
+        }
+        format!(
+            "{} [{}] {} {} @ {} [circuit: {}]",
+            if self.active { "*" } else { " " },
+            self.profile,
+            self.provider,
+            self.model_name,
+            self.endpoint,
+            circuit
+        )
+    }
+}
+
+fn default_profile_name() -> String {
+    "default".to_string()
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half-open",
+        }
+    }
+}
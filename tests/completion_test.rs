@@ -228,3 +228,75 @@ fn test_completion_response_serializes_cache_meta() {
     assert!(serialized.contains("cache_hit"));
     assert!(serialized.contains("cache_age_ms"));
 }
+
+#[test]
+fn test_suggestion_edit_for_prefix_completion() {
+    use nudge::protocol::{SuggestionEdit, TextPosition, TextRange};
+
+    let edit = SuggestionEdit::between("git st", "git status", 6).unwrap();
+    assert_eq!(edit.range, TextRange { start: 6, end: 6 });
+    assert_eq!(edit.insert, "atus");
+    assert_eq!(edit.cursor, Some(TextPosition { byte: 10, char: 10 }));
+    assert_eq!(edit.apply("git st").as_deref(), Some("git status"));
+
+    assert!(SuggestionEdit::between("ls", "ls", 2).is_none());
+}
+
+#[test]
+fn test_suggestion_edit_in_middle_uses_cursor_and_char_offsets() {
+    use nudge::protocol::{SuggestionEdit, TextRange};
+
+    // Repeated characters around the cursor: the edit lands at the cursor.
+    let edit = SuggestionEdit::between("echo aa", "echo aaa", 6).unwrap();
+    assert_eq!(edit.range, TextRange { start: 6, end: 6 });
+
+    // Multi-byte text before the change: byte and char offsets differ.
+    let buffer = "echo ünï | grep x";
+    let text = "echo ünï | grep -i x";
+    let edit = SuggestionEdit::between(buffer, text, 18).unwrap();
+    assert_eq!(edit.insert, "-i ");
+    assert_eq!(edit.range, TextRange { start: 18, end: 18 });
+    assert_eq!(edit.char_range, TextRange { start: 16, end: 16 });
+    assert_eq!(edit.cursor.unwrap().char, 19);
+    assert_eq!(edit.apply(buffer).as_deref(), Some(text));
+
+    // A rewrite replaces the differing span.
+    let edit = SuggestionEdit::between("gti status", "git status", 3).unwrap();
+    assert_eq!(edit.range, TextRange { start: 1, end: 3 });
+    assert_eq!(edit.insert, "it");
+}
+
+#[test]
+fn test_suggestion_edit_serializes_only_when_present() {
+    let plain = serde_json::to_value(Suggestion::new("ls".to_string())).unwrap();
+    assert!(plain.get("edit").is_none());
+
+    let mut suggestion = Suggestion::new("ls -la".to_string());
+    suggestion.edit = nudge::protocol::SuggestionEdit::between("ls", &suggestion.text, 2);
+    let with_edit = serde_json::to_value(suggestion).unwrap();
+    assert_eq!(with_edit["edit"]["range"]["start"], 2);
+    assert_eq!(with_edit["edit"]["insert"], " -la");
+    assert_eq!(with_edit["edit"]["cursor"]["char"], 6);
+}
+
+#[test]
+fn test_suggestion_placeholders_round_trip() {
+    use nudge::protocol::{Placeholder, TextRange};
+
+    let plain = serde_json::to_value(Suggestion::new("ls".to_string())).unwrap();
+    assert!(plain.get("placeholders").is_none());
+
+    let suggestion =
+        Suggestion::new("kubectl logs web-0".to_string()).with_placeholders(vec![Placeholder {
+            name: "pod".to_string(),
+            default: Some("web-0".to_string()),
+            range: TextRange { start: 13, end: 18 },
+            char_range: TextRange { start: 13, end: 18 },
+        }]);
+    let json = serde_json::to_string(&suggestion).unwrap();
+    let parsed: Suggestion = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.placeholders, suggestion.placeholders);
+
+    let legacy: Suggestion = serde_json::from_str(r#"{"text":"ls"}"#).unwrap();
+    assert!(legacy.placeholders.is_empty());
+}
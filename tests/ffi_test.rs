@@ -11,7 +11,8 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use nudge::ffi::{
-    nudge_complete, nudge_free, nudge_get_error, nudge_init, nudge_version, NudgeError,
+    nudge_complete, nudge_free, nudge_free_edit, nudge_get_edit, nudge_get_error, nudge_init,
+    nudge_version, NudgeEdit, NudgeError,
 };
 
 /// Test that init and free work correctly
@@ -318,3 +319,37 @@ fn test_get_error_null_context() {
         let _ = error_ptr;
     }
 }
+
+/// Test edit retrieval before any completion and with null pointers
+#[test]
+fn test_get_edit_without_completion() {
+    unsafe {
+        let mut edit = NudgeEdit {
+            replace_start: 0,
+            replace_end: 0,
+            replace_start_char: 0,
+            replace_end_char: 0,
+            cursor: 0,
+            cursor_char: 0,
+            insert: ptr::null_mut(),
+        };
+        assert_eq!(
+            nudge_get_edit(ptr::null_mut(), &mut edit),
+            NudgeError::NullPointer as i32
+        );
+
+        let ctx = nudge_init(ptr::null());
+        if ctx.is_null() {
+            return;
+        }
+        assert_eq!(
+            nudge_get_edit(ctx, ptr::null_mut()),
+            NudgeError::NullPointer as i32
+        );
+        assert_eq!(nudge_get_edit(ctx, &mut edit), 0);
+        assert!(edit.insert.is_null());
+        nudge_free_edit(&mut edit);
+        nudge_free_edit(ptr::null_mut());
+        nudge_free(ctx);
+    }
+}
@@ -0,0 +1,652 @@
+//! Integration tests for LLM provider backends against a local mock HTTP server.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{MockResponse, MockServer};
+use nudge::config::{ModelConfig, ModelProvider};
+use nudge::daemon::llm::provider::{
+    self, ChatRequest, ChatResponse, FimRequest, ProviderError, ResponseSchema,
+};
+
+fn first_line(text: &str) -> Option<usize> {
+    text.find('\n')
+}
+
+fn client(model: &ModelConfig) -> provider::HttpClients {
+    provider::HttpClients::new(&model.http).expect("client should build")
+}
+
+fn build(model: &ModelConfig) -> provider::SharedProvider {
+    provider::build_provider(model, client(model).for_endpoint(&model.endpoint))
+}
+
+fn model_config(provider: ModelProvider, endpoint: &str) -> ModelConfig {
+    ModelConfig {
+        provider,
+        endpoint: endpoint.to_string(),
+        model_name: "test-model".to_string(),
+        api_key: Some("test-key".to_string()),
+        ..ModelConfig::default()
+    }
+}
+
+async fn chat(model: &ModelConfig) -> Result<ChatResponse, ProviderError> {
+    let provider = build(model);
+    provider.chat(&chat_request(), Duration::from_secs(5)).await
+}
+
+fn fim_request() -> FimRequest {
+    FimRequest {
+        chat: chat_request(),
+        prefix: "git ".to_string(),
+        suffix: " origin main".to_string(),
+    }
+}
+
+fn chat_request() -> ChatRequest {
+    ChatRequest {
+        system: "You complete shell commands.".to_string(),
+        user: "git st".to_string(),
+        max_tokens: 64,
+        temperature: 0.3,
+        schema: None,
+    }
+}
+
+#[tokio::test]
+async fn anthropic_uses_messages_api_shape() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"git status"}],"stop_reason":"end_turn","usage":{"input_tokens":12,"output_tokens":3}}"#,
+    )])
+    .await;
+    let model = model_config(ModelProvider::Anthropic, &server.base_url);
+
+    let response = chat(&model)
+        .await
+        .expect("anthropic request should succeed");
+    assert_eq!(response.text, "git status");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/v1/messages");
+    assert_eq!(
+        request.headers.get("x-api-key").map(String::as_str),
+        Some("test-key")
+    );
+    assert!(request.headers.contains_key("anthropic-version"));
+    assert!(!request.headers.contains_key("authorization"));
+
+    let body = request.json();
+    assert_eq!(body["system"], "You complete shell commands.");
+    assert_eq!(body["messages"][0]["role"], "user");
+    assert_eq!(body["messages"][0]["content"], "git st");
+    assert_eq!(body["max_tokens"], 64);
+}
+
+#[tokio::test]
+async fn anthropic_overloaded_error_is_classified() {
+    let server = MockServer::start(vec![MockResponse::json(
+        529,
+        r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+    )
+    .with_header("retry-after", "2")])
+    .await;
+    let model = model_config(ModelProvider::Anthropic, &server.base_url);
+
+    let err = chat(&model)
+        .await
+        .expect_err("overloaded response should fail");
+    match err {
+        ProviderError::Overloaded {
+            status,
+            retry_after,
+            ..
+        } => {
+            assert_eq!(status, 529);
+            assert_eq!(retry_after, Some(Duration::from_secs(2)));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn openai_uses_chat_completions_with_bearer_auth() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"ls -la"}}]}"#,
+    )])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &format!("{}/v1", server.base_url));
+
+    let response = chat(&model).await.expect("openai request should succeed");
+    assert_eq!(response.text, "ls -la");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/chat/completions");
+    assert_eq!(
+        request.headers.get("authorization").map(String::as_str),
+        Some("Bearer test-key")
+    );
+    assert_eq!(request.json()["messages"][0]["role"], "system");
+}
+
+#[tokio::test]
+async fn schema_rejected_once_falls_back_to_unstructured_output() {
+    let server = MockServer::start(vec![
+        MockResponse::json(
+            400,
+            r#"{"error":{"message":"response_format json_schema is not supported"}}"#,
+        ),
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"index":0,"message":{"role":"assistant","content":"ls -la"}}]}"#,
+        ),
+    ])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &format!("{}/v1", server.base_url));
+    let provider = build(&model);
+    let request = ChatRequest {
+        schema: Some(ResponseSchema {
+            name: "command_completion",
+            description: "",
+            schema: serde_json::json!({"type": "object"}),
+        }),
+        ..chat_request()
+    };
+
+    for _ in 0..2 {
+        let response = provider
+            .chat(&request, Duration::from_secs(5))
+            .await
+            .expect("request without schema should succeed");
+        assert_eq!(response.text, "ls -la");
+        assert!(!response.structured);
+    }
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].json()["response_format"]["type"], "json_schema");
+    assert!(requests[1].json().get("response_format").is_none());
+    // The backend is not sent the schema again.
+    assert!(requests[2].json().get("response_format").is_none());
+}
+
+#[tokio::test]
+async fn anthropic_structured_output_uses_forced_tool() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"content":[{"type":"tool_use","id":"toolu_1","name":"command_completion","input":{"command":"git status"}}],"stop_reason":"tool_use"}"#,
+    )])
+    .await;
+    let model = model_config(ModelProvider::Anthropic, &server.base_url);
+    let provider = build(&model);
+    let request = ChatRequest {
+        schema: Some(ResponseSchema {
+            name: "command_completion",
+            description: "Return the completed shell command",
+            schema: serde_json::json!({"type": "object"}),
+        }),
+        ..chat_request()
+    };
+
+    let response = provider
+        .chat(&request, Duration::from_secs(5))
+        .await
+        .expect("structured request should succeed");
+    assert!(response.structured);
+    assert_eq!(response.text, r#"{"command":"git status"}"#);
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["tool_choice"]["name"], "command_completion");
+    assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+}
+
+#[tokio::test]
+async fn ollama_native_maps_not_found() {
+    let server = MockServer::start(vec![MockResponse::json(
+        404,
+        r#"{"error":"model 'test-model' not found, try pulling it first"}"#,
+    )])
+    .await;
+    let model = model_config(ModelProvider::Ollama, &format!("{}/v1", server.base_url));
+
+    let err = chat(&model).await.expect_err("missing model should fail");
+    assert!(matches!(err, ProviderError::NotFound { status: 404, .. }));
+    assert_eq!(server.requests()[0].path, "/api/chat");
+}
+
+#[tokio::test]
+async fn shared_provider_serves_sequential_requests() {
+    let server = MockServer::start(vec![
+        MockResponse::json(200, r#"{"choices":[{"message":{"content":"git status"}}]}"#),
+        MockResponse::json(200, r#"{"choices":[{"message":{"content":"git stash"}}]}"#),
+    ])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &server.base_url);
+    let provider = build(&model);
+
+    let first = provider
+        .chat(&chat_request(), Duration::from_secs(5))
+        .await
+        .expect("first request should succeed");
+    let second = provider
+        .chat(&chat_request(), Duration::from_millis(500))
+        .await
+        .expect("second request should succeed");
+
+    assert_eq!(first.text, "git status");
+    assert_eq!(second.text, "git stash");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn openai_stream_stops_at_cutoff() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"choices":[{"delta":{"role":"assistant"}}]}"#,
+        r#"{"choices":[{"delta":{"content":"git "}}]}"#,
+        r#"{"choices":[{"delta":{"content":"status\nThis shows"}}]}"#,
+        r#"{"choices":[{"delta":{"content":" the working tree"}}]}"#,
+        "[DONE]",
+    ])])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &server.base_url);
+    let provider = build(&model);
+
+    let response = provider
+        .chat_stream(&chat_request(), Duration::from_secs(5), &first_line)
+        .await
+        .expect("stream should succeed");
+
+    assert_eq!(response.text, "git status");
+    assert!(response.stopped_early);
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn anthropic_stream_collects_text_deltas() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"type":"message_start","message":{"id":"msg_1","content":[]}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"ls "}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"-la"}}"#,
+        r#"{"type":"content_block_stop","index":0}"#,
+        r#"{"type":"message_stop"}"#,
+    ])])
+    .await;
+    let model = model_config(ModelProvider::Anthropic, &server.base_url);
+    let provider = build(&model);
+
+    let response = provider
+        .chat_stream(&chat_request(), Duration::from_secs(5), &first_line)
+        .await
+        .expect("stream should succeed");
+
+    assert_eq!(response.text, "ls -la");
+    assert!(!response.stopped_early);
+}
+
+#[tokio::test]
+async fn ollama_stream_reads_ndjson() {
+    let server = MockServer::start(vec![MockResponse::ndjson(&[
+        r#"{"message":{"role":"assistant","content":"docker "},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":"ps"},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":""},"done":true}"#,
+    ])])
+    .await;
+    let model = model_config(ModelProvider::Ollama, &server.base_url);
+    let provider = build(&model);
+
+    let response = provider
+        .chat_stream(&chat_request(), Duration::from_secs(5), &first_line)
+        .await
+        .expect("stream should succeed");
+
+    assert_eq!(response.text, "docker ps");
+}
+
+#[tokio::test]
+async fn stream_falls_back_when_server_ignores_stream_flag() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"choices":[{"message":{"content":"make test"}}]}"#,
+    )])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &server.base_url);
+    let provider = build(&model);
+
+    let response = provider
+        .chat_stream(&chat_request(), Duration::from_secs(5), &first_line)
+        .await
+        .expect("non-streamed body should still parse");
+
+    assert_eq!(response.text, "make test");
+}
+
+#[tokio::test]
+async fn chain_falls_back_and_opens_circuit() {
+    use nudge::config::{CircuitBreakerConfig, FallbackModelConfig};
+    use nudge::daemon::llm::chain::ProviderChain;
+    use nudge::protocol::CircuitState;
+
+    let primary = MockServer::start(vec![MockResponse::json(
+        500,
+        r#"{"error":{"message":"internal error"}}"#,
+    )])
+    .await;
+    let fallback = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"type":"message","content":[{"type":"text","text":"git status"}]}"#,
+    )])
+    .await;
+
+    let mut model = model_config(ModelProvider::OpenAi, &primary.base_url);
+    model.circuit_breaker = CircuitBreakerConfig {
+        failure_threshold: 1,
+        cooldown_ms: 60_000,
+    };
+    model.fallbacks.push(FallbackModelConfig {
+        provider: ModelProvider::Anthropic,
+        endpoint: fallback.base_url.clone(),
+        model_name: "claude-3-5-haiku-latest".to_string(),
+        api_key: Some("fallback-key".to_string()),
+        ..FallbackModelConfig::default()
+    });
+    let chain = ProviderChain::with_client("default", &model, &client(&model));
+
+    for _ in 0..2 {
+        let response = chain
+            .chat(&chat_request(), Duration::from_secs(5))
+            .await
+            .expect("fallback should answer");
+        assert_eq!(response.text, "git status");
+    }
+
+    // Primary was tried once, then skipped while its circuit is open.
+    assert_eq!(primary.requests().len(), 1);
+    assert_eq!(fallback.requests().len(), 2);
+
+    let status = chain.status();
+    assert_eq!(status[0].circuit, CircuitState::Open);
+    assert!(status[0].retry_in_ms.is_some());
+    assert!(!status[0].active);
+    assert_eq!(status[1].circuit, CircuitState::Closed);
+    assert!(status[1].active);
+}
+
+#[tokio::test]
+async fn chain_shares_one_deadline_across_backends() {
+    use nudge::config::FallbackModelConfig;
+    use nudge::daemon::llm::chain::ProviderChain;
+
+    let body = r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#;
+    let slow = |body| MockResponse::json(200, body).with_delay(Duration::from_secs(2));
+    let primary = MockServer::start(vec![slow(body)]).await;
+    let fallback = MockServer::start(vec![slow(body)]).await;
+
+    let mut model = model_config(ModelProvider::OpenAi, &primary.base_url);
+    model.fallbacks.push(FallbackModelConfig {
+        provider: ModelProvider::OpenAi,
+        endpoint: fallback.base_url.clone(),
+        model_name: "fallback-model".to_string(),
+        ..FallbackModelConfig::default()
+    });
+    let chain = ProviderChain::with_client("default", &model, &client(&model));
+
+    let started = std::time::Instant::now();
+    let err = chain
+        .chat(&chat_request(), Duration::from_millis(300))
+        .await
+        .expect_err("both backends are too slow");
+    assert!(matches!(err.error, ProviderError::Timeout), "{:?}", err);
+    // One timeout for the whole chain, not one per backend
+    assert!(started.elapsed() < Duration::from_millis(550));
+}
+
+#[tokio::test]
+async fn rate_limits_do_not_open_the_circuit() {
+    use nudge::config::CircuitBreakerConfig;
+    use nudge::daemon::llm::chain::ProviderChain;
+    use nudge::protocol::CircuitState;
+
+    let server = MockServer::start(vec![
+        MockResponse::json(429, r#"{"error":{"message":"slow down"}}"#)
+            .with_header("retry-after", "30"),
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#,
+        ),
+    ])
+    .await;
+    let mut model = model_config(ModelProvider::OpenAi, &server.base_url);
+    model.circuit_breaker = CircuitBreakerConfig {
+        failure_threshold: 1,
+        cooldown_ms: 60_000,
+    };
+    let chain = ProviderChain::with_client("default", &model, &client(&model));
+
+    let err = chain
+        .chat(&chat_request(), Duration::from_secs(5))
+        .await
+        .expect_err("rate limit should fail the request");
+    assert!(matches!(err.error, ProviderError::RateLimited { .. }));
+    assert_eq!(err.backend, Some(0));
+    assert_eq!(chain.status()[0].circuit, CircuitState::Closed);
+
+    let response = chain
+        .chat(&chat_request(), Duration::from_secs(5))
+        .await
+        .expect("backend should still be tried");
+    assert_eq!(response.text, "git status");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn chain_reports_circuit_open_when_no_backend_admitted() {
+    use nudge::config::CircuitBreakerConfig;
+    use nudge::daemon::llm::chain::ProviderChain;
+
+    let server = MockServer::start(vec![MockResponse::json(500, "{}")]).await;
+    let mut model = model_config(ModelProvider::OpenAi, &server.base_url);
+    model.circuit_breaker = CircuitBreakerConfig {
+        failure_threshold: 1,
+        cooldown_ms: 60_000,
+    };
+    let chain = ProviderChain::with_client("default", &model, &client(&model));
+
+    let first = chain
+        .chat(&chat_request(), Duration::from_secs(5))
+        .await
+        .expect_err("server error should fail");
+    assert!(matches!(
+        first.error,
+        ProviderError::Server { status: 500, .. }
+    ));
+    assert_eq!(first.backend, Some(0));
+
+    let second = chain
+        .chat(&chat_request(), Duration::from_secs(5))
+        .await
+        .expect_err("open circuit should fail fast");
+    assert!(matches!(second.error, ProviderError::CircuitOpen { .. }));
+    assert_eq!(second.backend, None);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn http_settings_apply_proxy_and_static_headers() {
+    let proxy = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#,
+    )])
+    .await;
+    let mut model = model_config(ModelProvider::OpenAi, "http://llm.corp.invalid/v1");
+    model.http.proxy = Some(proxy.base_url.clone());
+    model
+        .http
+        .headers
+        .insert("X-Org-Id".to_string(), "acme".to_string());
+
+    let response = chat(&model).await.expect("request should go through proxy");
+    assert_eq!(response.text, "git status");
+
+    let requests = proxy.requests();
+    assert_eq!(requests.len(), 1);
+    // Plain-HTTP proxies receive the absolute target URL.
+    assert_eq!(
+        requests[0].path,
+        "http://llm.corp.invalid/v1/chat/completions"
+    );
+    assert_eq!(
+        requests[0].headers.get("x-org-id").map(String::as_str),
+        Some("acme")
+    );
+    assert_eq!(
+        requests[0].headers.get("authorization").map(String::as_str),
+        Some("Bearer test-key")
+    );
+}
+
+#[tokio::test]
+async fn loopback_endpoints_bypass_the_proxy() {
+    let body = r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#;
+    let server = MockServer::start(vec![MockResponse::json(200, body)]).await;
+    let proxy = MockServer::start(vec![MockResponse::json(200, body)]).await;
+    let mut model = model_config(ModelProvider::OpenAi, &server.base_url);
+    model.http.proxy = Some(proxy.base_url.clone());
+
+    let response = chat(&model).await.expect("request should go direct");
+    assert_eq!(response.text, "git status");
+    assert_eq!(server.requests().len(), 1);
+    assert!(proxy.requests().is_empty());
+}
+
+#[tokio::test]
+async fn retry_honors_retry_after_within_deadline() {
+    use nudge::config::RetryConfig;
+    use nudge::daemon::llm::retry::{self, RetryPolicy};
+
+    let server = MockServer::start(vec![
+        MockResponse::json(429, r#"{"error":{"message":"slow down"}}"#)
+            .with_header("retry-after", "0"),
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"git status"}}]}"#,
+        ),
+    ])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &server.base_url);
+    let provider = build(&model);
+    let policy = RetryPolicy::new(&RetryConfig::default());
+    let request = chat_request();
+
+    let response = retry::run(&policy, Duration::from_secs(5), |timeout| {
+        provider.chat(&request, timeout)
+    })
+    .await
+    .expect("retry should succeed");
+    assert_eq!(response.text, "git status");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn retry_stops_when_wait_passes_deadline_or_is_disabled() {
+    use nudge::config::RetryConfig;
+    use nudge::daemon::llm::retry::{self, RetryPolicy};
+
+    let server = MockServer::start(vec![
+        MockResponse::json(503, "{}").with_header("retry-after", "30")
+    ])
+    .await;
+    let model = model_config(ModelProvider::OpenAi, &server.base_url);
+    let provider = build(&model);
+
+    let policy = RetryPolicy::new(&RetryConfig::default());
+    let request = chat_request();
+    let err = retry::run(&policy, Duration::from_secs(5), |timeout| {
+        provider.chat(&request, timeout)
+    })
+    .await
+    .expect_err("retry-after beyond the deadline should give up");
+    assert!(err.is_retryable());
+    assert_eq!(server.requests().len(), 1);
+
+    let err = retry::run(
+        &RetryPolicy::disabled(),
+        Duration::from_secs(5),
+        |timeout| provider.chat(&request, timeout),
+    )
+    .await
+    .expect_err("disabled policy should not retry");
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn openai_native_fim_uses_completions_suffix() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"choices":[{"index":0,"text":"push"}]}"#,
+    )])
+    .await;
+    let mut model = model_config(ModelProvider::OpenAi, &format!("{}/v1", server.base_url));
+    model.fim.native = true;
+    let provider = build(&model);
+
+    let response = provider
+        .fill_in_middle(&fim_request(), Duration::from_secs(5))
+        .await
+        .expect("fim request should succeed");
+    assert_eq!(response.text, "push");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/completions");
+    let body = request.json();
+    assert_eq!(body["prompt"], "git ");
+    assert_eq!(body["suffix"], " origin main");
+}
+
+#[tokio::test]
+async fn ollama_native_fim_uses_generate_suffix() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"model":"test-model","response":"push","done":true}"#,
+    )])
+    .await;
+    let mut model = model_config(ModelProvider::Ollama, &format!("{}/v1", server.base_url));
+    model.fim.native = true;
+    let provider = build(&model);
+
+    let response = provider
+        .fill_in_middle(&fim_request(), Duration::from_secs(5))
+        .await
+        .expect("fim request should succeed");
+    assert_eq!(response.text, "push");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/generate");
+    assert_eq!(request.json()["suffix"], " origin main");
+}
+
+#[tokio::test]
+async fn fim_without_native_endpoint_uses_chat_prompt() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"type":"message","content":[{"type":"text","text":"{\"insertion\":\"push\"}"}]}"#,
+    )])
+    .await;
+    let mut model = model_config(ModelProvider::Anthropic, &server.base_url);
+    model.fim.native = true;
+    let provider = build(&model);
+
+    let response = provider
+        .fill_in_middle(&fim_request(), Duration::from_secs(5))
+        .await
+        .expect("fim request should succeed");
+    assert_eq!(response.text, r#"{"insertion":"push"}"#);
+    assert_eq!(server.requests()[0].path, "/v1/messages");
+}